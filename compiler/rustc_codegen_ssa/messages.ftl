@@ -213,6 +213,8 @@ codegen_ssa_multiple_external_func_decl = multiple declarations of external func
 codegen_ssa_multiple_main_functions = entry symbol `main` declared multiple times
     .help = did you use `#[no_mangle]` on `fn main`? Use `#[start]` instead
 
+codegen_ssa_mz_conversion_failed = failed to convert `{$path}` into an MZ executable: {$error}
+
 codegen_ssa_no_field = no field `{$name}`
 
 codegen_ssa_no_module_named =
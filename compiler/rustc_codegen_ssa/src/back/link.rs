@@ -50,7 +50,7 @@
 use super::linker::{self, Linker};
 use super::metadata::{MetadataPosition, create_wrapper_file};
 use super::rpath::{self, RPathConfig};
-use super::{apple, versioned_llvm_target};
+use super::{apple, mz, versioned_llvm_target};
 use crate::{
     CodegenResults, CompiledModule, CrateInfo, NativeLib, common, errors,
     looks_like_rust_object_file,
@@ -1149,6 +1149,12 @@ fn is_illegal_instruction(_status: &ExitStatus) -> bool {
         }
     }
 
+    // DOS can only load MZ executables, so the linked ELF image is converted
+    // after everything else that still needs to look at it has run.
+    if sess.target.is_like_msdos && crate_type == CrateType::Executable {
+        mz::convert_to_mz(sess, out_filename);
+    }
+
     if should_archive {
         let mut ab = archive_builder_builder.new_archive_builder(sess);
         ab.add_file(temp_filename);
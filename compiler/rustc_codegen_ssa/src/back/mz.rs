@@ -0,0 +1,299 @@
+//! Conversion of linked ELF images into DOS MZ executables.
+//!
+//! DOS cannot load ELF files, so for targets with `is_like_msdos` set the
+//! linker's output is flattened and wrapped into an MZ executable once linking
+//! is done. The load module of that executable consists of a small real-mode
+//! stub followed by the flat image:
+//!
+//! ```text
+//! +-------------+--------------+-------------------------+------------------+
+//! | MZ header + | real-mode    | flat image (file bytes  | .bss and stack   |
+//! | relocations | stub + stack | of every PT_LOAD)       | (`e_minalloc`)   |
+//! +-------------+--------------+-------------------------+------------------+
+//! ```
+//!
+//! The stub looks for a 32-bit DPMI host, switches to protected mode, creates a
+//! flat code and data descriptor whose base maps the image's link address to
+//! the place DOS loaded it, and then jumps to the ELF entry point (`_start`)
+//! with the following register state:
+//!
+//! - `CS`: 32-bit code selector with a 4 GiB limit,
+//! - `AX`: the matching 32-bit data selector,
+//! - `ES`: the PSP selector handed out by the DPMI host,
+//! - `EBX`: the base of both descriptors, i.e. the linear address of link address 0,
+//! - `ESI`: the real-mode segment of the PSP.
+//!
+//! `DS`, `SS` and `ESP` still refer to the stub and must be reloaded before use.
+//!
+//! The only entry in the relocation table patches the segment of the flat image
+//! into the stub, so DOS does the work of finding out where we were loaded.
+
+use std::fs;
+use std::path::Path;
+
+use object::read::elf::{FileHeader, ProgramHeader};
+use object::{LittleEndian, elf};
+use rustc_session::Session;
+
+use crate::errors;
+
+#[cfg(test)]
+mod tests;
+
+/// Size of the MZ header including the single relocation entry, rounded up to
+/// a whole paragraph.
+const HEADER_SIZE: usize = 32;
+/// Offset of the relocation table within the MZ header.
+const RELOCATION_TABLE_OFFSET: u16 = 0x1c;
+
+/// Machine code of the real-mode stub, assembled from the following source
+/// with `as --32` and linked at offset 0:
+///
+/// ```text
+///     .code16
+///     .intel_syntax noprefix
+/// start:
+///     push cs
+///     pop ds
+///     mov [psp_seg], es
+///     mov ax, 0x1687              ; DPMI installation check
+///     int 0x2f
+///     test ax, ax
+///     jnz no_dpmi
+///     test bl, 1                  ; 32-bit clients supported?
+///     jz no_dpmi
+///     mov [dpmi_entry], di
+///     mov [dpmi_entry + 2], es
+///     mov bx, si                  ; paragraphs of host private data
+///     test bx, bx
+///     jz 1f
+///     mov ah, 0x48
+///     int 0x21
+///     jc no_mem
+///     mov es, ax
+/// 1:
+///     mov ax, 1                   ; enter protected mode as a 32-bit client
+///     call dword ptr [dpmi_entry]
+///     jc no_pm
+///
+///     xor ax, ax                  ; allocate two LDT descriptors
+///     mov cx, 2
+///     int 0x31
+///     jc pm_fail
+///     mov [code_sel], ax
+///     mov bx, ax
+///     mov ax, 3                   ; get selector increment
+///     int 0x31
+///     add ax, bx
+///     mov [data_sel], ax
+///
+///     movzx eax, word ptr [payload_seg]
+///     shl eax, 4
+///     sub eax, [link_base]
+///     mov [image_base], eax
+///
+///     mov di, cs                  ; descriptors must use our privilege level
+///     and di, 3
+///     shl di, 5
+///     mov bx, [code_sel]
+///     mov cx, 0xc09a              ; 32-bit, page granular, readable code
+///     or cx, di
+///     call setup_desc
+///     jc pm_fail
+///     mov bx, [data_sel]
+///     mov cx, 0xc092              ; 32-bit, page granular, writable data
+///     or cx, di
+///     call setup_desc
+///     jc pm_fail
+///
+///     mov ax, [data_sel]
+///     mov ebx, [image_base]
+///     movzx esi, word ptr [psp_seg]
+///     jmp fword ptr [entry]
+///
+/// setup_desc:                     ; BX = selector, CX = access rights
+///     push cx
+///     mov ax, 7                   ; set segment base address
+///     mov dx, [image_base]
+///     mov cx, [image_base + 2]
+///     int 0x31
+///     jc 2f
+///     mov ax, 8                   ; set segment limit to 4 GiB
+///     mov cx, 0xffff
+///     mov dx, cx
+///     int 0x31
+///     jc 2f
+///     pop cx
+///     mov ax, 9                   ; set access rights
+///     int 0x31
+///     ret
+/// 2:
+///     pop cx
+///     ret
+///
+/// no_dpmi:
+///     mov dx, offset msg_no_dpmi
+///     jmp rm_fail
+/// no_mem:
+///     mov dx, offset msg_no_mem
+///     jmp rm_fail
+/// no_pm:
+///     mov dx, offset msg_no_pm
+/// rm_fail:
+///     mov ah, 9
+///     int 0x21
+/// pm_fail:
+///     mov ax, 0x4cff
+///     int 0x21
+///
+/// msg_no_dpmi: .ascii "no 32-bit DPMI host found\r\n$"
+/// msg_no_mem: .ascii "not enough memory for DPMI host\r\n$"
+/// msg_no_pm: .ascii "cannot switch to protected mode\r\n$"
+/// ```
+///
+/// It is followed by the variables described by the `STUB_*` offsets below and
+/// by the stack the stub runs on.
+#[rustfmt::skip]
+const STUB_CODE: [u8; 0x13c] = [
+    0x0e, 0x1f, 0x8c, 0x06, 0x50, 0x01, 0xb8, 0x87, 0x16, 0xcd, 0x2f, 0x85, 0xc0, 0x0f, 0x85, 0xb3,
+    0x00, 0xf6, 0xc3, 0x01, 0x0f, 0x84, 0xac, 0x00, 0x89, 0x3e, 0x4c, 0x01, 0x8c, 0x06, 0x4e, 0x01,
+    0x89, 0xf3, 0x85, 0xdb, 0x74, 0x0a, 0xb4, 0x48, 0xcd, 0x21, 0x0f, 0x82, 0x9b, 0x00, 0x8e, 0xc0,
+    0xb8, 0x01, 0x00, 0xff, 0x1e, 0x4c, 0x01, 0x0f, 0x82, 0x93, 0x00, 0x31, 0xc0, 0xb9, 0x02, 0x00,
+    0xcd, 0x31, 0x0f, 0x82, 0x8f, 0x00, 0xa3, 0x40, 0x01, 0x89, 0xc3, 0xb8, 0x03, 0x00, 0xcd, 0x31,
+    0x01, 0xd8, 0xa3, 0x42, 0x01, 0x66, 0x0f, 0xb7, 0x06, 0x52, 0x01, 0x66, 0xc1, 0xe0, 0x04, 0x66,
+    0x2b, 0x06, 0x44, 0x01, 0x66, 0xa3, 0x48, 0x01, 0x8c, 0xcf, 0x83, 0xe7, 0x03, 0xc1, 0xe7, 0x05,
+    0x8b, 0x1e, 0x40, 0x01, 0xb9, 0x9a, 0xc0, 0x09, 0xf9, 0xe8, 0x23, 0x00, 0x72, 0x57, 0x8b, 0x1e,
+    0x42, 0x01, 0xb9, 0x92, 0xc0, 0x09, 0xf9, 0xe8, 0x15, 0x00, 0x72, 0x49, 0xa1, 0x42, 0x01, 0x66,
+    0x8b, 0x1e, 0x48, 0x01, 0x66, 0x0f, 0xb7, 0x36, 0x50, 0x01, 0x66, 0xff, 0x2e, 0x3c, 0x01, 0x51,
+    0xb8, 0x07, 0x00, 0x8b, 0x16, 0x48, 0x01, 0x8b, 0x0e, 0x4a, 0x01, 0xcd, 0x31, 0x72, 0x13, 0xb8,
+    0x08, 0x00, 0xb9, 0xff, 0xff, 0x89, 0xca, 0xcd, 0x31, 0x72, 0x07, 0x59, 0xb8, 0x09, 0x00, 0xcd,
+    0x31, 0xc3, 0x59, 0xc3, 0xba, 0xda, 0x00, 0xeb, 0x08, 0xba, 0xf6, 0x00, 0xeb, 0x03, 0xba, 0x18,
+    0x01, 0xb4, 0x09, 0xcd, 0x21, 0xb8, 0xff, 0x4c, 0xcd, 0x21, 0x6e, 0x6f, 0x20, 0x33, 0x32, 0x2d,
+    0x62, 0x69, 0x74, 0x20, 0x44, 0x50, 0x4d, 0x49, 0x20, 0x68, 0x6f, 0x73, 0x74, 0x20, 0x66, 0x6f,
+    0x75, 0x6e, 0x64, 0x0d, 0x0a, 0x24, 0x6e, 0x6f, 0x74, 0x20, 0x65, 0x6e, 0x6f, 0x75, 0x67, 0x68,
+    0x20, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x20, 0x66, 0x6f, 0x72, 0x20, 0x44, 0x50, 0x4d, 0x49,
+    0x20, 0x68, 0x6f, 0x73, 0x74, 0x0d, 0x0a, 0x24, 0x63, 0x61, 0x6e, 0x6e, 0x6f, 0x74, 0x20, 0x73,
+    0x77, 0x69, 0x74, 0x63, 0x68, 0x20, 0x74, 0x6f, 0x20, 0x70, 0x72, 0x6f, 0x74, 0x65, 0x63, 0x74,
+    0x65, 0x64, 0x20, 0x6d, 0x6f, 0x64, 0x65, 0x0d, 0x0a, 0x24, 0x66, 0x90,
+];
+
+/// `entry`: 32-bit offset of the ELF entry point, directly followed by `code_sel`
+/// so that the two form the `m16:32` operand of the final far jump.
+const STUB_ENTRY: usize = 0x13c;
+/// `link_base`: lowest virtual address of the image.
+const STUB_LINK_BASE: usize = 0x144;
+/// `payload_seg`: paragraph offset of the flat image within the load module,
+/// turned into an absolute segment by the relocation table.
+const STUB_PAYLOAD_SEG: usize = 0x152;
+/// Total size of the stub, including its 512-byte stack. The initial `SS:SP`
+/// points at the end of it.
+const STUB_SIZE: usize = 0x360;
+
+/// The loadable part of a statically linked ELF executable, laid out the way
+/// it appears in memory.
+#[derive(Debug)]
+pub(super) struct FlatImage {
+    /// Virtual address of the first byte of `data`.
+    link_base: u32,
+    /// Virtual address of the entry point.
+    entry: u32,
+    /// Initialized contents of the image.
+    data: Vec<u8>,
+    /// Size of the image in memory, including zero-initialized data and the stack.
+    mem_size: u32,
+}
+
+impl FlatImage {
+    pub(super) fn from_elf(data: &[u8]) -> Result<FlatImage, String> {
+        let header = elf::FileHeader32::<LittleEndian>::parse(data).map_err(|e| e.to_string())?;
+        let endian = header.endian().map_err(|e| e.to_string())?;
+        if header.e_machine(endian) != elf::EM_386 {
+            return Err("not an i386 ELF file".to_string());
+        }
+        if header.e_type(endian) != elf::ET_EXEC {
+            return Err("not a statically linked executable".to_string());
+        }
+
+        let segments = header.program_headers(endian, data).map_err(|e| e.to_string())?;
+        let loadable = || {
+            segments.iter().filter(|ph| ph.p_type(endian) == elf::PT_LOAD && ph.p_memsz(endian) > 0)
+        };
+        let Some(link_base) = loadable().map(|ph| ph.p_vaddr(endian)).min() else {
+            return Err("executable has no loadable segments".to_string());
+        };
+        let mut file_size = 0;
+        let mut mem_size = 0;
+        for ph in loadable() {
+            let offset = ph.p_vaddr(endian) - link_base;
+            if ph.p_filesz(endian) > 0 {
+                file_size = file_size.max(offset + ph.p_filesz(endian));
+            }
+            mem_size = mem_size.max(offset + ph.p_memsz(endian));
+        }
+
+        let mut image = vec![0; file_size as usize];
+        for ph in loadable() {
+            let bytes = ph.data(endian, data).map_err(|()| "segment data out of bounds")?;
+            let offset = (ph.p_vaddr(endian) - link_base) as usize;
+            image[offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+
+        Ok(FlatImage { link_base, entry: header.e_entry(endian), data: image, mem_size })
+    }
+
+    pub(super) fn to_mz(&self) -> Result<Vec<u8>, String> {
+        let load_module_size = STUB_SIZE + self.data.len();
+        let file_size = HEADER_SIZE + load_module_size;
+        let mem_size = STUB_SIZE + self.mem_size as usize;
+        let pages = u16::try_from(file_size.div_ceil(512))
+            .map_err(|_| "image is too large for an MZ executable")?;
+        // Everything past the end of the file has to come from the extra
+        // memory DOS allocates behind the load module.
+        let min_alloc = u16::try_from(mem_size.saturating_sub(load_module_size).div_ceil(16))
+            .map_err(|_| "image does not fit into conventional memory")?;
+
+        let mut out = Vec::with_capacity(file_size);
+        let mut push = |value: u16| out.extend_from_slice(&value.to_le_bytes());
+        push(u16::from_le_bytes(*b"MZ"));
+        push((file_size % 512) as u16); // e_cblp
+        push(pages); // e_cp
+        push(1); // e_crlc
+        push((HEADER_SIZE / 16) as u16); // e_cparhdr
+        push(min_alloc); // e_minalloc
+        // Asking for exactly as much as we need leaves the rest of
+        // conventional memory to the DPMI host and to child processes.
+        push(min_alloc); // e_maxalloc
+        push(0); // e_ss
+        push(STUB_SIZE as u16); // e_sp
+        push(0); // e_csum
+        push(0); // e_ip
+        push(0); // e_cs
+        push(RELOCATION_TABLE_OFFSET); // e_lfarlc
+        push(0); // e_ovno
+        push(STUB_PAYLOAD_SEG as u16); // relocation: offset
+        push(0); // relocation: segment
+        out.resize(HEADER_SIZE, 0);
+
+        let mut stub = [0; STUB_SIZE];
+        stub[..STUB_CODE.len()].copy_from_slice(&STUB_CODE);
+        stub[STUB_ENTRY..][..4].copy_from_slice(&self.entry.to_le_bytes());
+        stub[STUB_LINK_BASE..][..4].copy_from_slice(&self.link_base.to_le_bytes());
+        stub[STUB_PAYLOAD_SEG..][..2].copy_from_slice(&((STUB_SIZE / 16) as u16).to_le_bytes());
+        out.extend_from_slice(&stub);
+        out.extend_from_slice(&self.data);
+        Ok(out)
+    }
+}
+
+/// Replaces the ELF executable at `out_filename` with an equivalent MZ executable.
+pub(super) fn convert_to_mz(sess: &Session, out_filename: &Path) {
+    let result = fs::read(out_filename)
+        .map_err(|e| e.to_string())
+        .and_then(|data| FlatImage::from_elf(&data))
+        .and_then(|image| image.to_mz())
+        .and_then(|mz| fs::write(out_filename, mz).map_err(|e| e.to_string()));
+    if let Err(error) = result {
+        sess.dcx()
+            .emit_fatal(errors::MzConversionFailed { path: out_filename.to_path_buf(), error });
+    }
+}
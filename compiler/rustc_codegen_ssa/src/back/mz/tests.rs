@@ -0,0 +1,47 @@
+use super::*;
+
+fn image(data_len: usize, mem_size: u32) -> FlatImage {
+    FlatImage { link_base: 0x1000, entry: 0x1234, data: vec![0xcc; data_len], mem_size }
+}
+
+fn word(mz: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([mz[offset], mz[offset + 1]])
+}
+
+#[test]
+fn header_layout() {
+    let mz = image(700, 0x2000).to_mz().unwrap();
+    let file_size = HEADER_SIZE + STUB_SIZE + 700;
+    assert_eq!(mz.len(), file_size);
+    assert_eq!(&mz[..2], b"MZ");
+    assert_eq!(word(&mz, 0x02), (file_size % 512) as u16);
+    assert_eq!(word(&mz, 0x04), file_size.div_ceil(512) as u16);
+    assert_eq!(word(&mz, 0x06), 1);
+    assert_eq!(word(&mz, 0x08), (HEADER_SIZE / 16) as u16);
+    assert_eq!(word(&mz, 0x0a), (0x2000 - 700usize).div_ceil(16) as u16);
+    assert_eq!(word(&mz, 0x0c), word(&mz, 0x0a));
+    assert_eq!(word(&mz, 0x10), STUB_SIZE as u16);
+    assert_eq!(word(&mz, 0x18), RELOCATION_TABLE_OFFSET);
+    assert_eq!(word(&mz, RELOCATION_TABLE_OFFSET as usize), STUB_PAYLOAD_SEG as u16);
+}
+
+#[test]
+fn stub_is_patched() {
+    let mz = image(16, 16).to_mz().unwrap();
+    let stub = &mz[HEADER_SIZE..][..STUB_SIZE];
+    assert_eq!(&stub[..STUB_CODE.len()], &STUB_CODE[..]);
+    assert_eq!(&stub[STUB_ENTRY..][..4], &0x1234u32.to_le_bytes());
+    assert_eq!(&stub[STUB_LINK_BASE..][..4], &0x1000u32.to_le_bytes());
+    assert_eq!(word(stub, STUB_PAYLOAD_SEG), (STUB_SIZE / 16) as u16);
+    assert_eq!(&mz[HEADER_SIZE + STUB_SIZE..], &[0xcc; 16]);
+}
+
+#[test]
+fn image_larger_than_conventional_memory() {
+    assert!(image(16, 0x20_0000).to_mz().is_err());
+}
+
+#[test]
+fn not_an_elf_file() {
+    assert!(FlatImage::from_elf(b"MZ\x90\x00").is_err());
+}
@@ -499,6 +499,13 @@ pub(crate) struct ProcessingDymutilFailed {
     pub output: String,
 }
 
+#[derive(Diagnostic)]
+#[diag(codegen_ssa_mz_conversion_failed)]
+pub(crate) struct MzConversionFailed {
+    pub path: PathBuf,
+    pub error: String,
+}
+
 #[derive(Diagnostic)]
 #[diag(codegen_ssa_unable_to_run_dsymutil)]
 pub(crate) struct UnableToRunDsymutil {
@@ -1,15 +1,21 @@
-//use crate::spec::crt_objects;
-use crate::spec::{Cc, LinkerFlavor, Lld, RelocModel, StackProbeType, TargetOptions};
+use crate::spec::{crt_objects, Cc, LinkerFlavor, Lld, RelocModel, StackProbeType, TargetOptions};
 
 pub(crate) fn opts() -> TargetOptions {
+    // The image is flattened into an MZ executable after linking, so don't
+    // waste conventional memory on page-aligning the segments.
+    let pre_link_args = TargetOptions::link_args(LinkerFlavor::Gnu(Cc::No, Lld::No), &["--nmagic"]);
+
     TargetOptions {
         os: "msdos6".into(), // omg i thought this was like a flavor text thing when i wrote it so i set it to "MS-DOS". its used for target_os. future onnie pls dont touch this
         linker: Some("rust-lld".into()),
         linker_flavor: LinkerFlavor::Gnu(Cc::No, Lld::Yes),
+        pre_link_args,
         stack_probes: StackProbeType::Inline,
         relocation_model: RelocModel::Static,
-        //pre_link_objects: crt_objects::pre_msdos6(),
-        //post_link_objects: crt_objects::post_msdos6(),
+        exe_suffix: ".exe".into(),
+        is_like_msdos: true,
+        pre_link_objects: crt_objects::pre_msdos6(),
+        post_link_objects: crt_objects::post_msdos6(),
         ..Default::default()
     }
 }
@@ -123,12 +123,10 @@ pub(super) fn post_wasi_self_contained() -> CrtObjects {
     new(&[])
 }
 
-/*
 pub(super) fn pre_msdos6() -> CrtObjects {
-    all(todo!())
+    all("crt0_msdos6.o")
 }
 
 pub(super) fn post_msdos6() -> CrtObjects {
-    new(&[])
+    all("crtend_msdos6.o")
 }
-*/
@@ -2326,6 +2326,9 @@ pub struct TargetOptions {
     pub is_like_msvc: bool,
     /// Whether a target toolchain is like WASM.
     pub is_like_wasm: bool,
+    /// Whether the target is like MS-DOS. Executables are linked as a flat 32-bit image
+    /// and then wrapped into an MZ executable with a DPMI stub. Defaults to false.
+    pub is_like_msdos: bool,
     /// Whether a target toolchain is like Android, implying a Linux kernel and a Bionic libc
     pub is_like_android: bool,
     /// Default supported version of DWARF on this platform.
@@ -2703,6 +2706,7 @@ fn default() -> TargetOptions {
             is_like_windows: false,
             is_like_msvc: false,
             is_like_wasm: false,
+            is_like_msdos: false,
             is_like_android: false,
             default_dwarf_version: 4,
             allows_weak_linkage: true,
@@ -1,11 +1,15 @@
 use crate::spec::{base, PanicStrategy, Target, TargetMetadata};
 
+// The image is linked to a fixed address and converted to MZ afterwards.
+const LINKER_SCRIPT: &str = include_str!("./i686_unknown_msdos6_linker_script.ld");
+
 pub(crate) fn target() -> Target {
     let mut base = base::msdos6::opts();
     base.cpu = "i686".into();
     base.disable_redzone = true;
     base.panic_strategy = PanicStrategy::Abort;
     base.features = "-mmx,-sse,+soft-float".into();
+    base.link_script = Some(LINKER_SCRIPT.into());
 
     Target {
         llvm_target: "i686-unknown-none".into(),
@@ -21,4 +25,4 @@ pub(crate) fn target() -> Target {
             std: None,
         },
     }
-}
\ No newline at end of file
+}
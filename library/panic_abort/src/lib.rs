@@ -51,6 +51,7 @@ unsafe fn abort() -> ! {
                             all(target_vendor = "fortanix", target_env = "sgx"),
                             target_os = "xous",
                             target_os = "uefi",
+                            target_os = "msdos6",
         ))] {
             unsafe fn abort() -> ! {
                 // call std::sys::abort_internal
@@ -0,0 +1,83 @@
+// crt0_msdos6.o is the C runtime startup object for the msdos6 target. It is
+// linked first into every executable and provides `_start`, the symbol the MZ
+// stub emitted by rustc jumps to once it has entered 32-bit protected mode.
+//
+// On entry the stub leaves us with:
+//
+// - `CS`: flat 32-bit code selector,
+// - `AX`: the matching flat data selector,
+// - `ES`: the PSP selector from the DPMI host,
+// - `EBX`: the linear address of link address 0,
+// - `ESI`: the real-mode segment of the PSP.
+//
+// The remaining segment registers and the stack still belong to the 16-bit
+// stub, so they are replaced before anything else happens. DOS does not clear
+// the memory it hands out behind the load module, so `.bss` has to be zeroed
+// here as well.
+//
+// Command-line arguments are not split up here: `main` is called with an empty
+// `argv`, and std reads the command tail from the PSP recorded below.
+
+#![feature(no_core)]
+#![feature(lang_items)]
+#![feature(auto_traits)]
+#![feature(decl_macro)]
+#![feature(rustc_attrs)]
+#![crate_type = "rlib"]
+#![no_core]
+#![allow(internal_features)]
+
+#[lang = "sized"]
+trait Sized {}
+#[lang = "sync"]
+auto trait Sync {}
+#[lang = "copy"]
+trait Copy {}
+#[lang = "freeze"]
+auto trait Freeze {}
+
+#[rustc_builtin_macro]
+macro global_asm("assembly template", $(operands,)* $(options($(option),*))?) {
+    /* compiler built-in */
+}
+
+global_asm!(
+    ".pushsection .text._start,\"ax\",@progbits",
+    ".globl _start",
+    ".type _start,@function",
+    "_start:",
+    "    mov dx, es",
+    "    mov ds, ax",
+    "    mov es, ax",
+    "    mov ss, ax",
+    "    mov esp, offset __stack_top",
+    "    cld",
+    "    mov edi, offset __bss_start",
+    "    mov ecx, offset __bss_end",
+    "    sub ecx, edi",
+    "    xor eax, eax",
+    "    rep stosb",
+    "    mov word ptr [__msdos6_psp_selector], dx",
+    "    mov word ptr [__msdos6_psp_segment], si",
+    "    mov dword ptr [__msdos6_image_base], ebx",
+    // Keep the stack 16-byte aligned at the call.
+    "    sub esp, 8",
+    "    push 0",
+    "    push 0",
+    "    call main",
+    // INT 21h/4Ch: terminate with the low byte of `main`'s result as ERRORLEVEL.
+    "    mov ah, 0x4c",
+    "    int 0x21",
+    "    hlt",
+    ".size _start, . - _start",
+    ".popsection",
+    ".pushsection .data.__msdos6_startup,\"aw\",@progbits",
+    ".balign 4",
+    ".globl __msdos6_image_base",
+    "__msdos6_image_base: .long 0",
+    ".globl __msdos6_psp_selector",
+    "__msdos6_psp_selector: .short 0",
+    ".globl __msdos6_psp_segment",
+    "__msdos6_psp_segment: .short 0",
+    ".popsection",
+);
@@ -0,0 +1,39 @@
+// crtend_msdos6.o is linked last into every msdos6 executable. It reserves the
+// initial stack, which the linker script places behind `.bss` at the very end
+// of the image, and marks its top with `__stack_top` for crt0_msdos6.o.
+//
+// The stack is a NOBITS section, so it costs conventional memory but no space
+// in the executable.
+
+#![feature(no_core)]
+#![feature(lang_items)]
+#![feature(auto_traits)]
+#![feature(decl_macro)]
+#![feature(rustc_attrs)]
+#![crate_type = "rlib"]
+#![no_core]
+#![allow(internal_features)]
+
+#[lang = "sized"]
+trait Sized {}
+#[lang = "sync"]
+auto trait Sync {}
+#[lang = "copy"]
+trait Copy {}
+#[lang = "freeze"]
+auto trait Freeze {}
+
+#[rustc_builtin_macro]
+macro global_asm("assembly template", $(operands,)* $(options($(option),*))?) {
+    /* compiler built-in */
+}
+
+// Same size as the minimum stack of spawned threads.
+global_asm!(
+    ".pushsection .stack,\"aw\",@nobits",
+    ".balign 16",
+    ".skip 65536",
+    ".globl __stack_top",
+    "__stack_top:",
+    ".popsection",
+);
@@ -0,0 +1,212 @@
+//! Full-screen text output.
+//!
+//! Cursor movement and colours go through the video BIOS, and
+//! [`TextBuffer`] writes straight into the text-mode frame buffer, which is
+//! much faster for redrawing the whole screen.
+//!
+//! A program that changes the video mode with [`set_video_mode`] gets the
+//! mode it started in back when it exits.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! #![feature(msdos6_std)]
+//! use std::os::msdos6::console::{self, Attribute, Color, TextBuffer};
+//!
+//! fn main() -> std::io::Result<()> {
+//!     let status = Attribute::new(Color::Black, Color::LightGray);
+//!     console::clear(Attribute::default())?;
+//!     let mut screen = TextBuffer::new()?;
+//!     let (_, rows) = console::size();
+//!     screen.fill_row(rows - 1, b' ', status);
+//!     screen.write(rows - 1, 1, b"F1 Help  F10 Quit", status);
+//!     console::set_cursor_position(0, 0)?;
+//!     Ok(())
+//! }
+//! ```
+
+#![unstable(feature = "msdos6_std", issue = "none")]
+
+use crate::io;
+use crate::marker::PhantomData;
+use crate::sys::console;
+
+/// The 16 colours of the text modes.
+///
+/// Background colours 8 to 15 show as blinking 0 to 7 unless blinking has
+/// been turned off.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Color {
+    Black = 0,
+    Blue = 1,
+    Green = 2,
+    Cyan = 3,
+    Red = 4,
+    Magenta = 5,
+    Brown = 6,
+    LightGray = 7,
+    DarkGray = 8,
+    LightBlue = 9,
+    LightGreen = 10,
+    LightCyan = 11,
+    LightRed = 12,
+    LightMagenta = 13,
+    Yellow = 14,
+    White = 15,
+}
+
+/// A character attribute: a foreground colour in the low nibble and a
+/// background colour in the high one.
+///
+/// Monochrome adapters show attributes as plain, bright, underlined or
+/// reverse video instead of in colour.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Attribute(pub u8);
+
+impl Attribute {
+    /// Returns the attribute for `foreground` on `background`.
+    pub const fn new(foreground: Color, background: Color) -> Attribute {
+        Attribute((background as u8) << 4 | foreground as u8)
+    }
+}
+
+/// Light grey on black, what DOS uses.
+impl Default for Attribute {
+    fn default() -> Attribute {
+        Attribute::new(Color::LightGray, Color::Black)
+    }
+}
+
+/// Returns the current video mode.
+pub fn video_mode() -> io::Result<u8> {
+    console::video_mode().map(|(mode, _)| mode)
+}
+
+/// Sets the video mode, such as 3 for 80×25 colour text. The mode the program
+/// started in is restored when it exits.
+pub fn set_video_mode(mode: u8) -> io::Result<()> {
+    console::set_video_mode(mode)
+}
+
+/// Returns the number of columns and rows on the screen.
+pub fn size() -> (u16, u16) {
+    console::size()
+}
+
+/// Moves the cursor to `row` and `column`, counted from 0.
+pub fn set_cursor_position(row: u8, column: u8) -> io::Result<()> {
+    console::set_cursor_position(row, column)
+}
+
+/// Returns the row and column of the cursor.
+pub fn cursor_position() -> io::Result<(u8, u8)> {
+    console::cursor_position()
+}
+
+/// Shows or hides the cursor.
+pub fn set_cursor_visible(visible: bool) -> io::Result<()> {
+    console::set_cursor_visible(visible)
+}
+
+/// Writes `bytes` at the cursor in `attribute`, moving the cursor on.
+///
+/// Unlike output to [`io::stdout`], this always goes to the screen, even
+/// when standard output is redirected.
+pub fn write_with_attribute(bytes: &[u8], attribute: Attribute) -> io::Result<()> {
+    console::write_with_attribute(bytes, attribute.0)
+}
+
+/// Clears the screen to spaces in `attribute`.
+pub fn clear(attribute: Attribute) -> io::Result<()> {
+    console::clear(attribute.0)
+}
+
+/// The text-mode frame buffer of the active page: at B800h on colour
+/// adapters and at B000h on monochrome ones.
+///
+/// The mode and the size of the screen are looked up again on every call, so
+/// a buffer follows [`set_video_mode`]. While the screen is in a graphics
+/// mode, writes are ignored and reads return `None`. Positions outside the
+/// screen are ignored when writing.
+#[derive(Debug)]
+pub struct TextBuffer {
+    // Writes to the screen aren't synchronised, so this isn't `Send` or `Sync`.
+    _marker: PhantomData<*mut u16>,
+}
+
+impl TextBuffer {
+    /// Returns the frame buffer of the current text mode, or an error in
+    /// graphics modes.
+    pub fn new() -> io::Result<TextBuffer> {
+        if console::text_screen().is_none() {
+            return Err(io::const_error!(
+                io::ErrorKind::Unsupported,
+                "the screen is not in a text mode"
+            ));
+        }
+        Ok(TextBuffer { _marker: PhantomData })
+    }
+
+    /// Returns whether the screen is in the text mode of monochrome adapters.
+    pub fn is_mono(&self) -> bool {
+        console::text_screen().is_some_and(|screen| screen.mono)
+    }
+
+    /// Returns the number of columns and rows.
+    pub fn size(&self) -> (u16, u16) {
+        console::size()
+    }
+
+    /// Puts `byte` in `attribute` at `row` and `column`.
+    pub fn put(&mut self, row: u16, column: u16, byte: u8, attribute: Attribute) {
+        if let Some(screen) = console::text_screen() {
+            put(&screen, row, column, byte, attribute);
+        }
+    }
+
+    /// Returns the character and attribute at `row` and `column`.
+    pub fn get(&self, row: u16, column: u16) -> Option<(u8, Attribute)> {
+        let cell = console::text_screen()?.cell(row, column)?;
+        let cell = unsafe { cell.read_volatile() };
+        Some((cell as u8, Attribute((cell >> 8) as u8)))
+    }
+
+    /// Writes `bytes` in `attribute` from `row` and `column` on, cutting them
+    /// off at the end of the row.
+    pub fn write(&mut self, row: u16, column: u16, bytes: &[u8], attribute: Attribute) {
+        let Some(screen) = console::text_screen() else { return };
+        for (offset, &byte) in bytes.iter().enumerate() {
+            let Some(column) = u16::try_from(offset).ok().and_then(|o| column.checked_add(o))
+            else {
+                break;
+            };
+            put(&screen, row, column, byte, attribute);
+        }
+    }
+
+    /// Fills `row` with `byte` in `attribute`.
+    pub fn fill_row(&mut self, row: u16, byte: u8, attribute: Attribute) {
+        let Some(screen) = console::text_screen() else { return };
+        for column in 0..screen.columns {
+            put(&screen, row, column, byte, attribute);
+        }
+    }
+
+    /// Fills the whole screen with `byte` in `attribute`.
+    pub fn fill(&mut self, byte: u8, attribute: Attribute) {
+        let Some(screen) = console::text_screen() else { return };
+        for row in 0..screen.rows {
+            for column in 0..screen.columns {
+                put(&screen, row, column, byte, attribute);
+            }
+        }
+    }
+}
+
+fn put(screen: &console::TextScreen, row: u16, column: u16, byte: u8, attribute: Attribute) {
+    if let Some(cell) = screen.cell(row, column) {
+        let value = u16::from(attribute.0) << 8 | u16::from(byte);
+        unsafe { cell.write_volatile(value) };
+    }
+}
@@ -0,0 +1,90 @@
+//! Handling Ctrl-C and Ctrl-Break.
+//!
+//! By default DOS ends a program as soon as it notices Ctrl-C or Ctrl-Break,
+//! without running destructors, flushing buffers or undoing changes to the
+//! screen. These functions replace that with a callback or a flag to poll.
+//! The original handlers are put back when the program exits, whether by
+//! returning from `main`, [`process::exit`] or [`process::abort`].
+//!
+//! [`process::exit`]: crate::process::exit
+//! [`process::abort`]: crate::process::abort
+//!
+//! # Examples
+//!
+//! ```no_run
+//! #![feature(msdos6_std)]
+//! use std::os::msdos6::ctrl_break;
+//!
+//! fn main() -> std::io::Result<()> {
+//!     ctrl_break::catch()?;
+//!     while !ctrl_break::take_pending() {
+//!         // Do some work.
+//!     }
+//!     println!("stopped");
+//!     Ok(())
+//! }
+//! ```
+
+#![unstable(feature = "msdos6_std", issue = "none")]
+
+use crate::io;
+use crate::sys::ctrl_break;
+
+/// Calls `handler` when Ctrl-C or Ctrl-Break is pressed, instead of ending the
+/// program.
+///
+/// `handler` is called by DOS, on an 8 KiB stack of its own, at a point where
+/// DOS is ready to be called again. It does not see Ctrl-Break until then,
+/// which is at the next DOS call if `BREAK=ON`, or at the next console I/O
+/// otherwise. The program continues where it was interrupted once `handler`
+/// returns. Presses while `handler` runs are recorded for [`take_pending`]
+/// instead of calling it again.
+///
+/// Prefer [`catch`] and [`take_pending`], which have none of the restrictions
+/// below.
+///
+/// # Safety
+///
+/// `handler` interrupts the program in the middle of a DOS call made by the
+/// standard library, which may hold locks it needs. It must not:
+///
+/// * do any I/O through the standard library, including `println!`, file
+///   and console I/O, and networking;
+/// * lock a [`Mutex`] or [`RwLock`] the interrupted code may hold, or wait
+///   on anything else another thread has to release;
+/// * yield to or wait for other threads, for example with [`thread::sleep`]
+///   or [`thread::yield_now`], as the interrupted thread can't be switched
+///   away from while on the handler's stack;
+/// * allocate or free memory, including through `Box`, `Vec` and `String`,
+///   as the allocator's lock may be held by the interrupted code.
+///
+/// Setting atomics and returning is always fine.
+///
+/// [`Mutex`]: crate::sync::Mutex
+/// [`RwLock`]: crate::sync::RwLock
+/// [`thread::sleep`]: crate::thread::sleep
+/// [`thread::yield_now`]: crate::thread::yield_now
+pub unsafe fn set_handler(handler: fn()) -> io::Result<()> {
+    ctrl_break::install(Some(handler))
+}
+
+/// Records Ctrl-C and Ctrl-Break presses for [`take_pending`], instead of
+/// ending the program.
+///
+/// Ctrl-Break is seen as soon as it is pressed, even by a program that never
+/// calls DOS. Ctrl-C is only seen when DOS checks for it: on console I/O, or
+/// on any DOS call if `BREAK=ON`.
+pub fn catch() -> io::Result<()> {
+    ctrl_break::install(None)
+}
+
+/// Returns whether Ctrl-C or Ctrl-Break was pressed since the last call,
+/// after [`catch`] has been called.
+pub fn take_pending() -> bool {
+    ctrl_break::take_pending()
+}
+
+/// Lets DOS end the program on Ctrl-C and Ctrl-Break again.
+pub fn restore_default() {
+    ctrl_break::restore()
+}
@@ -0,0 +1,160 @@
+//! Calling DOS and the BIOS directly.
+//!
+//! Programs run as 32-bit DPMI clients, so interrupts are made in real mode
+//! through the DPMI host. Segment registers passed to them are real-mode
+//! segments, and any buffer they point to must be in conventional memory:
+//! [`real_mode_address`] finds the segment and offset of such a buffer.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! #![feature(msdos6_std)]
+//! use std::os::msdos6::dos::{Registers, intdos};
+//!
+//! fn main() -> std::io::Result<()> {
+//!     // INT 21h/30h: get the DOS version.
+//!     let mut regs = Registers { eax: 0x3000, ..Default::default() };
+//!     unsafe { intdos(&mut regs)? };
+//!     println!("DOS {}.{:02}", regs.eax as u8, (regs.eax >> 8) as u8);
+//!     Ok(())
+//! }
+//! ```
+
+#![unstable(feature = "msdos6_std", issue = "none")]
+
+use crate::io;
+use crate::sys::pal::{dos, dpmi, psp};
+
+/// The carry flag, which DOS and the BIOS set when a call fails.
+pub const CARRY_FLAG: u16 = 0x0001;
+
+/// The registers an interrupt is called with and returns.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Registers {
+    pub eax: u32,
+    pub ebx: u32,
+    pub ecx: u32,
+    pub edx: u32,
+    pub esi: u32,
+    pub edi: u32,
+    pub ebp: u32,
+    pub flags: u16,
+    pub ds: u16,
+    pub es: u16,
+    pub fs: u16,
+    pub gs: u16,
+}
+
+impl Registers {
+    fn to_real_mode(&self) -> dpmi::RealModeRegisters {
+        dpmi::RealModeRegisters {
+            eax: self.eax,
+            ebx: self.ebx,
+            ecx: self.ecx,
+            edx: self.edx,
+            esi: self.esi,
+            edi: self.edi,
+            ebp: self.ebp,
+            flags: self.flags,
+            ds: self.ds,
+            es: self.es,
+            fs: self.fs,
+            gs: self.gs,
+            ..Default::default()
+        }
+    }
+
+    fn from_real_mode(regs: &dpmi::RealModeRegisters) -> Registers {
+        Registers {
+            eax: regs.eax,
+            ebx: regs.ebx,
+            ecx: regs.ecx,
+            edx: regs.edx,
+            esi: regs.esi,
+            edi: regs.edi,
+            ebp: regs.ebp,
+            flags: regs.flags,
+            ds: regs.ds,
+            es: regs.es,
+            fs: regs.fs,
+            gs: regs.gs,
+        }
+    }
+}
+
+/// Calls real-mode interrupt `interrupt` with `regs`, and stores the
+/// registers it returns back into `regs`.
+///
+/// This only fails if the DPMI host can't make the call. Whether the
+/// interrupt itself succeeded is up to the caller to find out, usually from
+/// [`CARRY_FLAG`] in `regs.flags`.
+///
+/// # Safety
+///
+/// Interrupt handlers can do anything, including overwriting memory that
+/// Rust code owns. The caller must make sure the call is sound.
+pub unsafe fn int86(interrupt: u8, regs: &mut Registers) -> io::Result<()> {
+    let mut real_mode = regs.to_real_mode();
+    dos::int86(interrupt, &mut real_mode)?;
+    *regs = Registers::from_real_mode(&real_mode);
+    Ok(())
+}
+
+/// Calls INT 21h with `regs`, and stores the registers it returns back into
+/// `regs`.
+///
+/// If DOS sets the carry flag, the error is returned with the extended error
+/// code as its [raw OS error](io::Error::raw_os_error).
+///
+/// # Safety
+///
+/// As with [`int86`], the caller must make sure the call is sound.
+pub unsafe fn intdos(regs: &mut Registers) -> io::Result<()> {
+    let mut real_mode = regs.to_real_mode();
+    let result = dos::int21(&mut real_mode);
+    *regs = Registers::from_real_mode(&real_mode);
+    result
+}
+
+/// Returns the real-mode segment and offset of `ptr`, or `None` if it is not
+/// in conventional memory. Statics and the stack of the main thread always
+/// are. The heap may be allocated from extended memory, and the stacks of
+/// threads from `std::thread` are on the heap, so a buffer on the stack
+/// has to be checked like any other unless it is the main thread's.
+pub fn real_mode_address<T: ?Sized>(ptr: *const T) -> Option<(u16, u16)> {
+    let linear = dpmi::ptr_to_linear(ptr.cast());
+    if linear >= 0x10_0000 {
+        return None;
+    }
+    Some(((linear >> 4) as u16, (linear & 0xf) as u16))
+}
+
+/// Returns a pointer to the real-mode address `segment:offset`.
+pub fn real_mode_ptr(segment: u16, offset: u16) -> *mut u8 {
+    dpmi::linear_to_ptr((u32::from(segment) << 4) + u32::from(offset))
+}
+
+/// Returns the real-mode segment of the program segment prefix.
+pub fn psp_segment() -> u16 {
+    psp::segment()
+}
+
+/// Returns the protected-mode selector of the program segment prefix.
+pub fn psp_selector() -> u16 {
+    psp::selector()
+}
+
+/// Returns the protected-mode selector of the environment block, which the
+/// DPMI host stores at PSP:002Ch in place of its segment.
+pub fn environment_selector() -> u16 {
+    psp::environment_selector()
+}
+
+/// Returns the real-mode segment of the environment block, or `None` if there
+/// is none.
+pub fn environment_segment() -> Option<u16> {
+    match environment_selector() {
+        0 => None,
+        selector => dpmi::segment_base(selector).map(|base| (base >> 4) as u16),
+    }
+}
@@ -0,0 +1,28 @@
+//! MS-DOS-specific extensions to the primitives in the `std::ffi` module.
+//!
+//! An [`OsStr`] holds UTF-8: file names and arguments are decoded from the
+//! active code page when DOS gives them to us, and encoded back when they are
+//! passed to DOS. These traits give access to the bytes. Bytes that aren't
+//! UTF-8 are taken to be in the code page already, and reach DOS unchanged.
+//!
+//! [`OsStr`]: crate::ffi::OsStr
+//!
+//! # Examples
+//!
+//! ```
+//! #![feature(msdos6_std)]
+//! use std::ffi::OsString;
+//! use std::os::msdos6::ffi::OsStringExt;
+//!
+//! // "CAFÉ" in code pages 437 and 850.
+//! let name = OsString::from_vec(b"CAF\x90".to_vec());
+//! println!("{}", name.to_string_lossy());
+//! assert_eq!(name.into_vec(), b"CAF\x90");
+//! ```
+
+#![unstable(feature = "msdos6_std", issue = "none")]
+
+#[path = "../unix/ffi/os_str.rs"]
+mod os_str;
+
+pub use self::os_str::{OsStrExt, OsStringExt};
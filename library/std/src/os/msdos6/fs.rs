@@ -0,0 +1,112 @@
+//! MS-DOS-specific extensions to primitives in the [`std::fs`] module.
+//!
+//! [`std::fs`]: crate::fs
+
+#![unstable(feature = "msdos6_std", issue = "none")]
+
+use crate::ffi::OsString;
+use crate::path::{Path, PathBuf};
+use crate::sealed::Sealed;
+use crate::sys_common::AsInner;
+use crate::{fs, io, sys};
+
+/// MS-DOS-specific extensions to [`fs::DirEntry`].
+pub trait DirEntryExt: Sealed {
+    /// Returns the 8.3 alias of this entry's file name.
+    ///
+    /// Without long file name support, every name is an 8.3 name and this is
+    /// the same as [`fs::DirEntry::file_name`].
+    fn short_name(&self) -> OsString;
+}
+
+impl Sealed for fs::DirEntry {}
+
+/// MS-DOS-specific extensions to [`fs::Metadata`].
+///
+/// Dates and times are in the packed format DOS uses. A date holds the day in
+/// bits 0–4, the month in bits 5–8 and the year since 1980 in bits 9–15; a
+/// time holds the seconds divided by 2 in bits 0–4, the minutes in bits 5–10
+/// and the hour in bits 11–15. A date of 0 means it was not recorded.
+pub trait MetadataExt: Sealed {
+    /// Returns the DOS attribute byte of the file: read-only (`0x01`),
+    /// hidden (`0x02`), system (`0x04`), directory (`0x10`) and archive
+    /// (`0x20`).
+    fn file_attributes(&self) -> u8;
+
+    /// Returns the date of the last write.
+    fn last_write_date(&self) -> u16;
+
+    /// Returns the time of the last write.
+    fn last_write_time(&self) -> u16;
+
+    /// Returns the date of the last access, which only the long file name
+    /// API records.
+    fn last_access_date(&self) -> u16;
+
+    /// Returns the creation date, which only the long file name API records.
+    fn creation_date(&self) -> u16;
+
+    /// Returns the creation time, which only the long file name API records.
+    fn creation_time(&self) -> u16;
+}
+
+impl Sealed for fs::Metadata {}
+
+impl MetadataExt for fs::Metadata {
+    fn file_attributes(&self) -> u8 {
+        self.as_inner().attributes()
+    }
+
+    fn last_write_date(&self) -> u16 {
+        self.as_inner().dos_modified().0
+    }
+
+    fn last_write_time(&self) -> u16 {
+        self.as_inner().dos_modified().1
+    }
+
+    fn last_access_date(&self) -> u16 {
+        self.as_inner().dos_accessed().0
+    }
+
+    fn creation_date(&self) -> u16 {
+        self.as_inner().dos_created().0
+    }
+
+    fn creation_time(&self) -> u16 {
+        self.as_inner().dos_created().1
+    }
+}
+
+impl DirEntryExt for fs::DirEntry {
+    fn short_name(&self) -> OsString {
+        self.as_inner().short_name()
+    }
+}
+
+/// Returns `path` with every component replaced by its 8.3 alias.
+///
+/// This is the form of the path programs unaware of long file names need to
+/// be given. Without long file name support, `path` is returned unchanged.
+///
+/// # Examples
+///
+/// ```no_run
+/// #![feature(msdos6_std)]
+/// use std::os::msdos6::fs::short_path;
+///
+/// fn main() -> std::io::Result<()> {
+///     // Prints something like `C:\PROGRA~1\README~1.TXT`.
+///     println!("{}", short_path(r"C:\Program Files\Readme first.txt")?.display());
+///     Ok(())
+/// }
+/// ```
+pub fn short_path<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
+    sys::fs::short_path(path.as_ref())
+}
+
+/// Returns whether DOS provides the long file name API (INT 21h functions
+/// 71xxh), which std uses for all paths when it is available.
+pub fn long_file_names() -> bool {
+    sys::fs::long_file_names()
+}
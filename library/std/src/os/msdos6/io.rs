@@ -0,0 +1,74 @@
+//! MS-DOS-specific extensions to general I/O primitives.
+
+#![unstable(feature = "msdos6_std", issue = "none")]
+
+use crate::sys::stdio::{STDERR_HANDLE, STDIN_HANDLE, STDOUT_HANDLE};
+use crate::sys_common::{AsInner, FromInner, IntoInner};
+use crate::{fs, io, sys};
+
+/// Raw DOS file handles, as used by INT 21h.
+pub type RawHandle = u16;
+
+/// Extracts the raw DOS handle of an object.
+pub trait AsRawHandle {
+    /// Returns the raw handle of this object.
+    ///
+    /// The handle stays owned by this object and is only valid as long as
+    /// the object is.
+    fn as_raw_handle(&self) -> RawHandle;
+}
+
+/// Constructs an object from a raw DOS handle.
+pub trait FromRawHandle {
+    /// Constructs a new object from `handle`, which it takes ownership of.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be open, and nothing else may use or close it afterwards.
+    unsafe fn from_raw_handle(handle: RawHandle) -> Self;
+}
+
+/// Consumes an object and returns its raw DOS handle.
+pub trait IntoRawHandle {
+    /// Returns the raw handle of this object, which the caller now owns and
+    /// is responsible for closing with INT 21h/3Eh.
+    fn into_raw_handle(self) -> RawHandle;
+}
+
+impl AsRawHandle for fs::File {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.as_inner().handle()
+    }
+}
+
+/// DOS keeps no append mode for a handle; [`fs::File`] emulates it by seeking
+/// to the end before each write. A file made from a raw handle therefore
+/// doesn't append, even if the handle came from a file opened with
+/// [`OpenOptions::append`].
+///
+/// [`OpenOptions::append`]: fs::OpenOptions::append
+impl FromRawHandle for fs::File {
+    unsafe fn from_raw_handle(handle: RawHandle) -> fs::File {
+        fs::File::from_inner(sys::fs::File::from_handle(handle))
+    }
+}
+
+impl IntoRawHandle for fs::File {
+    fn into_raw_handle(self) -> RawHandle {
+        self.into_inner().into_handle()
+    }
+}
+
+macro_rules! impl_as_raw_handle {
+    ($handle:expr => $($t:ty),*) => {$(
+        impl AsRawHandle for $t {
+            fn as_raw_handle(&self) -> RawHandle {
+                $handle
+            }
+        }
+    )*};
+}
+
+impl_as_raw_handle!(STDIN_HANDLE => io::Stdin, io::StdinLock<'_>);
+impl_as_raw_handle!(STDOUT_HANDLE => io::Stdout, io::StdoutLock<'_>);
+impl_as_raw_handle!(STDERR_HANDLE => io::Stderr, io::StderrLock<'_>);
@@ -0,0 +1,156 @@
+//! Raw keyboard input.
+//!
+//! [`io::stdin`] only sees characters, once the user has finished a line. This
+//! module reads key presses as the keyboard BIOS reports them, including keys
+//! without a character like the cursor and function keys, and can check for
+//! one without waiting.
+//!
+//! [`io::stdin`]: crate::io::stdin
+//!
+//! # Examples
+//!
+//! ```no_run
+//! #![feature(msdos6_std)]
+//! use std::os::msdos6::keyboard;
+//!
+//! fn main() -> std::io::Result<()> {
+//!     loop {
+//!         let key = keyboard::read_key()?;
+//!         match key.scan_code {
+//!             0x01 => break, // Esc
+//!             0x48 => println!("up"),
+//!             0x50 => println!("down"),
+//!             _ if key.char().is_some() => println!("typed {:?}", key.char()),
+//!             _ => {}
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+#![unstable(feature = "msdos6_std", issue = "none")]
+
+use crate::sys::keyboard;
+use crate::{io, thread};
+
+/// The scan codes the BIOS reports with E0h for the grey cursor keys of
+/// enhanced keyboards, alone and with Ctrl. E0h is also a character in the code
+/// pages ('α' in 437), which a keyboard layout may put on any other key.
+const GREY_KEYS: [u8; 20] = [
+    0x47, 0x48, 0x49, 0x4b, 0x4d, 0x4f, 0x50, 0x51, 0x52, 0x53, // Home to Delete
+    0x73, 0x74, 0x75, 0x76, 0x77, 0x84, 0x8d, 0x91, 0x92, 0x93, // with Ctrl
+];
+
+/// A key press, as reported by the keyboard BIOS.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+    /// The scan code of the key, which tells apart keys without a character
+    /// and keys that produce the same one, like the two Enter keys.
+    pub scan_code: u8,
+    /// The character the key produced, in the active code page. It is 0 for
+    /// keys without one, and E0h for the grey cursor keys of enhanced
+    /// keyboards.
+    pub ascii: u8,
+    /// The shift flags when the key was read.
+    pub shift_flags: ShiftFlags,
+}
+
+impl KeyEvent {
+    /// Returns whether this key has no character of its own, like the cursor
+    /// and function keys.
+    pub fn is_extended(&self) -> bool {
+        self.ascii == 0 || (self.ascii == 0xe0 && GREY_KEYS.contains(&self.scan_code))
+    }
+
+    /// Returns the character this key produced, if it is ASCII.
+    pub fn char(&self) -> Option<char> {
+        (!self.is_extended() && self.ascii.is_ascii()).then(|| char::from(self.ascii))
+    }
+
+    fn from_bios(key: u16, shift_flags: ShiftFlags) -> KeyEvent {
+        KeyEvent { scan_code: (key >> 8) as u8, ascii: key as u8, shift_flags }
+    }
+}
+
+/// The state of the shift and lock keys.
+///
+/// The low byte is the same on all keyboards. The high byte, which tells the
+/// left and right Ctrl and Alt keys apart, is only filled in for enhanced
+/// keyboards.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ShiftFlags(pub u16);
+
+impl ShiftFlags {
+    pub const RIGHT_SHIFT: u16 = 0x0001;
+    pub const LEFT_SHIFT: u16 = 0x0002;
+    pub const CTRL: u16 = 0x0004;
+    pub const ALT: u16 = 0x0008;
+    pub const SCROLL_LOCK: u16 = 0x0010;
+    pub const NUM_LOCK: u16 = 0x0020;
+    pub const CAPS_LOCK: u16 = 0x0040;
+    pub const INSERT: u16 = 0x0080;
+    pub const LEFT_CTRL: u16 = 0x0100;
+    pub const LEFT_ALT: u16 = 0x0200;
+    pub const RIGHT_CTRL: u16 = 0x0400;
+    pub const RIGHT_ALT: u16 = 0x0800;
+    pub const SYS_REQ: u16 = 0x8000;
+
+    /// Returns whether all the bits in `flags` are set.
+    pub fn contains(&self, flags: u16) -> bool {
+        self.0 & flags == flags
+    }
+
+    /// Returns whether either Shift key is down.
+    pub fn shift(&self) -> bool {
+        self.0 & (Self::LEFT_SHIFT | Self::RIGHT_SHIFT) != 0
+    }
+
+    /// Returns whether either Ctrl key is down.
+    pub fn ctrl(&self) -> bool {
+        self.contains(Self::CTRL)
+    }
+
+    /// Returns whether either Alt key is down.
+    pub fn alt(&self) -> bool {
+        self.contains(Self::ALT)
+    }
+}
+
+/// Returns whether the BIOS reports the keys of an enhanced (101/102-key)
+/// keyboard, such as F11 and F12.
+pub fn has_enhanced_keyboard() -> bool {
+    keyboard::enhanced()
+}
+
+/// Waits for a key press and returns it.
+///
+/// While waiting, the rest of the time slice is given up to other programs
+/// when running under a multitasker.
+pub fn read_key() -> io::Result<KeyEvent> {
+    loop {
+        if let Some(key) = poll_key()? {
+            return Ok(key);
+        }
+        thread::yield_now();
+    }
+}
+
+/// Returns the next key press if there is one, without waiting.
+pub fn poll_key() -> io::Result<Option<KeyEvent>> {
+    if keyboard::peek()?.is_none() {
+        return Ok(None);
+    }
+    let key = keyboard::read()?;
+    Ok(Some(KeyEvent::from_bios(key, shift_flags()?)))
+}
+
+/// Returns the next key press if there is one, but leaves it to be read.
+pub fn peek_key() -> io::Result<Option<KeyEvent>> {
+    let Some(key) = keyboard::peek()? else { return Ok(None) };
+    Ok(Some(KeyEvent::from_bios(key, shift_flags()?)))
+}
+
+/// Returns the current state of the shift and lock keys.
+pub fn shift_flags() -> io::Result<ShiftFlags> {
+    keyboard::shift_flags().map(ShiftFlags)
+}
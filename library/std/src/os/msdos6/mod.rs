@@ -0,0 +1,13 @@
+//! MS-DOS-specific definitions.
+
+#![unstable(feature = "msdos6_std", issue = "none")]
+#![doc(cfg(target_os = "msdos6"))]
+
+pub mod console;
+pub mod ctrl_break;
+pub mod dos;
+pub mod ffi;
+pub mod fs;
+pub mod io;
+pub mod keyboard;
+pub mod serial;
@@ -0,0 +1,235 @@
+//! Serial ports.
+//!
+//! [`SerialPort`] drives the 8250 or 16550 UART of COM1 to COM4 directly,
+//! with interrupts, so that bytes arriving while the program is busy wait in
+//! a buffer instead of being lost. The 16550's FIFOs are used when it has
+//! them. Opening a port unmasks its IRQ; closing it, or exiting the program
+//! with it open, masks the IRQ again and puts back the interrupt vector.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! #![feature(msdos6_std)]
+//! use std::io::{Read, Write};
+//! use std::os::msdos6::serial::{SerialPort, Settings};
+//! use std::time::Duration;
+//!
+//! fn main() -> std::io::Result<()> {
+//!     let mut port = SerialPort::open(1, &Settings::new(9600))?;
+//!     port.set_read_timeout(Some(Duration::from_secs(2)))?;
+//!     port.write_all(b"*IDN?\r\n")?;
+//!     let mut reply = [0; 64];
+//!     let len = port.read(&mut reply)?;
+//!     println!("{}", String::from_utf8_lossy(&reply[..len]));
+//!     Ok(())
+//! }
+//! ```
+
+#![unstable(feature = "msdos6_std", issue = "none")]
+
+use crate::fmt;
+use crate::io::{self, Read, Write};
+use crate::sys::serial;
+use crate::time::Duration;
+
+/// The parity bit sent after the data bits.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+    /// Always 1.
+    Mark,
+    /// Always 0.
+    Space,
+}
+
+/// The number of stop bits. With 5 data bits, `Two` means one and a half.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+/// How to program the UART.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Settings {
+    /// The speed in bits per second. It has to divide 115200.
+    pub baud_rate: u32,
+    /// From 5 to 8.
+    pub data_bits: u8,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    /// Whether to use the FIFOs of a 16550A. Ignored on UARTs without them.
+    pub fifo: bool,
+}
+
+impl Settings {
+    /// Returns the settings for `baud_rate`, 8 data bits, no parity and one
+    /// stop bit.
+    pub const fn new(baud_rate: u32) -> Settings {
+        Settings {
+            baud_rate,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            fifo: true,
+        }
+    }
+
+    /// The baud rate divisor.
+    fn divisor(&self) -> Option<u16> {
+        if self.baud_rate == 0 || serial::MAX_BAUD_RATE % self.baud_rate != 0 {
+            return None;
+        }
+        u16::try_from(serial::MAX_BAUD_RATE / self.baud_rate).ok()
+    }
+
+    /// The value of the line control register.
+    fn line_control(&self) -> Option<u8> {
+        if !(5..=8).contains(&self.data_bits) {
+            return None;
+        }
+        let parity = match self.parity {
+            Parity::None => 0x00,
+            Parity::Odd => 0x08,
+            Parity::Even => 0x18,
+            Parity::Mark => 0x28,
+            Parity::Space => 0x38,
+        };
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 0x00,
+            StopBits::Two => 0x04,
+        };
+        Some((self.data_bits - 5) | stop_bits | parity)
+    }
+}
+
+/// 9600 baud, 8 data bits, no parity and one stop bit.
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::new(9600)
+    }
+}
+
+/// An open COM port.
+///
+/// Reads wait for at least one byte and return what has arrived by then.
+/// Writes return once the data is queued; [`flush`] waits until it has been
+/// sent. Both wait forever unless a timeout is set, and let other threads
+/// run meanwhile. Bytes still queued when the port is dropped are not sent.
+///
+/// [`flush`]: Write::flush
+pub struct SerialPort {
+    inner: serial::SerialPort,
+}
+
+impl SerialPort {
+    /// Opens COM1 to COM4, given its `number`, with `settings`.
+    ///
+    /// Fails with [`io::ErrorKind::NotFound`] if the BIOS knows of no such
+    /// port, and with [`io::ErrorKind::ResourceBusy`] if it is already open.
+    pub fn open(number: u8, settings: &Settings) -> io::Result<SerialPort> {
+        let Some(divisor) = settings.divisor() else {
+            return Err(io::const_error!(
+                io::ErrorKind::InvalidInput,
+                "the baud rate has to divide 115200",
+            ));
+        };
+        let Some(line_control) = settings.line_control() else {
+            return Err(io::const_error!(
+                io::ErrorKind::InvalidInput,
+                "a serial port takes 5 to 8 data bits",
+            ));
+        };
+        let inner = serial::SerialPort::open(number, divisor, line_control, settings.fifo)?;
+        Ok(SerialPort { inner })
+    }
+
+    /// Returns the number of the port, from 1 to 4.
+    pub fn number(&self) -> u8 {
+        self.inner.number()
+    }
+
+    /// Sets how long reads wait for a byte before failing with
+    /// [`io::ErrorKind::TimedOut`]. `None` waits forever.
+    ///
+    /// A zero duration is refused, as for sockets.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        check_timeout(timeout)?;
+        self.inner.set_read_timeout(timeout);
+        Ok(())
+    }
+
+    /// Sets how long writes wait for room in the buffer, and flushes for it
+    /// to empty, before failing with [`io::ErrorKind::TimedOut`]. `None`
+    /// waits forever.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        check_timeout(timeout)?;
+        self.inner.set_write_timeout(timeout);
+        Ok(())
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.inner.read_timeout()
+    }
+
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.inner.write_timeout()
+    }
+
+    /// Returns the number of received bytes waiting to be read.
+    pub fn bytes_to_read(&self) -> usize {
+        self.inner.bytes_to_read()
+    }
+
+    /// Returns the number of bytes waiting to be sent.
+    pub fn bytes_to_write(&self) -> usize {
+        self.inner.bytes_to_write()
+    }
+
+    /// Drops the received bytes that haven't been read.
+    pub fn clear_input(&mut self) {
+        self.inner.clear_input()
+    }
+
+    /// Returns an error for the overruns, parity and framing errors and
+    /// breaks seen since the last call, if there were any.
+    ///
+    /// Bytes received with parity or framing errors are still read.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.inner.take_error()
+    }
+}
+
+impl Read for SerialPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Write for SerialPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl fmt::Debug for SerialPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SerialPort").field("number", &self.number()).finish_non_exhaustive()
+    }
+}
+
+fn check_timeout(timeout: Option<Duration>) -> io::Result<()> {
+    if timeout == Some(Duration::ZERO) {
+        return Err(io::const_error!(
+            io::ErrorKind::InvalidInput,
+            "cannot set a 0 duration timeout",
+        ));
+    }
+    Ok(())
+}
@@ -39,6 +39,7 @@
 /// Hermit                 | `read_entropy`
 /// Horizon                | `getrandom` shim
 /// AIX, Hurd, L4Re, QNX   | `/dev/urandom`
+/// MS-DOS                 | SipHash over timer and TSC samples (not cryptographically secure)
 /// Redox                  | `/scheme/rand`
 /// RTEMS                  | [`arc4random_buf`](https://docs.rtems.org/branches/master/bsp-howto/getentropy.html)
 /// SGX                    | [`rdrand`](https://en.wikipedia.org/wiki/RDRAND)
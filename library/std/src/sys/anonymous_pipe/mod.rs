@@ -7,6 +7,9 @@
     } else if #[cfg(windows)] {
         mod windows;
         pub use windows::{AnonPipe, pipe};
+    } else if #[cfg(target_os = "msdos6")] {
+        mod msdos6;
+        pub use msdos6::{AnonPipe, pipe};
     } else {
         mod unsupported;
         pub use unsupported::{AnonPipe, pipe};
@@ -0,0 +1,25 @@
+use crate::io;
+use crate::pipe::{PipeReader, PipeWriter};
+use crate::process::Stdio;
+pub use crate::sys::pipe::AnonPipe;
+use crate::sys_common::FromInner;
+
+// DOS runs one program at a time, so there is no one to talk to through a pipe.
+#[inline]
+pub fn pipe() -> io::Result<(AnonPipe, AnonPipe)> {
+    Err(io::Error::UNSUPPORTED_PLATFORM)
+}
+
+#[unstable(feature = "anonymous_pipe", issue = "127154")]
+impl From<PipeReader> for Stdio {
+    fn from(pipe: PipeReader) -> Self {
+        Self::from_inner(pipe.0.into())
+    }
+}
+
+#[unstable(feature = "anonymous_pipe", issue = "127154")]
+impl From<PipeWriter> for Stdio {
+    fn from(pipe: PipeWriter) -> Self {
+        Self::from_inner(pipe.0.into())
+    }
+}
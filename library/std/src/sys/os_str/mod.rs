@@ -7,6 +7,9 @@
     ))] {
         mod wtf8;
         pub use wtf8::{Buf, Slice};
+    } else if #[cfg(target_os = "msdos6")] {
+        mod msdos6;
+        pub use msdos6::{Buf, Slice};
     } else {
         mod bytes;
         pub use bytes::{Buf, Slice};
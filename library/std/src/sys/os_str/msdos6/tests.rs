@@ -0,0 +1,40 @@
+use super::*;
+
+#[test]
+fn names_from_dos_are_utf8() {
+    // The code page is 437 until `codepage::init` asks DOS.
+    let name = Buf::from_string(codepage::decode(b"CAF\x90.TXT"));
+    assert_eq!(name.as_slice().to_str(), Ok("CAFÉ.TXT"));
+    assert_eq!(name.into_string(), Ok(String::from("CAFÉ.TXT")));
+}
+
+#[test]
+fn into_string_fails_like_to_str() {
+    let name = Buf { inner: b"CAF\x90.TXT".to_vec() };
+    assert!(name.as_slice().to_str().is_err());
+    assert_eq!(name.clone().into_string().unwrap_err().inner, name.inner);
+}
+
+#[test]
+fn to_string_lossy_decodes_bytes_that_are_not_utf8() {
+    let name = unsafe { Slice::from_encoded_bytes_unchecked(b"CAF\x90.TXT") };
+    assert_eq!(name.to_string_lossy(), "CAFÉ.TXT");
+    assert_eq!(name.to_string(), "CAFÉ.TXT");
+    // Only those bytes are.
+    let mixed = [b"\\CAF\xc3\x89\\".as_slice(), b"CAF\x90.TXT"].concat();
+    let mixed = unsafe { Slice::from_encoded_bytes_unchecked(&mixed) };
+    assert_eq!(mixed.to_string_lossy(), "\\CAFÉ\\CAFÉ.TXT");
+}
+
+#[test]
+fn encode_round_trips_decoded_names() {
+    let bytes = b"CAF\x90.TXT";
+    assert_eq!(&*codepage::encode(codepage::decode(bytes).as_bytes()).unwrap(), bytes);
+    assert_eq!(&*codepage::encode(b"README.TXT").unwrap(), b"README.TXT");
+    assert!(codepage::encode("日本.TXT".as_bytes()).is_err());
+
+    // A decoded directory joined with a name in the code page, as given
+    // through `OsStrExt`, comes out in the code page throughout.
+    let mixed = [b"C:\\CAF\xc3\x89\\".as_slice(), bytes].concat();
+    assert_eq!(&*codepage::encode(&mixed).unwrap(), b"C:\\CAF\x90\\CAF\x90.TXT");
+}
@@ -1,32 +1,168 @@
-use crate::{
-    alloc::{GlobalAlloc, Layout, System},
-    ptr,
-    sync::atomic::{AtomicUsize, Ordering},
-};
+// FIXME(static_mut_refs): Do not allow `static_mut_refs` lint
+#![allow(static_mut_refs)]
 
-#[repr(align(4096))]
-struct HeapData([u8; 65536]);
+//! The system allocator for msdos6.
+//!
+//! dlmalloc keeps the free lists; memory is requested from the DPMI host in
+//! large segments. Extended memory (DPMI 0501h) is preferred, and whatever is
+//! left of conventional memory (DPMI 0100h, the protected-mode counterpart of
+//! INT 21h/48h) is used once the host runs out of it.
 
-static mut HEAP_DATA: HeapData = HeapData([0; 65536]);
-static HEAP_USED: AtomicUsize = AtomicUsize::new(0);
+use super::dpmi;
+use crate::alloc::{GlobalAlloc, Layout, System};
+use crate::ptr;
+
+static mut DLMALLOC: dlmalloc::Dlmalloc<DosMemory> =
+    dlmalloc::Dlmalloc::new_with_allocator(DosMemory);
 
 #[stable(feature = "alloc_system_type", since = "1.28.0")]
 unsafe impl GlobalAlloc for System {
     #[inline]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        if layout.align() > 8 {
-            return ptr::null_mut();
-        }
-        let num_blocks = if layout.size() % 8 == 0 {
-            layout.size() / 8
+        // SAFETY: DLMALLOC access is guaranteed to be safe because the lock gives us unique and non-reentrant access.
+        // Calling malloc() is safe because preconditions on this function match the trait method preconditions.
+        let _lock = lock::lock();
+        unsafe { DLMALLOC.malloc(layout.size(), layout.align()) }
+    }
+
+    #[inline]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: DLMALLOC access is guaranteed to be safe because the lock gives us unique and non-reentrant access.
+        // Calling calloc() is safe because preconditions on this function match the trait method preconditions.
+        let _lock = lock::lock();
+        unsafe { DLMALLOC.calloc(layout.size(), layout.align()) }
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: DLMALLOC access is guaranteed to be safe because the lock gives us unique and non-reentrant access.
+        // Calling free() is safe because preconditions on this function match the trait method preconditions.
+        let _lock = lock::lock();
+        unsafe { DLMALLOC.free(ptr, layout.size(), layout.align()) }
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: DLMALLOC access is guaranteed to be safe because the lock gives us unique and non-reentrant access.
+        // Calling realloc() is safe because preconditions on this function match the trait method preconditions.
+        let _lock = lock::lock();
+        unsafe { DLMALLOC.realloc(ptr, layout.size(), layout.align(), new_size) }
+    }
+}
+
+/// Where a segment handed to dlmalloc came from, stored in front of it so it
+/// can be given back to the right place.
+#[repr(C, align(16))]
+struct SegmentHeader {
+    /// DPMI memory block handle, or 0 for conventional memory.
+    handle: u32,
+    /// Selector of the conventional memory block.
+    selector: u16,
+}
+
+const HEADER_SIZE: usize = size_of::<SegmentHeader>();
+
+/// The conventional memory DPMI 0100h can hand out in one block, in paragraphs.
+const MAX_DOS_PARAGRAPHS: usize = 0xffff;
+
+struct DosMemory;
+
+impl DosMemory {
+    unsafe fn header<'a>(ptr: *mut u8) -> &'a mut SegmentHeader {
+        unsafe { &mut *ptr.sub(HEADER_SIZE).cast::<SegmentHeader>() }
+    }
+}
+
+unsafe impl dlmalloc::Allocator for DosMemory {
+    fn alloc(&self, size: usize) -> (*mut u8, usize, u32) {
+        let Some(total) = size.checked_add(HEADER_SIZE) else {
+            return (ptr::null_mut(), 0, 0);
+        };
+
+        let (base, header) = if let Some(block) = dpmi::allocate_memory(total as u32) {
+            (dpmi::linear_to_ptr(block.linear), SegmentHeader { handle: block.handle, selector: 0 })
         } else {
-            (layout.size() / 8) + 1
+            let paragraphs = total.div_ceil(16);
+            if paragraphs > MAX_DOS_PARAGRAPHS {
+                return (ptr::null_mut(), 0, 0);
+            }
+            match dpmi::allocate_dos_memory(paragraphs as u16) {
+                Ok(block) => (
+                    dpmi::linear_to_ptr(u32::from(block.segment) << 4),
+                    SegmentHeader { handle: 0, selector: block.selector },
+                ),
+                Err(_) => return (ptr::null_mut(), 0, 0),
+            }
         };
-        HEAP_USED.fetch_add(num_blocks, Ordering::Relaxed);
-        let ptr = unsafe { ptr::addr_of_mut!(HEAP_DATA.0[HEAP_USED.load(Ordering::Relaxed) - num_blocks ]) as *mut u8 };
-        ptr
+
+        unsafe {
+            base.cast::<SegmentHeader>().write(header);
+            (base.add(HEADER_SIZE), size, 0)
+        }
     }
 
-    #[inline]
-    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {}
-}
\ No newline at end of file
+    fn remap(&self, ptr: *mut u8, _oldsize: usize, newsize: usize, _can_move: bool) -> *mut u8 {
+        // Conventional memory blocks can grow in place if DOS has room
+        // behind them, just like with INT 21h/4Ah.
+        let header = unsafe { Self::header(ptr) };
+        let paragraphs = newsize.saturating_add(HEADER_SIZE).div_ceil(16);
+        if header.handle == 0
+            && paragraphs <= MAX_DOS_PARAGRAPHS
+            && unsafe { dpmi::resize_dos_memory(header.selector, paragraphs as u16) }
+        {
+            ptr
+        } else {
+            ptr::null_mut()
+        }
+    }
+
+    fn free_part(&self, _ptr: *mut u8, _oldsize: usize, _newsize: usize) -> bool {
+        false
+    }
+
+    fn free(&self, ptr: *mut u8, _size: usize) -> bool {
+        let header = unsafe { Self::header(ptr) };
+        if header.handle != 0 {
+            unsafe { dpmi::free_memory(header.handle) }
+        } else {
+            unsafe { dpmi::free_dos_memory(header.selector) }
+        }
+    }
+
+    fn can_release_part(&self, _flags: u32) -> bool {
+        false
+    }
+
+    fn allocates_zeros(&self) -> bool {
+        false
+    }
+
+    fn page_size(&self) -> usize {
+        4096
+    }
+}
+
+mod lock {
+    use crate::sync::atomic::AtomicI32;
+    use crate::sync::atomic::Ordering::{Acquire, Release};
+
+    static LOCKED: AtomicI32 = AtomicI32::new(0);
+
+    pub struct DropLock;
+
+    pub fn lock() -> DropLock {
+        loop {
+            if LOCKED.swap(1, Acquire) == 0 {
+                return DropLock;
+            }
+            crate::hint::spin_loop();
+        }
+    }
+
+    impl Drop for DropLock {
+        fn drop(&mut self) {
+            let r = LOCKED.swap(0, Release);
+            debug_assert_eq!(r, 1);
+        }
+    }
+}
@@ -1,36 +1,71 @@
+//! Arguments come from the command tail DOS leaves at PSP:0080h.
+//!
+//! DOS passes the command line as one string, so it is split the way
+//! COMMAND.COM users expect: arguments are separated by spaces or tabs, and
+//! double quotes group characters, spaces included, into a single argument.
+//! The quotes themselves are dropped. There is no escape character, since `\`
+//! is the path separator. Each argument is then decoded from the code page.
+
+use super::{codepage, psp};
 use crate::ffi::OsString;
-use crate::fmt;
+use crate::{fmt, vec};
+
+#[cfg(test)]
+mod tests;
 
-pub struct Args {}
+pub struct Args {
+    parsed_args_list: vec::IntoIter<OsString>,
+}
 
 pub fn args() -> Args {
-    Args {}
+    let program = psp::program_path().unwrap_or_default();
+    let mut args = vec![OsString::from(codepage::decode(program))];
+    args.extend(parse_command_tail(psp::command_tail()));
+    Args { parsed_args_list: args.into_iter() }
+}
+
+fn parse_command_tail(tail: &[u8]) -> impl Iterator<Item = OsString> + '_ {
+    let mut bytes = tail.iter().copied().peekable();
+    crate::iter::from_fn(move || {
+        while bytes.next_if(|&b| b == b' ' || b == b'\t').is_some() {}
+        bytes.peek()?;
+
+        let mut arg = Vec::new();
+        let mut in_quotes = false;
+        while let Some(b) = bytes.next_if(|&b| in_quotes || (b != b' ' && b != b'\t')) {
+            match b {
+                b'"' => in_quotes = !in_quotes,
+                b => arg.push(b),
+            }
+        }
+        Some(OsString::from(codepage::decode(&arg)))
+    })
 }
 
 impl fmt::Debug for Args {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_list().finish()
+        self.parsed_args_list.as_slice().fmt(f)
     }
 }
 
 impl Iterator for Args {
     type Item = OsString;
     fn next(&mut self) -> Option<OsString> {
-        None
+        self.parsed_args_list.next()
     }
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (0, Some(0))
+        self.parsed_args_list.size_hint()
     }
 }
 
-impl ExactSizeIterator for Args {
-    fn len(&self) -> usize {
-        0
+impl DoubleEndedIterator for Args {
+    fn next_back(&mut self) -> Option<OsString> {
+        self.parsed_args_list.next_back()
     }
 }
 
-impl DoubleEndedIterator for Args {
-    fn next_back(&mut self) -> Option<OsString> {
-        None
+impl ExactSizeIterator for Args {
+    fn len(&self) -> usize {
+        self.parsed_args_list.len()
     }
 }
@@ -0,0 +1,40 @@
+use super::*;
+
+fn split(tail: &[u8]) -> Vec<OsString> {
+    parse_command_tail(tail).collect()
+}
+
+#[test]
+fn empty_tail() {
+    assert!(split(b"").is_empty());
+    assert!(split(b"  \t ").is_empty());
+}
+
+#[test]
+fn spaces_and_tabs() {
+    // DOS puts a space between the program name and the tail.
+    assert_eq!(split(b" foo bar"), ["foo", "bar"]);
+    assert_eq!(split(b"\tfoo \t bar\t"), ["foo", "bar"]);
+    assert_eq!(split(b"/a /b:c"), ["/a", "/b:c"]);
+}
+
+#[test]
+fn quotes_group_and_are_dropped() {
+    assert_eq!(split(br#" "hello world" x"#), ["hello world", "x"]);
+    assert_eq!(split(br#"a"b c"d"#), ["ab cd"]);
+    assert_eq!(split(br#""" x """#), ["", "x", ""]);
+    // An unterminated quote runs to the end of the tail.
+    assert_eq!(split(b"x \"a b\tc "), ["x", "a b\tc "]);
+}
+
+#[test]
+fn no_escape_character() {
+    assert_eq!(split(br#"C:\DOS\ "a\" b"#), ["C:\\DOS\\", "a\\", "b"]);
+    assert_eq!(split(br#"\"a b\""#), ["\\a b\\"]);
+}
+
+#[test]
+fn arguments_are_decoded() {
+    // From code page 437, which is active until `codepage::init` asks DOS.
+    assert_eq!(split(b"caf\x82 \"\x90T\x90\""), ["café", "ÉTÉ"]);
+}
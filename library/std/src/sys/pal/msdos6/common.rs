@@ -1,12 +1,34 @@
+use super::{codepage, console, ctrl_break, dos, fs, net, os, psp, serial};
 use crate::io as std_io;
 
 // SAFETY: must be called only once during runtime initialization.
 // NOTE: this is not guaranteed to run, for example when Rust code is called externally.
-pub unsafe fn init(_argc: isize, _argv: *const *const u8, _sigpipe: u8) {}
+pub unsafe fn init(_argc: isize, _argv: *const *const u8, _sigpipe: u8) {
+    // `crt0_msdos6.o` passes no `argv`; arguments are read from the PSP instead.
+    unsafe { psp::init() };
+    codepage::init();
+    os::init_environment();
+    fs::init();
+}
 
 // SAFETY: must be called only once during runtime cleanup.
 // NOTE: this is not guaranteed to run, for example when the program aborts.
-pub unsafe fn cleanup() {}
+pub unsafe fn cleanup() {
+    restore_all();
+}
+
+/// Puts back what the program changed outside of itself: interrupt vectors,
+/// the packet driver's receiver, the UARTs and the video mode.
+///
+/// This runs on every way out of the program: from `cleanup` when `main`
+/// returns, and from `dos::exit` for `process::exit` and aborts. Each of
+/// these only acts the first time, so exiting after `cleanup` is fine.
+pub fn restore_all() {
+    ctrl_break::restore();
+    net::shutdown();
+    serial::restore();
+    console::restore();
+}
 
 pub fn unsupported<T>() -> std_io::Result<T> {
     Err(unsupported_err())
@@ -20,10 +42,55 @@ pub fn is_interrupted(_code: i32) -> bool {
     false
 }
 
-pub fn decode_error_kind(_code: i32) -> crate::io::ErrorKind {
-    crate::io::ErrorKind::Uncategorized
+pub fn decode_error_kind(code: i32) -> crate::io::ErrorKind {
+    use crate::io::ErrorKind;
+
+    match code as u16 {
+        dos::ERROR_FILE_NOT_FOUND
+        | dos::ERROR_PATH_NOT_FOUND
+        | dos::ERROR_INVALID_DRIVE
+        | dos::ERROR_BAD_NETPATH
+        | dos::ERROR_BAD_NET_NAME
+        | dos::ERROR_DEV_NOT_EXIST => ErrorKind::NotFound,
+        dos::ERROR_ACCESS_DENIED | dos::ERROR_NETWORK_ACCESS_DENIED => ErrorKind::PermissionDenied,
+        dos::ERROR_FILE_EXISTS => ErrorKind::AlreadyExists,
+        dos::ERROR_SHARING_VIOLATION
+        | dos::ERROR_LOCK_VIOLATION
+        | dos::ERROR_CURRENT_DIRECTORY
+        | dos::ERROR_NOT_READY
+        | dos::ERROR_NETWORK_BUSY => ErrorKind::ResourceBusy,
+        dos::ERROR_INVALID_FUNCTION
+        | dos::ERROR_NOT_SUPPORTED
+        | dos::ERROR_NET_NOT_SUPPORTED
+        | dos::ERROR_MISSING_COMPONENT => ErrorKind::Unsupported,
+        dos::ERROR_NOT_ENOUGH_MEMORY | dos::ERROR_SHARING_BUFFER_EXCEEDED => ErrorKind::OutOfMemory,
+        dos::ERROR_INVALID_HANDLE
+        | dos::ERROR_INVALID_ACCESS
+        | dos::ERROR_BAD_ENVIRONMENT
+        | dos::ERROR_INVALID_PARAMETER => ErrorKind::InvalidInput,
+        dos::ERROR_BAD_FORMAT | dos::ERROR_INVALID_DATA => ErrorKind::InvalidData,
+        dos::ERROR_NOT_SAME_DEVICE => ErrorKind::CrossesDevices,
+        dos::ERROR_WRITE_PROTECT => ErrorKind::ReadOnlyFilesystem,
+        dos::ERROR_HANDLE_DISK_FULL => ErrorKind::StorageFull,
+        dos::ERROR_DISK_QUOTA_EXCEEDED => ErrorKind::QuotaExceeded,
+        dos::ERROR_HANDLE_EOF => ErrorKind::UnexpectedEof,
+        _ => ErrorKind::Uncategorized,
+    }
 }
 
+/// The ERRORLEVEL an aborted program exits with, the same as `abort()` in
+/// the Microsoft C runtime.
+const ABORT_EXIT_CODE: u8 = 3;
+
+// `ud2` would only crash the machine, so abort by ending the process instead.
 pub fn abort_internal() -> ! {
-    core::intrinsics::abort();
+    dos::exit(ABORT_EXIT_CODE)
+}
+
+// This function is needed by the panic runtime.
+#[cfg(not(test))]
+#[no_mangle]
+// NB. used by libpanic_abort
+pub extern "C" fn __rust_abort() {
+    abort_internal();
 }
@@ -0,0 +1,190 @@
+//! The video BIOS (INT 10h) and the text-mode frame buffer.
+
+use super::dos;
+use super::dpmi::{self, RealModeRegisters};
+use crate::io;
+use crate::sync::atomic::AtomicU16;
+use crate::sync::atomic::Ordering::Relaxed;
+
+/// The current video mode, in the BIOS data area.
+const BIOS_MODE: u32 = 0x449;
+/// The number of columns on the screen.
+const BIOS_COLUMNS: u32 = 0x44a;
+/// The offset of the active page in the frame buffer.
+const BIOS_PAGE_OFFSET: u32 = 0x44e;
+/// The number of rows on the screen minus one, kept by EGA and later BIOSes.
+const BIOS_ROWS_MINUS_ONE: u32 = 0x484;
+/// The text-mode frame buffer of colour adapters.
+const COLOR_TEXT_BUFFER: u32 = 0xb8000;
+/// The text-mode frame buffer of the MDA and Hercules cards.
+const MONO_TEXT_BUFFER: u32 = 0xb0000;
+/// The only text mode of monochrome adapters.
+pub const MONO_TEXT_MODE: u8 = 0x07;
+
+// The video mode the program started in, if it has changed it; otherwise
+// `NOT_CHANGED`.
+const NOT_CHANGED: u16 = u16::MAX;
+static SAVED_MODE: AtomicU16 = AtomicU16::new(NOT_CHANGED);
+
+fn int10(mut regs: RealModeRegisters) -> io::Result<RealModeRegisters> {
+    dos::int86(0x10, &mut regs)?;
+    Ok(regs)
+}
+
+fn bios_data<T: Copy>(address: u32) -> T {
+    unsafe { dpmi::linear_to_ptr(address).cast::<T>().read_volatile() }
+}
+
+/// INT 10h/0Fh: returns the current video mode and the active page.
+pub fn video_mode() -> io::Result<(u8, u8)> {
+    let regs = int10(RealModeRegisters { eax: 0x0f00, ..Default::default() })?;
+    // Bit 7 says the screen wasn't cleared when the mode was set.
+    Ok((regs.eax as u8 & 0x7f, (regs.ebx >> 8) as u8))
+}
+
+/// INT 10h/00h: sets the video mode, remembering the one the program started
+/// in so that `restore` can put it back.
+pub fn set_video_mode(mode: u8) -> io::Result<()> {
+    if SAVED_MODE.load(Relaxed) == NOT_CHANGED {
+        SAVED_MODE.store(video_mode()?.0.into(), Relaxed);
+    }
+    int10(RealModeRegisters { eax: u32::from(mode), ..Default::default() })?;
+    Ok(())
+}
+
+/// Puts back the video mode the program started in, if it was changed.
+pub fn restore() {
+    let mode = SAVED_MODE.swap(NOT_CHANGED, Relaxed);
+    if mode != NOT_CHANGED {
+        let _ = int10(RealModeRegisters { eax: u32::from(mode), ..Default::default() });
+    }
+}
+
+/// Returns the number of columns and rows of the screen.
+pub fn size() -> (u16, u16) {
+    let columns = bios_data::<u16>(BIOS_COLUMNS);
+    // CGA and MDA BIOSes don't keep the row count and only have 25.
+    let rows = match bios_data::<u8>(BIOS_ROWS_MINUS_ONE) {
+        0 => 25,
+        rows => u16::from(rows) + 1,
+    };
+    (if columns == 0 { 80 } else { columns }, rows)
+}
+
+/// INT 10h/02h: moves the cursor of the active page.
+pub fn set_cursor_position(row: u8, column: u8) -> io::Result<()> {
+    let page = video_mode()?.1;
+    int10(RealModeRegisters {
+        eax: 0x0200,
+        ebx: u32::from(page) << 8,
+        edx: u32::from(row) << 8 | u32::from(column),
+        ..Default::default()
+    })?;
+    Ok(())
+}
+
+/// INT 10h/03h: returns the row and column of the cursor of the active page.
+pub fn cursor_position() -> io::Result<(u8, u8)> {
+    let page = video_mode()?.1;
+    let regs =
+        int10(RealModeRegisters { eax: 0x0300, ebx: u32::from(page) << 8, ..Default::default() })?;
+    Ok(((regs.edx >> 8) as u8, regs.edx as u8))
+}
+
+/// INT 10h/01h: shows or hides the cursor. A visible cursor gets the usual
+/// underline shape.
+pub fn set_cursor_visible(visible: bool) -> io::Result<()> {
+    let mono = video_mode()?.0 == MONO_TEXT_MODE;
+    let shape = match (visible, mono) {
+        (false, _) => 0x2000,
+        (true, false) => 0x0607,
+        (true, true) => 0x0b0c,
+    };
+    int10(RealModeRegisters { eax: 0x0100, ecx: shape, ..Default::default() })?;
+    Ok(())
+}
+
+/// Writes `bytes` at the cursor with `attribute`, moving the cursor on like
+/// teletype output. CR, LF, BS and BEL act as they do on the console.
+pub fn write_with_attribute(bytes: &[u8], attribute: u8) -> io::Result<()> {
+    let page = video_mode()?.1;
+    for &byte in bytes {
+        if matches!(byte, b'\r' | b'\n' | 0x08 | 0x07) {
+            // INT 10h/0Eh: teletype output, which handles these itself.
+            int10(RealModeRegisters {
+                eax: 0x0e00 | u32::from(byte),
+                ebx: u32::from(page) << 8,
+                ..Default::default()
+            })?;
+            continue;
+        }
+        // INT 10h/09h: write the character and attribute at the cursor, then
+        // advance the cursor, which 09h doesn't. Teletype output of the same
+        // character does that and also wraps and scrolls.
+        int10(RealModeRegisters {
+            eax: 0x0900 | u32::from(byte),
+            ebx: u32::from(page) << 8 | u32::from(attribute),
+            ecx: 1,
+            ..Default::default()
+        })?;
+        int10(RealModeRegisters {
+            eax: 0x0e00 | u32::from(byte),
+            ebx: u32::from(page) << 8,
+            ..Default::default()
+        })?;
+    }
+    Ok(())
+}
+
+/// INT 10h/06h: clears the screen to spaces with `attribute`.
+pub fn clear(attribute: u8) -> io::Result<()> {
+    let (columns, rows) = size();
+    int10(RealModeRegisters {
+        eax: 0x0600,
+        ebx: u32::from(attribute) << 8,
+        ecx: 0,
+        edx: u32::from(rows.min(256) - 1) << 8 | u32::from(columns.min(256) - 1),
+        ..Default::default()
+    })?;
+    Ok(())
+}
+
+/// The text-mode frame buffer of the active page and the size of the screen.
+#[derive(Copy, Clone)]
+pub struct TextScreen {
+    cells: *mut u16,
+    pub columns: u16,
+    pub rows: u16,
+    pub mono: bool,
+}
+
+impl TextScreen {
+    /// Returns the cell at `row` and `column`, or `None` outside the screen.
+    pub fn cell(&self, row: u16, column: u16) -> Option<*mut u16> {
+        (row < self.rows && column < self.columns).then(|| {
+            let index = usize::from(row) * usize::from(self.columns) + usize::from(column);
+            unsafe { self.cells.add(index) }
+        })
+    }
+}
+
+/// Returns the frame buffer of the current mode, or `None` in graphics modes.
+///
+/// This only reads the BIOS data area, so that it is cheap enough to call
+/// before every write to the screen.
+pub fn text_screen() -> Option<TextScreen> {
+    let mode = bios_data::<u8>(BIOS_MODE) & 0x7f;
+    let base = match mode {
+        MONO_TEXT_MODE => MONO_TEXT_BUFFER,
+        0x00..=0x03 => COLOR_TEXT_BUFFER,
+        _ => return None,
+    };
+    let offset = u32::from(bios_data::<u16>(BIOS_PAGE_OFFSET));
+    let (columns, rows) = size();
+    Some(TextScreen {
+        cells: dpmi::linear_to_ptr(base + offset).cast(),
+        columns,
+        rows,
+        mono: mode == MONO_TEXT_MODE,
+    })
+}
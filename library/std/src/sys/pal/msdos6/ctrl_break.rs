@@ -0,0 +1,176 @@
+//! Ctrl-C and Ctrl-Break.
+//!
+//! DOS calls INT 23h when it sees Ctrl-C typed, or Ctrl-Break noted by its
+//! INT 1Bh handler, which the BIOS keyboard handler calls. Without a handler
+//! of ours, DOS ends the program right there.
+//!
+//! The DPMI host reflects INT 23h to its protected-mode vector, where
+//! `__msdos6_int23` either calls a Rust callback or records the press. INT 1Bh
+//! is not reflected, so to see Ctrl-Break without waiting for DOS, a
+//! real-mode callback is put in its real-mode vector. Real-mode vectors are not
+//! restored by the host when we exit, so `restore` has to run on every way out
+//! of the program.
+
+use super::dpmi::{self, RealModeRegisters};
+use crate::arch::{asm, global_asm};
+use crate::io;
+use crate::sync::atomic::Ordering::Relaxed;
+use crate::sync::atomic::{AtomicBool, AtomicPtr, AtomicU16, AtomicU32};
+
+// Set by the handlers when no callback is installed.
+static PENDING: AtomicBool = AtomicBool::new(false);
+// The callback, or null to set `PENDING` instead.
+static CALLBACK: AtomicPtr<()> = AtomicPtr::new(crate::ptr::null_mut());
+
+static INT23_INSTALLED: AtomicBool = AtomicBool::new(false);
+static OLD_INT23_SELECTOR: AtomicU16 = AtomicU16::new(0);
+static OLD_INT23_OFFSET: AtomicU32 = AtomicU32::new(0);
+
+// The real-mode callback in the INT 1Bh vector and the vector it replaced,
+// as `segment << 16 | offset`. Zero if INT 1Bh is not hooked.
+static INT1B_CALLBACK: AtomicU32 = AtomicU32::new(0);
+static OLD_INT1B: AtomicU32 = AtomicU32::new(0);
+// The registers of the interrupted real-mode code while `__msdos6_int1b` runs.
+static mut INT1B_REGS: RealModeRegisters = RealModeRegisters::zeroed();
+
+// The stack `dispatch` runs on. While it is in use, a press is only recorded
+// in `PENDING`.
+static STACK: dpmi::InterruptStack<0x2000> = dpmi::InterruptStack::new();
+
+extern "C" {
+    fn __msdos6_int23();
+    fn __msdos6_int1b();
+}
+
+global_asm!(
+    ".pushsection .text.__msdos6_int23,\"ax\"",
+    ".globl __msdos6_int23",
+    "__msdos6_int23:",
+    "push ds",
+    "push es",
+    "pushad",
+    "lea ebx, [{stack}]",
+    "lea eax, [{dispatch}]",
+    "call __msdos6_interrupt_entry",
+    "test al, al",
+    "jnz 2f",
+    "mov byte ptr [{pending}], 1",
+    "2:",
+    "popad",
+    "pop es",
+    "pop ds",
+    "iretd",
+    ".popsection",
+    ".pushsection .text.__msdos6_int1b,\"ax\"",
+    ".globl __msdos6_int1b",
+    "__msdos6_int1b:",
+    // Entered with DS:ESI pointing to the real-mode stack and ES:EDI to
+    // `INT1B_REGS`. Return to the interrupted code by popping the IP, CS and
+    // flags of its `int` from that stack.
+    "cld",
+    "lodsw",
+    "mov word ptr es:[edi + 0x2a], ax",
+    "lodsw",
+    "mov word ptr es:[edi + 0x2c], ax",
+    "lodsw",
+    "mov word ptr es:[edi + 0x20], ax",
+    "add word ptr es:[edi + 0x2e], 6",
+    "mov byte ptr es:[{pending}], 1",
+    "iretd",
+    ".popsection",
+    stack = sym STACK,
+    dispatch = sym dispatch,
+    pending = sym PENDING,
+);
+
+/// Never reentered, as `STACK` is in use while it runs.
+extern "C" fn dispatch() {
+    let callback = CALLBACK.load(Relaxed);
+    if callback.is_null() {
+        PENDING.store(true, Relaxed);
+    } else {
+        let callback: fn() = unsafe { crate::mem::transmute(callback) };
+        // The host entered us with interrupts disabled; the callback may take a while.
+        unsafe { asm!("sti", options(nomem, nostack)) };
+        callback();
+        unsafe { asm!("cli", options(nomem, nostack)) };
+    }
+}
+
+/// Handles Ctrl-C and Ctrl-Break with `callback`, or by recording them for
+/// [`take_pending`] if it is `None`. A callback runs inside whatever DOS call
+/// was interrupted, so it must not do I/O, take locks or yield.
+pub fn install(callback: Option<fn()>) -> io::Result<()> {
+    CALLBACK.store(callback.map_or(crate::ptr::null_mut(), |f| f as *mut ()), Relaxed);
+
+    if !INT23_INSTALLED.load(Relaxed) {
+        dpmi::init_interrupt_entry();
+        let (selector, offset) = dpmi::protected_mode_vector(0x23);
+        OLD_INT23_SELECTOR.store(selector, Relaxed);
+        OLD_INT23_OFFSET.store(offset, Relaxed);
+        let handler = (dpmi::code_selector(), __msdos6_int23 as usize as u32);
+        if !unsafe { dpmi::set_protected_mode_vector(0x23, handler) } {
+            return Err(io::const_error!(
+                io::ErrorKind::Other,
+                "the DPMI host refused to set the INT 23h vector"
+            ));
+        }
+        INT23_INSTALLED.store(true, Relaxed);
+    }
+
+    // A callback may want to call DOS, which it can't from inside the keyboard
+    // interrupt, so it is left to DOS to turn Ctrl-Break into INT 23h at its
+    // next chance. Only a flag can be set right away.
+    match callback {
+        Some(_) => unhook_int1b(),
+        None => hook_int1b()?,
+    }
+    Ok(())
+}
+
+fn hook_int1b() -> io::Result<()> {
+    if INT1B_CALLBACK.load(Relaxed) != 0 {
+        return Ok(());
+    }
+    let regs = unsafe { &raw mut INT1B_REGS };
+    let Some(callback) = (unsafe { dpmi::allocate_real_mode_callback(__msdos6_int1b, regs) })
+    else {
+        return Err(io::const_error!(
+            io::ErrorKind::OutOfMemory,
+            "the DPMI host has no real-mode callbacks left"
+        ));
+    };
+    let (segment, offset) = dpmi::real_mode_vector(0x1b);
+    OLD_INT1B.store(u32::from(segment) << 16 | u32::from(offset), Relaxed);
+    unsafe { dpmi::set_real_mode_vector(0x1b, callback) };
+    INT1B_CALLBACK.store(u32::from(callback.0) << 16 | u32::from(callback.1), Relaxed);
+    Ok(())
+}
+
+fn unhook_int1b() {
+    let callback = INT1B_CALLBACK.swap(0, Relaxed);
+    if callback == 0 {
+        return;
+    }
+    let old = OLD_INT1B.load(Relaxed);
+    unsafe {
+        dpmi::set_real_mode_vector(0x1b, ((old >> 16) as u16, old as u16));
+        dpmi::free_real_mode_callback(((callback >> 16) as u16, callback as u16));
+    }
+}
+
+/// Returns whether Ctrl-C or Ctrl-Break was pressed since the last call.
+pub fn take_pending() -> bool {
+    PENDING.swap(false, Relaxed)
+}
+
+/// Puts back the vectors we replaced, so that DOS ends the program on
+/// Ctrl-C again.
+pub fn restore() {
+    unhook_int1b();
+    if INT23_INSTALLED.swap(false, Relaxed) {
+        let old = (OLD_INT23_SELECTOR.load(Relaxed), OLD_INT23_OFFSET.load(Relaxed));
+        unsafe { dpmi::set_protected_mode_vector(0x23, old) };
+    }
+    CALLBACK.store(crate::ptr::null_mut(), Relaxed);
+}
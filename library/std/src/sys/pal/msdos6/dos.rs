@@ -0,0 +1,274 @@
+//! Calling DOS from protected mode.
+//!
+//! The DPMI host reflects INT 21h to real mode, but it does not translate the
+//! pointers passed in registers, so DOS can only see conventional memory. Calls
+//! are therefore made through DPMI 0300h with a real-mode register image, and
+//! anything DOS reads or writes is staged in the transfer buffer. The buffer
+//! lives in `.bss`, and like the rest of the image it is loaded below 1 MiB.
+
+use super::codepage;
+use super::dpmi::{self, RealModeRegisters};
+use crate::arch::asm;
+use crate::io;
+use crate::path::Path;
+use crate::sync::atomic::{AtomicU16, Ordering};
+use crate::sync::{Mutex, MutexGuard, PoisonError};
+
+pub const ERROR_INVALID_FUNCTION: u16 = 0x01;
+pub const ERROR_FILE_NOT_FOUND: u16 = 0x02;
+pub const ERROR_PATH_NOT_FOUND: u16 = 0x03;
+pub const ERROR_ACCESS_DENIED: u16 = 0x05;
+pub const ERROR_INVALID_HANDLE: u16 = 0x06;
+pub const ERROR_NOT_ENOUGH_MEMORY: u16 = 0x08;
+pub const ERROR_BAD_ENVIRONMENT: u16 = 0x0a;
+pub const ERROR_BAD_FORMAT: u16 = 0x0b;
+pub const ERROR_INVALID_ACCESS: u16 = 0x0c;
+pub const ERROR_INVALID_DATA: u16 = 0x0d;
+pub const ERROR_INVALID_DRIVE: u16 = 0x0f;
+pub const ERROR_CURRENT_DIRECTORY: u16 = 0x10;
+pub const ERROR_NOT_SAME_DEVICE: u16 = 0x11;
+pub const ERROR_NO_MORE_FILES: u16 = 0x12;
+pub const ERROR_WRITE_PROTECT: u16 = 0x13;
+pub const ERROR_NOT_READY: u16 = 0x15;
+pub const ERROR_SHARING_VIOLATION: u16 = 0x20;
+pub const ERROR_LOCK_VIOLATION: u16 = 0x21;
+pub const ERROR_SHARING_BUFFER_EXCEEDED: u16 = 0x24;
+pub const ERROR_HANDLE_EOF: u16 = 0x26;
+pub const ERROR_HANDLE_DISK_FULL: u16 = 0x27;
+pub const ERROR_NOT_SUPPORTED: u16 = 0x32;
+pub const ERROR_BAD_NETPATH: u16 = 0x35;
+pub const ERROR_NETWORK_BUSY: u16 = 0x36;
+pub const ERROR_DEV_NOT_EXIST: u16 = 0x37;
+pub const ERROR_NETWORK_ACCESS_DENIED: u16 = 0x41;
+pub const ERROR_BAD_NET_NAME: u16 = 0x43;
+pub const ERROR_DISK_QUOTA_EXCEEDED: u16 = 0x4d;
+pub const ERROR_FILE_EXISTS: u16 = 0x50;
+pub const ERROR_INVALID_PARAMETER: u16 = 0x57;
+pub const ERROR_NET_NOT_SUPPORTED: u16 = 0x59;
+pub const ERROR_MISSING_COMPONENT: u16 = 0x5a;
+
+/// The carry flag, which DOS sets when a call fails.
+pub const CARRY_FLAG: u16 = 0x0001;
+
+// The error code of the last DOS call that failed, for `os::errno`.
+static LAST_ERROR: AtomicU16 = AtomicU16::new(0);
+
+/// Calls INT 21h with `regs`, turning a set carry flag into a DOS error code.
+///
+/// The error code in AX is only the one DOS 2 knew about, so the extended
+/// error is asked for with INT 21h/59h. That tells apart, for example, a
+/// sharing violation from a file that is read-only, where AX says "access
+/// denied" for both. `regs` is left as the failed call returned it.
+pub fn int21(regs: &mut RealModeRegisters) -> io::Result<()> {
+    int86(0x21, regs)?;
+    if regs.flags & CARRY_FLAG != 0 {
+        let code = match extended_error() {
+            0 => regs.eax as u16,
+            code => code,
+        };
+        LAST_ERROR.store(code, Ordering::Relaxed);
+        return Err(io::Error::from_raw_os_error(code.into()));
+    }
+    Ok(())
+}
+
+/// INT 21h/59h: returns the extended error code of the last failed call, or
+/// zero if it can't be had.
+fn extended_error() -> u16 {
+    let mut regs = RealModeRegisters { eax: 0x5900, ..Default::default() };
+    match int86(0x21, &mut regs) {
+        Ok(()) => regs.eax as u16,
+        Err(_) => 0,
+    }
+}
+
+/// Returns the error code of the last DOS call that failed.
+pub fn last_error() -> u16 {
+    LAST_ERROR.load(Ordering::Relaxed)
+}
+
+/// Calls real-mode interrupt `interrupt` with `regs`.
+pub fn int86(interrupt: u8, regs: &mut RealModeRegisters) -> io::Result<()> {
+    if !dpmi::simulate_real_mode_interrupt(interrupt, regs) {
+        return Err(io::const_error!(
+            io::ErrorKind::Other,
+            "the DPMI host failed to call real mode"
+        ));
+    }
+    Ok(())
+}
+
+/// INT 21h/4Ch: ends the process with `code` as its ERRORLEVEL.
+///
+/// Unlike other DOS calls this is made from protected mode, so that the DPMI
+/// host can clean up after its client before passing the call on to DOS.
+///
+/// What the program changed outside of itself is put back first, with
+/// `common::restore_all`.
+pub fn exit(code: u8) -> ! {
+    super::common::restore_all();
+    unsafe { asm!("int 0x21", in("eax") 0x4c00 | u32::from(code), options(noreturn)) }
+}
+
+pub const TRANSFER_BUFFER_SIZE: usize = 0x4000;
+
+#[repr(C, align(16))]
+pub struct TransferBuffer {
+    data: [u8; TRANSFER_BUFFER_SIZE],
+}
+
+static TRANSFER_BUFFER: Mutex<TransferBuffer> =
+    Mutex::new(TransferBuffer { data: [0; TRANSFER_BUFFER_SIZE] });
+
+/// Locks the transfer buffer for the duration of a DOS call.
+pub fn transfer_buffer() -> MutexGuard<'static, TransferBuffer> {
+    TRANSFER_BUFFER.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl TransferBuffer {
+    /// The real-mode segment of the buffer. Its offset is always 0.
+    pub fn segment(&self) -> u16 {
+        (dpmi::ptr_to_linear(self.data.as_ptr()) >> 4) as u16
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Copies `bytes`, those of an `OsStr`, into the buffer at `offset` in
+    /// the code page with a terminating NUL, as DOS expects for paths.
+    pub fn put_asciiz(&mut self, offset: usize, bytes: &[u8]) -> io::Result<u16> {
+        let bytes = codepage::encode(bytes)?;
+        if bytes.contains(&0) {
+            return Err(io::const_error!(
+                io::ErrorKind::InvalidInput,
+                "path must not contain interior null bytes",
+            ));
+        }
+        let Some(dest) = self.data.get_mut(offset..offset + bytes.len() + 1) else {
+            return Err(io::const_error!(io::ErrorKind::InvalidFilename, "path is too long"));
+        };
+        dest[..bytes.len()].copy_from_slice(&bytes);
+        dest[bytes.len()] = 0;
+        Ok(offset as u16)
+    }
+
+    /// Reads a NUL-terminated string DOS left in the buffer at `offset`.
+    pub fn get_asciiz(&self, offset: usize) -> &[u8] {
+        let bytes = &self.data[offset..];
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        &bytes[..len]
+    }
+}
+
+/// Runs an INT 21h function that takes an ASCIIZ path in DS:DX.
+pub fn path_call(path: &Path, mut regs: RealModeRegisters) -> io::Result<RealModeRegisters> {
+    let mut transfer = transfer_buffer();
+    regs.edx = transfer.put_asciiz(0, path.as_os_str().as_encoded_bytes())?.into();
+    regs.ds = transfer.segment();
+    int21(&mut regs)?;
+    Ok(regs)
+}
+
+/// INT 21h/3Fh: reads from a handle into `buf`, at most one transfer buffer at a time.
+pub fn read(handle: u16, buf: &mut [u8]) -> io::Result<usize> {
+    let mut transfer = transfer_buffer();
+    let len = buf.len().min(TRANSFER_BUFFER_SIZE);
+    let mut regs = RealModeRegisters {
+        eax: 0x3f00,
+        ebx: handle.into(),
+        ecx: len as u32,
+        ds: transfer.segment(),
+        ..Default::default()
+    };
+    int21(&mut regs)?;
+    let read = (regs.eax as u16 as usize).min(len);
+    buf[..read].copy_from_slice(&transfer.as_slice()[..read]);
+    Ok(read)
+}
+
+/// INT 21h/40h: writes `buf` to a handle, at most one transfer buffer at a time.
+pub fn write(handle: u16, buf: &[u8]) -> io::Result<usize> {
+    // A zero-length write truncates the file at the current position.
+    if buf.is_empty() {
+        return Ok(0);
+    }
+    let mut transfer = transfer_buffer();
+    let len = buf.len().min(TRANSFER_BUFFER_SIZE);
+    transfer.as_mut_slice()[..len].copy_from_slice(&buf[..len]);
+    let mut regs = RealModeRegisters {
+        eax: 0x4000,
+        ebx: handle.into(),
+        ecx: len as u32,
+        ds: transfer.segment(),
+        ..Default::default()
+    };
+    int21(&mut regs)?;
+    Ok(regs.eax as u16 as usize)
+}
+
+/// INT 21h/40h with a length of zero: truncates or extends the file to the
+/// current position.
+pub fn truncate_here(handle: u16) -> io::Result<()> {
+    let mut regs = RealModeRegisters { eax: 0x4000, ebx: handle.into(), ..Default::default() };
+    int21(&mut regs)
+}
+
+/// INT 21h/42h: moves the file pointer of a handle, returning the new position.
+///
+/// `whence` is 0 for the start of the file, 1 for the current position and 2
+/// for the end.
+pub fn seek(handle: u16, offset: u32, whence: u8) -> io::Result<u32> {
+    let mut regs = RealModeRegisters {
+        eax: 0x4200 | u32::from(whence),
+        ebx: handle.into(),
+        ecx: offset >> 16,
+        edx: offset & 0xffff,
+        ..Default::default()
+    };
+    int21(&mut regs)?;
+    Ok(((regs.edx & 0xffff) << 16) | (regs.eax & 0xffff))
+}
+
+/// INT 21h/3Eh: closes a handle.
+pub fn close(handle: u16) -> io::Result<()> {
+    let mut regs = RealModeRegisters { eax: 0x3e00, ebx: handle.into(), ..Default::default() };
+    int21(&mut regs)
+}
+
+/// INT 21h/45h: duplicates a handle.
+pub fn duplicate(handle: u16) -> io::Result<u16> {
+    let mut regs = RealModeRegisters { eax: 0x4500, ebx: handle.into(), ..Default::default() };
+    int21(&mut regs)?;
+    Ok(regs.eax as u16)
+}
+
+/// INT 21h/46h: makes `target` refer to the same file as `handle`, closing
+/// whatever `target` referred to before.
+pub fn force_duplicate(handle: u16, target: u16) -> io::Result<()> {
+    let mut regs = RealModeRegisters {
+        eax: 0x4600,
+        ebx: handle.into(),
+        ecx: target.into(),
+        ..Default::default()
+    };
+    int21(&mut regs)
+}
+
+/// INT 21h/4400h: returns the device information word of a handle.
+pub fn device_info(handle: u16) -> io::Result<u16> {
+    let mut regs = RealModeRegisters { eax: 0x4400, ebx: handle.into(), ..Default::default() };
+    int21(&mut regs)?;
+    Ok(regs.edx as u16)
+}
+
+/// Set in the device information word if the handle refers to a character device.
+pub const DEVICE_INFO_IS_DEVICE: u16 = 0x0080;
+/// Set for a character device that is the console input device.
+pub const DEVICE_INFO_CONSOLE_INPUT: u16 = 0x0001;
+/// Set for a character device that is the console output device.
+pub const DEVICE_INFO_CONSOLE_OUTPUT: u16 = 0x0002;
@@ -0,0 +1,451 @@
+//! Services of the DPMI host msdos6 programs run under.
+//!
+//! The MZ stub emitted by rustc enters protected mode as a 32-bit DPMI client
+//! and jumps to `_start` in `crt0_msdos6.o` with flat code and data selectors.
+//! Their base is the linear address of link address 0 and their limit is
+//! 4 GiB, so any linear address can be reached by subtracting that base.
+
+use crate::arch::{asm, global_asm};
+use crate::cell::UnsafeCell;
+use crate::mem;
+use crate::sync::atomic::Ordering::Relaxed;
+use crate::sync::atomic::{AtomicBool, AtomicU16};
+
+extern "C" {
+    // Recorded by `_start` in `crt0_msdos6.o`.
+    static __msdos6_image_base: u32;
+}
+
+/// Turns a linear address into a pointer that can be used with our flat selectors.
+pub fn linear_to_ptr(linear: u32) -> *mut u8 {
+    linear.wrapping_sub(unsafe { __msdos6_image_base }) as usize as *mut u8
+}
+
+/// Turns a pointer into the linear address it refers to.
+pub fn ptr_to_linear(ptr: *const u8) -> u32 {
+    (ptr as usize as u32).wrapping_add(unsafe { __msdos6_image_base })
+}
+
+/// A block of linear memory allocated with DPMI function 0501h.
+#[derive(Copy, Clone)]
+pub struct MemoryBlock {
+    pub linear: u32,
+    pub handle: u32,
+}
+
+/// DPMI 0501h: allocates `size` bytes of linear memory, usually from extended memory.
+pub fn allocate_memory(size: u32) -> Option<MemoryBlock> {
+    let (failed, bx, cx, si, di): (u8, u32, u32, u32, u32);
+    unsafe {
+        asm!(
+            // `esi` is reserved by LLVM, so swap it with a scratch register.
+            "xchg esi, {si}",
+            "int 0x31",
+            "setc {failed}",
+            "xchg esi, {si}",
+            failed = out(reg_byte) failed,
+            si = inout(reg) 0 => si,
+            inout("eax") 0x0501 => _,
+            inout("ebx") size >> 16 => bx,
+            inout("ecx") size & 0xffff => cx,
+            out("edi") di,
+        );
+    }
+    if failed != 0 {
+        return None;
+    }
+    Some(MemoryBlock { linear: (bx << 16) | (cx & 0xffff), handle: (si << 16) | (di & 0xffff) })
+}
+
+/// DPMI 0502h: frees a block returned by [`allocate_memory`].
+pub unsafe fn free_memory(handle: u32) -> bool {
+    let failed: u8;
+    unsafe {
+        asm!(
+            "xchg esi, {si}",
+            "int 0x31",
+            "setc {failed}",
+            "xchg esi, {si}",
+            failed = out(reg_byte) failed,
+            si = inout(reg) handle >> 16 => _,
+            inout("eax") 0x0502 => _,
+            in("edi") handle & 0xffff,
+        );
+    }
+    failed == 0
+}
+
+/// A block of conventional memory allocated with DPMI function 0100h.
+#[derive(Copy, Clone)]
+pub struct DosMemoryBlock {
+    /// Real-mode segment of the block.
+    pub segment: u16,
+    /// Selector the host created for the block.
+    pub selector: u16,
+}
+
+/// DPMI 0100h: allocates conventional memory, like INT 21h/48h does in real mode.
+///
+/// On failure, returns the size of the largest available block in paragraphs.
+pub fn allocate_dos_memory(paragraphs: u16) -> Result<DosMemoryBlock, u16> {
+    let (failed, ax, bx, dx): (u8, u32, u32, u32);
+    unsafe {
+        asm!(
+            "int 0x31",
+            "setc {failed}",
+            failed = out(reg_byte) failed,
+            inout("eax") 0x0100 => ax,
+            inout("ebx") u32::from(paragraphs) => bx,
+            out("edx") dx,
+        );
+    }
+    if failed != 0 {
+        return Err(bx as u16);
+    }
+    Ok(DosMemoryBlock { segment: ax as u16, selector: dx as u16 })
+}
+
+/// DPMI 0101h: frees conventional memory, like INT 21h/49h does in real mode.
+pub unsafe fn free_dos_memory(selector: u16) -> bool {
+    let failed: u8;
+    unsafe {
+        asm!(
+            "int 0x31",
+            "setc {failed}",
+            failed = out(reg_byte) failed,
+            inout("eax") 0x0101 => _,
+            in("edx") u32::from(selector),
+        );
+    }
+    failed == 0
+}
+
+/// DPMI 0102h: resizes conventional memory in place, like INT 21h/4Ah does in real mode.
+pub unsafe fn resize_dos_memory(selector: u16, paragraphs: u16) -> bool {
+    let failed: u8;
+    unsafe {
+        asm!(
+            "int 0x31",
+            "setc {failed}",
+            failed = out(reg_byte) failed,
+            inout("eax") 0x0102 => _,
+            inout("ebx") u32::from(paragraphs) => _,
+            in("edx") u32::from(selector),
+        );
+    }
+    failed == 0
+}
+
+/// DPMI 0006h: returns the linear base address of the segment `selector` refers to.
+pub fn segment_base(selector: u16) -> Option<u32> {
+    let (failed, cx, dx): (u8, u32, u32);
+    unsafe {
+        asm!(
+            "int 0x31",
+            "setc {failed}",
+            failed = out(reg_byte) failed,
+            inout("eax") 0x0006 => _,
+            in("ebx") u32::from(selector),
+            out("ecx") cx,
+            out("edx") dx,
+        );
+    }
+    if failed != 0 {
+        return None;
+    }
+    Some((cx << 16) | (dx & 0xffff))
+}
+
+/// Returns the limit of the segment `selector` refers to, as read with `lsl`.
+pub fn segment_limit(selector: u16) -> Option<u32> {
+    let (valid, limit): (u8, u32);
+    unsafe {
+        asm!(
+            "lsl {limit}, {selector:e}",
+            "setz {valid}",
+            limit = out(reg) limit,
+            selector = in(reg) u32::from(selector),
+            valid = out(reg_byte) valid,
+            options(nomem, nostack),
+        );
+    }
+    if valid != 0 { Some(limit) } else { None }
+}
+
+/// The register image DPMI 0300h loads before switching to real mode, and
+/// fills in again once the interrupt handler returns.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RealModeRegisters {
+    pub edi: u32,
+    pub esi: u32,
+    pub ebp: u32,
+    reserved: u32,
+    pub ebx: u32,
+    pub edx: u32,
+    pub ecx: u32,
+    pub eax: u32,
+    pub flags: u16,
+    pub es: u16,
+    pub ds: u16,
+    pub fs: u16,
+    pub gs: u16,
+    pub ip: u16,
+    pub cs: u16,
+    /// Left at zero, so the host provides a real-mode stack.
+    pub sp: u16,
+    pub ss: u16,
+}
+
+impl RealModeRegisters {
+    /// All registers zero, for use in statics.
+    pub const fn zeroed() -> RealModeRegisters {
+        RealModeRegisters {
+            edi: 0,
+            esi: 0,
+            ebp: 0,
+            reserved: 0,
+            ebx: 0,
+            edx: 0,
+            ecx: 0,
+            eax: 0,
+            flags: 0,
+            es: 0,
+            ds: 0,
+            fs: 0,
+            gs: 0,
+            ip: 0,
+            cs: 0,
+            sp: 0,
+            ss: 0,
+        }
+    }
+}
+
+/// DPMI 0300h: runs real-mode interrupt `interrupt` with `regs`.
+pub fn simulate_real_mode_interrupt(interrupt: u8, regs: &mut RealModeRegisters) -> bool {
+    let failed: u8;
+    unsafe {
+        asm!(
+            "int 0x31",
+            "setc {failed}",
+            failed = out(reg_byte) failed,
+            inout("eax") 0x0300 => _,
+            inout("ebx") u32::from(interrupt) => _,
+            inout("ecx") 0 => _,
+            in("edi") regs as *mut RealModeRegisters,
+        );
+    }
+    failed == 0
+}
+
+/// DPMI 0200h: returns the real-mode interrupt vector `interrupt` as a
+/// `(segment, offset)` pair.
+pub fn real_mode_vector(interrupt: u8) -> (u16, u16) {
+    let (cx, dx): (u32, u32);
+    unsafe {
+        asm!(
+            "int 0x31",
+            inout("eax") 0x0200 => _,
+            in("ebx") u32::from(interrupt),
+            out("ecx") cx,
+            out("edx") dx,
+        );
+    }
+    (cx as u16, dx as u16)
+}
+
+/// DPMI 0201h: sets the real-mode interrupt vector `interrupt`.
+pub unsafe fn set_real_mode_vector(interrupt: u8, (segment, offset): (u16, u16)) {
+    unsafe {
+        asm!(
+            "int 0x31",
+            inout("eax") 0x0201 => _,
+            in("ebx") u32::from(interrupt),
+            in("ecx") u32::from(segment),
+            in("edx") u32::from(offset),
+        );
+    }
+}
+
+/// DPMI 0204h: returns the protected-mode interrupt vector `interrupt` as a
+/// `(selector, offset)` pair.
+pub fn protected_mode_vector(interrupt: u8) -> (u16, u32) {
+    let (cx, edx): (u32, u32);
+    unsafe {
+        asm!(
+            "int 0x31",
+            inout("eax") 0x0204 => _,
+            in("ebx") u32::from(interrupt),
+            out("ecx") cx,
+            out("edx") edx,
+        );
+    }
+    (cx as u16, edx)
+}
+
+/// DPMI 0205h: sets the protected-mode interrupt vector `interrupt`.
+pub unsafe fn set_protected_mode_vector(interrupt: u8, (selector, offset): (u16, u32)) -> bool {
+    let failed: u8;
+    unsafe {
+        asm!(
+            "int 0x31",
+            "setc {failed}",
+            failed = out(reg_byte) failed,
+            inout("eax") 0x0205 => _,
+            in("ebx") u32::from(interrupt),
+            in("ecx") u32::from(selector),
+            in("edx") offset,
+        );
+    }
+    failed == 0
+}
+
+/// DPMI 0303h: allocates a real-mode callback, a real-mode address that
+/// calls `procedure` in protected mode, with `regs` holding the real-mode
+/// registers. Returns the address as a `(segment, offset)` pair.
+///
+/// `procedure` is entered with DS:ESI pointing to the real-mode stack and
+/// ES:EDI to `regs`, and has to return with `iretd`.
+pub unsafe fn allocate_real_mode_callback(
+    procedure: unsafe extern "C" fn(),
+    regs: *mut RealModeRegisters,
+) -> Option<(u16, u16)> {
+    let (failed, cx, dx): (u8, u32, u32);
+    unsafe {
+        asm!(
+            // DS:ESI is the procedure, so DS has to be our code selector for
+            // the call. Nothing in between may touch memory.
+            "push ds",
+            "mov {scratch:e}, cs",
+            "mov ds, {scratch:e}",
+            "xchg esi, {si}",
+            "int 0x31",
+            "setc {failed}",
+            "xchg esi, {si}",
+            "pop ds",
+            failed = out(reg_byte) failed,
+            scratch = out(reg) _,
+            si = inout(reg) procedure as usize => _,
+            inout("eax") 0x0303 => _,
+            out("ecx") cx,
+            out("edx") dx,
+            in("edi") regs,
+        );
+    }
+    if failed != 0 {
+        return None;
+    }
+    Some((cx as u16, dx as u16))
+}
+
+/// DPMI 0304h: frees a callback returned by [`allocate_real_mode_callback`].
+pub unsafe fn free_real_mode_callback((segment, offset): (u16, u16)) {
+    unsafe {
+        asm!(
+            "int 0x31",
+            inout("eax") 0x0304 => _,
+            in("ecx") u32::from(segment),
+            in("edx") u32::from(offset),
+        );
+    }
+}
+
+/// Returns the selector in CS.
+pub fn code_selector() -> u16 {
+    let cs: u32;
+    unsafe { asm!("mov {0:e}, cs", out(reg) cs, options(nomem, nostack, preserves_flags)) };
+    cs as u16
+}
+
+/// Returns the selector in DS.
+pub fn data_selector() -> u16 {
+    let ds: u32;
+    unsafe { asm!("mov {0:e}, ds", out(reg) ds, options(nomem, nostack, preserves_flags)) };
+    ds as u16
+}
+
+// Our data selector, for `__msdos6_interrupt_entry` to load. The host enters
+// interrupt handlers and real-mode callbacks with its own.
+static DATA_SELECTOR: AtomicU16 = AtomicU16::new(0);
+
+/// A stack for an interrupt handler that runs Rust code, as the one the host
+/// enters it on is in a segment of its own.
+///
+/// Handlers are entered through `__msdos6_interrupt_entry`, from a stub that
+/// saves every register, sets EBX to the `InterruptStack`, EAX to an
+/// `extern "C"` function taking one 32-bit argument and EDX to that
+/// argument, and calls it. It switches to our data segment and to the stack,
+/// runs the function there, and switches back, returning AL = 1. If a handler
+/// is still running on the stack, the function isn't called and AL is 0.
+/// [`init_interrupt_entry`] has to be called before any such stub can run.
+#[repr(C, align(16))]
+pub struct InterruptStack<const SIZE: usize> {
+    /// The host's ESP and SS while a handler runs, in the layout `lss` expects.
+    saved: UnsafeCell<[u32; 2]>,
+    /// The end of `stack`, from the start of the `InterruptStack`.
+    top: u32,
+    /// Set while a handler runs on `stack`.
+    busy: AtomicBool,
+    stack: UnsafeCell<StackBytes<SIZE>>,
+}
+
+#[repr(align(16))]
+struct StackBytes<const SIZE: usize>([u8; SIZE]);
+
+// SAFETY: only `__msdos6_interrupt_entry` uses the stack, and `busy` keeps
+// it to one handler at a time.
+unsafe impl<const SIZE: usize> Sync for InterruptStack<SIZE> {}
+
+impl<const SIZE: usize> InterruptStack<SIZE> {
+    pub const fn new() -> InterruptStack<SIZE> {
+        const { assert!(SIZE % 16 == 0) };
+        InterruptStack {
+            saved: UnsafeCell::new([0; 2]),
+            top: (mem::offset_of!(Self, stack) + SIZE) as u32,
+            busy: AtomicBool::new(false),
+            stack: UnsafeCell::new(StackBytes([0; SIZE])),
+        }
+    }
+}
+
+/// Records our data selector for `__msdos6_interrupt_entry`.
+pub fn init_interrupt_entry() {
+    DATA_SELECTOR.store(data_selector(), Relaxed);
+}
+
+global_asm!(
+    ".pushsection .text.__msdos6_interrupt_entry,\"ax\"",
+    ".globl __msdos6_interrupt_entry",
+    "__msdos6_interrupt_entry:",
+    "mov cx, word ptr cs:[{data_selector}]",
+    "mov ds, cx",
+    "mov es, cx",
+    "mov cl, 1",
+    "xchg byte ptr [ebx + {busy}], cl",
+    "test cl, cl",
+    "jnz 2f",
+    "mov dword ptr [ebx + {saved}], esp",
+    "mov word ptr [ebx + {saved} + 4], ss",
+    "mov cx, ds",
+    "mov ss, cx",
+    "mov ecx, dword ptr [ebx + {top}]",
+    // Keep the stack aligned to 16 bytes at the call.
+    "lea esp, [ebx + ecx - 12]",
+    "cld",
+    "push edx",
+    // EBX is preserved across the call.
+    "call eax",
+    "lss esp, [ebx + {saved}]",
+    "mov byte ptr [ebx + {busy}], 0",
+    "mov al, 1",
+    "ret",
+    "2:",
+    "xor al, al",
+    "ret",
+    ".popsection",
+    data_selector = sym DATA_SELECTOR,
+    saved = const mem::offset_of!(InterruptStack<0>, saved),
+    top = const mem::offset_of!(InterruptStack<0>, top),
+    busy = const mem::offset_of!(InterruptStack<0>, busy),
+);
@@ -1,144 +1,426 @@
+use super::codepage;
+use super::dos::{self, RealModeRegisters};
 use crate::ffi::OsString;
 use crate::fmt;
-use crate::hash::{Hash, Hasher};
 use crate::io::{self, BorrowedCursor, IoSlice, IoSliceMut, SeekFrom};
 use crate::path::{Path, PathBuf};
+use crate::sync::Arc;
+use crate::sync::atomic::AtomicBool;
+use crate::sync::atomic::Ordering::Relaxed;
+use crate::sys::thread::Thread;
 use crate::sys::time::SystemTime;
 use crate::sys::unsupported;
-
-pub struct File(!);
-
-pub struct FileAttr(!);
-
-pub struct ReadDir(!);
-
-pub struct DirEntry(!);
+use crate::sys_common::AsInner;
+
+pub const ATTRIBUTE_READONLY: u8 = 0x01;
+pub const ATTRIBUTE_HIDDEN: u8 = 0x02;
+pub const ATTRIBUTE_SYSTEM: u8 = 0x04;
+pub const ATTRIBUTE_VOLUME_LABEL: u8 = 0x08;
+pub const ATTRIBUTE_DIRECTORY: u8 = 0x10;
+pub const ATTRIBUTE_ARCHIVE: u8 = 0x20;
+/// Never stored on disk; DOS uses it for character devices.
+pub const ATTRIBUTE_DEVICE: u8 = 0x40;
+
+const ACCESS_READ: u8 = 0x00;
+const ACCESS_WRITE: u8 = 0x01;
+const ACCESS_READ_WRITE: u8 = 0x02;
+
+pub const SHARE_COMPATIBILITY: u8 = 0x00;
+pub const SHARE_DENY_ALL: u8 = 0x10;
+pub const SHARE_DENY_WRITE: u8 = 0x20;
+pub const SHARE_DENY_READ: u8 = 0x30;
+pub const SHARE_DENY_NONE: u8 = 0x40;
+
+pub struct File {
+    handle: u16,
+    // DOS has no append mode, so every write seeks to the end first.
+    append: bool,
+}
+
+#[derive(Clone)]
+pub struct FileAttr {
+    size: u64,
+    attributes: u8,
+    modified: DosTime,
+    // Only recorded by the long file name API.
+    accessed: DosTime,
+    created: DosTime,
+}
+
+/// A packed DOS date and time; a date of 0 means it was not recorded.
+#[derive(Copy, Clone, Default)]
+struct DosTime {
+    date: u16,
+    time: u16,
+}
+
+pub struct ReadDir {
+    root: Arc<PathBuf>,
+    search: Option<Search>,
+    // The entry the search started with, which has not been returned yet.
+    pending: Option<Found>,
+}
+
+pub struct DirEntry {
+    root: Arc<PathBuf>,
+    found: Found,
+}
+
+/// A directory search with FindFirst/FindNext, or with their long file name
+/// counterparts when DOS supports them.
+enum Search {
+    /// The disk transfer area the short name calls fill in. Its first 21
+    /// bytes are the state DOS keeps between calls.
+    Short([u8; DTA_SIZE]),
+    /// The search handle from INT 21h/714Eh.
+    Long(u16),
+}
+
+/// A file a search found.
+struct Found {
+    name: Vec<u8>,
+    short_name: Vec<u8>,
+    attr: FileAttr,
+}
+
+const DTA_SIZE: usize = 43;
+/// Size of the record the long file name search fills in.
+const FIND_DATA_SIZE: usize = 318;
+/// Where search results go in the transfer buffer, out of the way of the pattern.
+const FIND_RESULT_OFFSET: usize = 0;
+const FIND_PATTERN_OFFSET: usize = 0x200;
+/// Everything but volume labels.
+const SEARCH_ATTRIBUTES: u8 = ATTRIBUTE_HIDDEN | ATTRIBUTE_SYSTEM | ATTRIBUTE_DIRECTORY;
 
 #[derive(Clone, Debug)]
-pub struct OpenOptions {}
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+    share_mode: u8,
+    attributes: u8,
+}
 
 #[derive(Copy, Clone, Debug, Default)]
-pub struct FileTimes {}
+pub struct FileTimes {
+    modified: Option<SystemTime>,
+}
 
-pub struct FilePermissions(!);
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FilePermissions {
+    attributes: u8,
+}
 
-pub struct FileType(!);
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct FileType {
+    attributes: u8,
+}
 
 #[derive(Debug)]
 pub struct DirBuilder {}
 
 impl FileAttr {
+    fn new(size: u64, attributes: u8, modified: DosTime) -> FileAttr {
+        FileAttr {
+            size,
+            attributes,
+            modified,
+            accessed: DosTime::default(),
+            created: DosTime::default(),
+        }
+    }
+
     pub fn size(&self) -> u64 {
-        self.0
+        self.size
     }
 
     pub fn perm(&self) -> FilePermissions {
-        self.0
+        FilePermissions { attributes: self.attributes }
     }
 
     pub fn file_type(&self) -> FileType {
-        self.0
+        FileType { attributes: self.attributes }
     }
 
     pub fn modified(&self) -> io::Result<SystemTime> {
-        self.0
+        self.modified.to_system_time()
     }
 
     pub fn accessed(&self) -> io::Result<SystemTime> {
-        self.0
+        self.accessed.to_system_time()
     }
 
     pub fn created(&self) -> io::Result<SystemTime> {
-        self.0
+        self.created.to_system_time()
     }
-}
 
-impl Clone for FileAttr {
-    fn clone(&self) -> FileAttr {
-        self.0
+    pub fn attributes(&self) -> u8 {
+        self.attributes
     }
-}
 
-impl FilePermissions {
-    pub fn readonly(&self) -> bool {
-        self.0
+    /// The packed DOS date and time of the last write.
+    pub fn dos_modified(&self) -> (u16, u16) {
+        (self.modified.date, self.modified.time)
     }
 
-    pub fn set_readonly(&mut self, _readonly: bool) {
-        self.0
+    /// The packed DOS date and time of the last access. Only the date is
+    /// recorded, and only by the long file name API.
+    pub fn dos_accessed(&self) -> (u16, u16) {
+        (self.accessed.date, self.accessed.time)
     }
-}
 
-impl Clone for FilePermissions {
-    fn clone(&self) -> FilePermissions {
-        self.0
+    /// The packed DOS date and time of creation, recorded only by the long
+    /// file name API.
+    pub fn dos_created(&self) -> (u16, u16) {
+        (self.created.date, self.created.time)
     }
 }
 
-impl PartialEq for FilePermissions {
-    fn eq(&self, _other: &FilePermissions) -> bool {
-        self.0
+impl DosTime {
+    fn to_system_time(self) -> io::Result<SystemTime> {
+        if self.date == 0 {
+            return unsupported();
+        }
+        SystemTime::from_dos(self.date, self.time)
+            .ok_or(io::const_error!(io::ErrorKind::InvalidData, "invalid DOS timestamp"))
     }
 }
 
-impl Eq for FilePermissions {}
+impl FilePermissions {
+    pub fn readonly(&self) -> bool {
+        self.attributes & ATTRIBUTE_READONLY != 0
+    }
 
-impl fmt::Debug for FilePermissions {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0
+    pub fn set_readonly(&mut self, readonly: bool) {
+        if readonly {
+            self.attributes |= ATTRIBUTE_READONLY;
+        } else {
+            self.attributes &= !ATTRIBUTE_READONLY;
+        }
     }
 }
 
 impl FileTimes {
     pub fn set_accessed(&mut self, _t: SystemTime) {}
-    pub fn set_modified(&mut self, _t: SystemTime) {}
+    pub fn set_modified(&mut self, t: SystemTime) {
+        self.modified = Some(t);
+    }
 }
 
 impl FileType {
     pub fn is_dir(&self) -> bool {
-        self.0
+        self.attributes & ATTRIBUTE_DIRECTORY != 0
     }
 
     pub fn is_file(&self) -> bool {
-        self.0
+        self.attributes & (ATTRIBUTE_DIRECTORY | ATTRIBUTE_VOLUME_LABEL | ATTRIBUTE_DEVICE) == 0
     }
 
     pub fn is_symlink(&self) -> bool {
-        self.0
+        false
     }
-}
 
-impl Clone for FileType {
-    fn clone(&self) -> FileType {
-        self.0
+    pub fn is_char_device(&self) -> bool {
+        self.attributes & ATTRIBUTE_DEVICE != 0
     }
 }
 
-impl Copy for FileType {}
-
-impl PartialEq for FileType {
-    fn eq(&self, _other: &FileType) -> bool {
-        self.0
-    }
-}
-
-impl Eq for FileType {}
-
-impl Hash for FileType {
-    fn hash<H: Hasher>(&self, _h: &mut H) {
-        self.0
-    }
-}
+static LONG_FILE_NAMES: AtomicBool = AtomicBool::new(false);
 
-impl fmt::Debug for FileType {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0
-    }
+/// Checks whether DOS provides the long file name API, as it does in
+/// Windows 9x DOS boxes or with DOSLFN loaded.
+pub fn init() {
+    // INT 21h/7147h: get the current directory by its long name. Without
+    // support for it, DOS leaves the carry set and AX at 7100h.
+    let mut transfer = dos::transfer_buffer();
+    let mut regs = RealModeRegisters {
+        eax: 0x7147,
+        flags: dos::CARRY_FLAG,
+        ds: transfer.segment(),
+        ..Default::default()
+    };
+    let supported = dos::int21(&mut regs).is_ok() && regs.eax as u16 != 0x7100;
+    LONG_FILE_NAMES.store(supported, Relaxed);
+}
+
+/// Whether paths go through the long file name API.
+pub fn long_file_names() -> bool {
+    LONG_FILE_NAMES.load(Relaxed)
+}
+
+/// The value of AX for INT 21h `function`, or for its long file name
+/// counterpart 71xxh if DOS supports it.
+pub fn path_function(function: u8) -> u32 {
+    if long_file_names() { 0x7100 | u32::from(function) } else { u32::from(function) << 8 }
+}
+
+fn word(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn dword(bytes: &[u8], offset: usize) -> u32 {
+    u32::from(word(bytes, offset)) | u32::from(word(bytes, offset + 2)) << 16
+}
+
+fn asciiz(bytes: &[u8]) -> &[u8] {
+    &bytes[..bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len())]
+}
+
+fn is_no_more_files(err: &io::Error) -> bool {
+    matches!(
+        err.raw_os_error(),
+        Some(code) if code == dos::ERROR_NO_MORE_FILES.into() || code == dos::ERROR_FILE_NOT_FOUND.into()
+    )
+}
+
+impl Search {
+    /// Starts a search for `pattern`, or returns `None` if nothing matches.
+    fn first(pattern: &Path) -> io::Result<Option<(Search, Found)>> {
+        let result = if long_file_names() {
+            find_long(Some(pattern), 0).map(|(handle, found)| (Search::Long(handle), found))
+        } else {
+            let mut dta = [0; DTA_SIZE];
+            find_short(Some(pattern), &mut dta).map(|found| (Search::Short(dta), found))
+        };
+        match result {
+            Ok(first) => Ok(Some(first)),
+            Err(err) if is_no_more_files(&err) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn next(&mut self) -> io::Result<Option<Found>> {
+        let result = match self {
+            Search::Short(dta) => find_short(None, dta),
+            Search::Long(handle) => find_long(None, *handle).map(|(_, found)| found),
+        };
+        match result {
+            Ok(found) => Ok(Some(found)),
+            Err(err) if is_no_more_files(&err) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        if let Search::Long(handle) = *self {
+            // INT 21h/71A1h: end a long file name search.
+            let mut regs =
+                RealModeRegisters { eax: 0x71a1, ebx: handle.into(), ..Default::default() };
+            let _ = dos::int21(&mut regs);
+        }
+    }
+}
+
+/// Runs FindFirst (INT 21h/4Eh) for `pattern`, or FindNext (INT 21h/4Fh) if
+/// there is none, with `dta` as the DTA. The caller's DTA is put back afterwards.
+fn find_short(pattern: Option<&Path>, dta: &mut [u8; DTA_SIZE]) -> io::Result<Found> {
+    let mut transfer = dos::transfer_buffer();
+    let segment = transfer.segment();
+
+    // INT 21h/2Fh: get the current DTA.
+    let mut previous = RealModeRegisters { eax: 0x2f00, ..Default::default() };
+    dos::int21(&mut previous)?;
+    let set_dta = |segment: u16, offset: u32| {
+        // INT 21h/1Ah: set the DTA.
+        let mut regs =
+            RealModeRegisters { eax: 0x1a00, edx: offset, ds: segment, ..Default::default() };
+        dos::int21(&mut regs)
+    };
+
+    // Everything that can fail is done before the DTA is changed, so that
+    // nothing returns early without putting it back.
+    let mut regs = match pattern {
+        Some(pattern) => RealModeRegisters {
+            eax: 0x4e00,
+            ecx: SEARCH_ATTRIBUTES.into(),
+            edx: transfer
+                .put_asciiz(FIND_PATTERN_OFFSET, pattern.as_os_str().as_encoded_bytes())?
+                .into(),
+            ds: segment,
+            ..Default::default()
+        },
+        None => RealModeRegisters { eax: 0x4f00, ..Default::default() },
+    };
+    transfer.as_mut_slice()[FIND_RESULT_OFFSET..][..DTA_SIZE].copy_from_slice(dta);
+    set_dta(segment, FIND_RESULT_OFFSET as u32)?;
+    let result = dos::int21(&mut regs);
+    dta.copy_from_slice(&transfer.as_slice()[FIND_RESULT_OFFSET..][..DTA_SIZE]);
+    set_dta(previous.es, previous.ebx & 0xffff)?;
+    result?;
+
+    let name = asciiz(&dta[0x1e..]).to_vec();
+    Ok(Found {
+        short_name: name.clone(),
+        name,
+        attr: FileAttr::new(
+            dword(dta, 0x1a).into(),
+            dta[0x15],
+            DosTime { date: word(dta, 0x18), time: word(dta, 0x16) },
+        ),
+    })
+}
+
+/// Runs INT 21h/714Eh for `pattern`, or INT 21h/714Fh on `handle` if there
+/// is none, returning the search handle along with what was found.
+fn find_long(pattern: Option<&Path>, handle: u16) -> io::Result<(u16, Found)> {
+    let mut transfer = dos::transfer_buffer();
+    let segment = transfer.segment();
+    let mut regs = RealModeRegisters {
+        // Ask for DOS dates and times rather than Win32 FILETIMEs.
+        esi: 1,
+        edi: FIND_RESULT_OFFSET as u32,
+        es: segment,
+        ..Default::default()
+    };
+    match pattern {
+        Some(pattern) => {
+            regs.eax = 0x714e;
+            regs.ecx = SEARCH_ATTRIBUTES.into();
+            regs.edx = transfer
+                .put_asciiz(FIND_PATTERN_OFFSET, pattern.as_os_str().as_encoded_bytes())?
+                .into();
+            regs.ds = segment;
+        }
+        None => {
+            regs.eax = 0x714f;
+            regs.ebx = handle.into();
+        }
+    }
+    dos::int21(&mut regs)?;
+    let handle = if pattern.is_some() { regs.eax as u16 } else { handle };
+
+    let record = &transfer.as_slice()[FIND_RESULT_OFFSET..][..FIND_DATA_SIZE];
+    let time = |offset| DosTime { time: word(record, offset), date: word(record, offset + 2) };
+    let name = asciiz(&record[0x2c..0x130]).to_vec();
+    // The short name is left empty when the long name is a valid 8.3 name.
+    let short_name = match asciiz(&record[0x130..]) {
+        [] => name.clone(),
+        short_name => short_name.to_vec(),
+    };
+    let found = Found {
+        name,
+        short_name,
+        attr: FileAttr {
+            size: u64::from(dword(record, 0x1c)) << 32 | u64::from(dword(record, 0x20)),
+            attributes: record[0],
+            modified: time(0x14),
+            accessed: time(0x0c),
+            created: time(0x04),
+        },
+    };
+    Ok((handle, found))
 }
 
 impl fmt::Debug for ReadDir {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // This will only be called from std::fs::ReadDir, which will add a "ReadDir()" frame.
+        // Thus the result will be e.g. 'ReadDir("C:\")'
+        fmt::Debug::fmt(&*self.root, f)
     }
 }
 
@@ -146,128 +428,401 @@ impl Iterator for ReadDir {
     type Item = io::Result<DirEntry>;
 
     fn next(&mut self) -> Option<io::Result<DirEntry>> {
-        self.0
+        loop {
+            let found = match self.pending.take() {
+                Some(found) => found,
+                None => match self.search.as_mut()?.next() {
+                    Ok(Some(found)) => found,
+                    Ok(None) => {
+                        self.search = None;
+                        return None;
+                    }
+                    Err(err) => {
+                        self.search = None;
+                        return Some(Err(err));
+                    }
+                },
+            };
+            if matches!(found.name.as_slice(), b"." | b"..") {
+                continue;
+            }
+            return Some(Ok(DirEntry { root: self.root.clone(), found }));
+        }
     }
 }
 
 impl DirEntry {
     pub fn path(&self) -> PathBuf {
-        self.0
+        self.root.join(self.file_name())
     }
 
     pub fn file_name(&self) -> OsString {
-        self.0
+        OsString::from(codepage::decode(&self.found.name))
+    }
+
+    /// The 8.3 alias of the file, which is its name when there are no long file names.
+    pub fn short_name(&self) -> OsString {
+        OsString::from(codepage::decode(&self.found.short_name))
     }
 
     pub fn metadata(&self) -> io::Result<FileAttr> {
-        self.0
+        Ok(self.found.attr.clone())
     }
 
     pub fn file_type(&self) -> io::Result<FileType> {
-        self.0
+        Ok(self.found.attr.file_type())
     }
 }
 
 impl OpenOptions {
     pub fn new() -> OpenOptions {
-        OpenOptions {}
+        OpenOptions {
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+            share_mode: SHARE_DENY_NONE,
+            attributes: 0,
+        }
+    }
+
+    pub fn read(&mut self, read: bool) {
+        self.read = read;
+    }
+    pub fn write(&mut self, write: bool) {
+        self.write = write;
+    }
+    pub fn append(&mut self, append: bool) {
+        self.append = append;
+    }
+    pub fn truncate(&mut self, truncate: bool) {
+        self.truncate = truncate;
+    }
+    pub fn create(&mut self, create: bool) {
+        self.create = create;
+    }
+    pub fn create_new(&mut self, create_new: bool) {
+        self.create_new = create_new;
+    }
+
+    pub fn share_mode(&mut self, share_mode: u8) {
+        self.share_mode = share_mode;
+    }
+    pub fn attributes(&mut self, attributes: u8) {
+        self.attributes = attributes;
+    }
+
+    fn get_access_mode(&self) -> io::Result<u8> {
+        match (self.read, self.write, self.append) {
+            (true, false, false) => Ok(ACCESS_READ),
+            (false, true, false) | (false, _, true) => Ok(ACCESS_WRITE),
+            (true, true, false) | (true, _, true) => Ok(ACCESS_READ_WRITE),
+            (false, false, false) => {
+                Err(io::const_error!(io::ErrorKind::InvalidInput, "no access mode was requested"))
+            }
+        }
+    }
+
+    fn check_creation_mode(&self) -> io::Result<()> {
+        match (self.write, self.append) {
+            (true, false) => {}
+            (false, false) => {
+                if self.truncate || self.create || self.create_new {
+                    return Err(io::const_error!(
+                        io::ErrorKind::InvalidInput,
+                        "creating or truncating a file requires write access",
+                    ));
+                }
+            }
+            (_, true) => {
+                if self.truncate && !self.create_new {
+                    return Err(io::const_error!(
+                        io::ErrorKind::InvalidInput,
+                        "a file cannot be both truncated and appended to",
+                    ));
+                }
+            }
+        }
+        Ok(())
     }
-
-    pub fn read(&mut self, _read: bool) {}
-    pub fn write(&mut self, _write: bool) {}
-    pub fn append(&mut self, _append: bool) {}
-    pub fn truncate(&mut self, _truncate: bool) {}
-    pub fn create(&mut self, _create: bool) {}
-    pub fn create_new(&mut self, _create_new: bool) {}
 }
 
 impl File {
-    pub fn open(_path: &Path, _opts: &OpenOptions) -> io::Result<File> {
-        unsupported()
+    pub fn open(path: &Path, opts: &OpenOptions) -> io::Result<File> {
+        let access = opts.get_access_mode()?;
+        opts.check_creation_mode()?;
+
+        if long_file_names() {
+            return Self::open_long(path, opts, access);
+        }
+
+        let create = |function: u32| {
+            let regs = RealModeRegisters {
+                eax: function,
+                ecx: opts.attributes.into(),
+                ..Default::default()
+            };
+            dos::path_call(path, regs).map(|regs| regs.eax as u16)
+        };
+
+        let handle = if opts.create_new {
+            // INT 21h/5Bh: create a file, failing if it already exists.
+            create(0x5b00)?
+        } else {
+            // INT 21h/3Dh: open an existing file.
+            let regs = RealModeRegisters {
+                eax: 0x3d00 | u32::from(access | opts.share_mode),
+                ..Default::default()
+            };
+            match dos::path_call(path, regs) {
+                Ok(regs) => {
+                    let handle = regs.eax as u16;
+                    if opts.truncate {
+                        if let Err(err) = dos::truncate_here(handle) {
+                            let _ = dos::close(handle);
+                            return Err(err);
+                        }
+                    }
+                    handle
+                }
+                Err(err)
+                    if opts.create
+                        && err.raw_os_error() == Some(dos::ERROR_FILE_NOT_FOUND.into()) =>
+                {
+                    // INT 21h/3Ch: create or truncate a file.
+                    create(0x3c00)?
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        Ok(File { handle, append: opts.append })
+    }
+
+    /// INT 21h/716Ch: opens or creates a file by its long name.
+    fn open_long(path: &Path, opts: &OpenOptions, access: u8) -> io::Result<File> {
+        const OPEN: u32 = 0x01;
+        const TRUNCATE: u32 = 0x02;
+        const CREATE: u32 = 0x10;
+
+        let action = match (opts.create_new, opts.create, opts.truncate) {
+            (true, _, _) => CREATE,
+            (false, true, true) => CREATE | TRUNCATE,
+            (false, true, false) => CREATE | OPEN,
+            (false, false, true) => TRUNCATE,
+            (false, false, false) => OPEN,
+        };
+        let mut transfer = dos::transfer_buffer();
+        let mut regs = RealModeRegisters {
+            eax: 0x716c,
+            ebx: (access | opts.share_mode).into(),
+            ecx: opts.attributes.into(),
+            edx: action,
+            esi: transfer.put_asciiz(0, path.as_os_str().as_encoded_bytes())?.into(),
+            ds: transfer.segment(),
+            ..Default::default()
+        };
+        dos::int21(&mut regs)?;
+        Ok(File { handle: regs.eax as u16, append: opts.append })
+    }
+
+    /// INT 21h/5Ah: creates a new file with a unique name in `dir`,
+    /// returning it along with its path.
+    pub fn create_temp(dir: &Path) -> io::Result<(File, PathBuf)> {
+        let mut dir = dir.as_os_str().as_encoded_bytes().to_vec();
+        if !matches!(dir.last(), Some(b'\\' | b'/')) {
+            dir.push(b'\\');
+        }
+        let mut transfer = dos::transfer_buffer();
+        let mut regs = RealModeRegisters {
+            eax: 0x5a00,
+            edx: transfer.put_asciiz(0, &dir)?.into(),
+            ds: transfer.segment(),
+            ..Default::default()
+        };
+        dos::int21(&mut regs)?;
+        let path = codepage::decode(transfer.get_asciiz(0));
+        Ok((File { handle: regs.eax as u16, append: false }, path.into()))
+    }
+
+    pub fn handle(&self) -> u16 {
+        self.handle
+    }
+
+    pub fn into_handle(self) -> u16 {
+        let handle = self.handle;
+        crate::mem::forget(self);
+        handle
+    }
+
+    /// DOS can't tell whether a handle was meant to append, so the file
+    /// doesn't.
+    pub fn from_handle(handle: u16) -> File {
+        File { handle, append: false }
     }
 
     pub fn file_attr(&self) -> io::Result<FileAttr> {
-        self.0
+        if dos::device_info(self.handle)? & dos::DEVICE_INFO_IS_DEVICE != 0 {
+            return Ok(FileAttr::new(0, ATTRIBUTE_DEVICE, DosTime::default()));
+        }
+
+        let position = dos::seek(self.handle, 0, 1)?;
+        let size = dos::seek(self.handle, 0, 2)?;
+        dos::seek(self.handle, position, 0)?;
+
+        // INT 21h/5700h: get the date and time the file was last written.
+        let mut regs =
+            RealModeRegisters { eax: 0x5700, ebx: self.handle.into(), ..Default::default() };
+        dos::int21(&mut regs)?;
+        // DOS can only query attributes by path.
+        let modified = DosTime { date: regs.edx as u16, time: regs.ecx as u16 };
+        Ok(FileAttr::new(size.into(), 0, modified))
     }
 
     pub fn fsync(&self) -> io::Result<()> {
-        self.0
+        // INT 21h/68h: commit the file's buffers to disk.
+        let mut regs =
+            RealModeRegisters { eax: 0x6800, ebx: self.handle.into(), ..Default::default() };
+        dos::int21(&mut regs)
     }
 
     pub fn datasync(&self) -> io::Result<()> {
-        self.0
+        self.fsync()
+    }
+
+    /// INT 21h/5Ch: locks or unlocks the whole file. Needs SHARE.EXE.
+    fn lock_region(&self, unlock: bool) -> io::Result<()> {
+        let mut regs = RealModeRegisters {
+            eax: 0x5c00 | u32::from(unlock),
+            ebx: self.handle.into(),
+            esi: 0xffff,
+            edi: 0xffff,
+            ..Default::default()
+        };
+        dos::int21(&mut regs)
     }
 
+    /// Waits for another program or thread to unlock the file, letting other
+    /// threads run meanwhile.
     pub fn lock(&self) -> io::Result<()> {
-        self.0
+        while !self.try_lock()? {
+            Thread::yield_now();
+        }
+        Ok(())
     }
 
     pub fn lock_shared(&self) -> io::Result<()> {
-        self.0
+        unsupported()
     }
 
     pub fn try_lock(&self) -> io::Result<bool> {
-        self.0
+        match self.lock_region(false) {
+            Ok(()) => Ok(true),
+            Err(err) if err.raw_os_error() == Some(dos::ERROR_LOCK_VIOLATION.into()) => Ok(false),
+            Err(err) => Err(err),
+        }
     }
 
     pub fn try_lock_shared(&self) -> io::Result<bool> {
-        self.0
+        unsupported()
     }
 
     pub fn unlock(&self) -> io::Result<()> {
-        self.0
+        self.lock_region(true)
     }
 
-    pub fn truncate(&self, _size: u64) -> io::Result<()> {
-        self.0
+    pub fn truncate(&self, size: u64) -> io::Result<()> {
+        let size = u32::try_from(size).map_err(|_| {
+            io::const_error!(io::ErrorKind::InvalidInput, "file size is too large for DOS")
+        })?;
+        let position = dos::seek(self.handle, 0, 1)?;
+        dos::seek(self.handle, size, 0)?;
+        let result = dos::truncate_here(self.handle);
+        dos::seek(self.handle, position, 0)?;
+        result
     }
 
-    pub fn read(&self, _buf: &mut [u8]) -> io::Result<usize> {
-        self.0
+    pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        dos::read(self.handle, buf)
     }
 
-    pub fn read_vectored(&self, _bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
-        self.0
+    pub fn read_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        io::default_read_vectored(|buf| self.read(buf), bufs)
     }
 
     pub fn is_read_vectored(&self) -> bool {
-        self.0
+        false
     }
 
-    pub fn read_buf(&self, _cursor: BorrowedCursor<'_>) -> io::Result<()> {
-        self.0
+    pub fn read_buf(&self, cursor: BorrowedCursor<'_>) -> io::Result<()> {
+        io::default_read_buf(|buf| self.read(buf), cursor)
     }
 
-    pub fn write(&self, _buf: &[u8]) -> io::Result<usize> {
-        self.0
+    pub fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        if self.append {
+            dos::seek(self.handle, 0, 2)?;
+        }
+        dos::write(self.handle, buf)
     }
 
-    pub fn write_vectored(&self, _bufs: &[IoSlice<'_>]) -> io::Result<usize> {
-        self.0
+    pub fn write_vectored(&self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        io::default_write_vectored(|buf| self.write(buf), bufs)
     }
 
     pub fn is_write_vectored(&self) -> bool {
-        self.0
+        false
     }
 
     pub fn flush(&self) -> io::Result<()> {
-        self.0
+        Ok(())
     }
 
-    pub fn seek(&self, _pos: SeekFrom) -> io::Result<u64> {
-        self.0
+    pub fn seek(&self, pos: SeekFrom) -> io::Result<u64> {
+        let out_of_range =
+            || io::const_error!(io::ErrorKind::InvalidInput, "seek offset is out of range for DOS");
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (u32::try_from(offset).map_err(|_| out_of_range())?, 0),
+            SeekFrom::Current(offset) => {
+                (i32::try_from(offset).map_err(|_| out_of_range())? as u32, 1)
+            }
+            SeekFrom::End(offset) => (i32::try_from(offset).map_err(|_| out_of_range())? as u32, 2),
+        };
+        dos::seek(self.handle, offset, whence).map(u64::from)
     }
 
     pub fn duplicate(&self) -> io::Result<File> {
-        self.0
+        Ok(File { handle: dos::duplicate(self.handle)?, append: self.append })
     }
 
     pub fn set_permissions(&self, _perm: FilePermissions) -> io::Result<()> {
-        self.0
+        // DOS can only change attributes by path.
+        unsupported()
     }
 
-    pub fn set_times(&self, _times: FileTimes) -> io::Result<()> {
-        self.0
+    pub fn set_times(&self, times: FileTimes) -> io::Result<()> {
+        let Some(modified) = times.modified else { return Ok(()) };
+        let Some((date, time)) = modified.to_dos() else {
+            return Err(io::const_error!(
+                io::ErrorKind::InvalidInput,
+                "DOS cannot represent timestamps outside 1980 to 2107",
+            ));
+        };
+        // INT 21h/5701h: set the date and time the file was last written.
+        let mut regs = RealModeRegisters {
+            eax: 0x5701,
+            ebx: self.handle.into(),
+            ecx: time.into(),
+            edx: date.into(),
+            ..Default::default()
+        };
+        dos::int21(&mut regs)
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        let _ = dos::close(self.handle);
     }
 }
 
@@ -276,43 +831,118 @@ pub fn new() -> DirBuilder {
         DirBuilder {}
     }
 
-    pub fn mkdir(&self, _p: &Path) -> io::Result<()> {
-        unsupported()
+    pub fn mkdir(&self, p: &Path) -> io::Result<()> {
+        // INT 21h/39h: create a directory.
+        dos::path_call(p, RealModeRegisters { eax: path_function(0x39), ..Default::default() })
+            .map(drop)
     }
 }
 
 impl fmt::Debug for File {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0
-    }
-}
-
-pub fn readdir(_p: &Path) -> io::Result<ReadDir> {
-    unsupported()
-}
-
-pub fn unlink(_p: &Path) -> io::Result<()> {
-    unsupported()
-}
-
-pub fn rename(_old: &Path, _new: &Path) -> io::Result<()> {
-    unsupported()
-}
-
-pub fn set_perm(_p: &Path, perm: FilePermissions) -> io::Result<()> {
-    match perm.0 {}
-}
-
-pub fn rmdir(_p: &Path) -> io::Result<()> {
-    unsupported()
-}
-
-pub fn remove_dir_all(_path: &Path) -> io::Result<()> {
-    unsupported()
-}
-
-pub fn exists(_path: &Path) -> io::Result<bool> {
-    unsupported()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("File").field("handle", &self.handle).finish()
+    }
+}
+
+pub use crate::sys_common::fs::{exists, remove_dir_all};
+
+pub fn readdir(p: &Path) -> io::Result<ReadDir> {
+    let root = Arc::new(p.to_path_buf());
+    // An empty root directory has no `.` entry either, so nothing may match.
+    let (search, pending) = match Search::first(&p.join("*.*"))? {
+        Some((search, first)) => (Some(search), Some(first)),
+        None => (None, None),
+    };
+    Ok(ReadDir { root, search, pending })
+}
+
+pub fn unlink(p: &Path) -> io::Result<()> {
+    // INT 21h/41h: delete a file.
+    dos::path_call(p, RealModeRegisters { eax: path_function(0x41), ..Default::default() })
+        .map(drop)
+}
+
+pub fn rename(old: &Path, new: &Path) -> io::Result<()> {
+    match dos_rename(old, new) {
+        // DOS refuses to replace an existing file, which std::fs::rename does.
+        // Depending on the version, the extended error says so or is just
+        // "access denied", which is also what a missing or open `old` gives.
+        Err(err)
+            if matches!(
+                err.raw_os_error().map(|code| code as u16),
+                Some(dos::ERROR_ACCESS_DENIED | dos::ERROR_FILE_EXISTS)
+            ) =>
+        {
+            match (stat(old), stat(new)) {
+                (Ok(_), Ok(attr)) if attr.file_type().is_file() && !attr.perm().readonly() => {
+                    replace(old, new)
+                }
+                _ => Err(err),
+            }
+        }
+        result => result,
+    }
+}
+
+/// INT 21h/56h: renames a file or directory, DS:DX to ES:DI.
+fn dos_rename(old: &Path, new: &Path) -> io::Result<()> {
+    let mut transfer = dos::transfer_buffer();
+    let old_offset = transfer.put_asciiz(0, old.as_os_str().as_encoded_bytes())?;
+    let new_offset =
+        transfer.put_asciiz(dos::TRANSFER_BUFFER_SIZE / 2, new.as_os_str().as_encoded_bytes())?;
+    let segment = transfer.segment();
+    let mut regs = RealModeRegisters {
+        eax: path_function(0x56),
+        edx: old_offset.into(),
+        edi: new_offset.into(),
+        ds: segment,
+        es: segment,
+        ..Default::default()
+    };
+    dos::int21(&mut regs)
+}
+
+/// Replaces the file `new` with `old`. `new` is renamed out of the way
+/// rather than deleted, so that it can be put back if `old` can't take its
+/// place, and is only deleted once `old` has.
+fn replace(old: &Path, new: &Path) -> io::Result<()> {
+    let backup = unused_name(new)?;
+    dos_rename(new, &backup)?;
+    match dos_rename(old, new) {
+        Ok(()) => unlink(&backup),
+        Err(err) => {
+            let _ = dos_rename(&backup, new);
+            Err(err)
+        }
+    }
+}
+
+/// Returns a short name that isn't taken in the directory of `path`.
+fn unused_name(path: &Path) -> io::Result<PathBuf> {
+    for n in 0..1000 {
+        let name = path.with_file_name(format!("~RENAME.{n:03}"));
+        match stat(&name) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(name),
+            Err(err) => return Err(err),
+            Ok(_) => {}
+        }
+    }
+    Err(io::const_error!(io::ErrorKind::AlreadyExists, "no free name to rename the file to"))
+}
+
+pub fn set_perm(p: &Path, perm: FilePermissions) -> io::Result<()> {
+    // INT 21h/4301h or 7143h with BL=1: set file attributes.
+    let attributes = perm.attributes
+        & (ATTRIBUTE_READONLY | ATTRIBUTE_HIDDEN | ATTRIBUTE_SYSTEM | ATTRIBUTE_ARCHIVE);
+    let (eax, ebx) = if long_file_names() { (0x7143, 1) } else { (0x4301, 0) };
+    dos::path_call(p, RealModeRegisters { eax, ebx, ecx: attributes.into(), ..Default::default() })
+        .map(drop)
+}
+
+pub fn rmdir(p: &Path) -> io::Result<()> {
+    // INT 21h/3Ah: remove a directory.
+    dos::path_call(p, RealModeRegisters { eax: path_function(0x3a), ..Default::default() })
+        .map(drop)
 }
 
 pub fn readlink(_p: &Path) -> io::Result<PathBuf> {
@@ -327,18 +957,65 @@ pub fn link(_src: &Path, _dst: &Path) -> io::Result<()> {
     unsupported()
 }
 
-pub fn stat(_p: &Path) -> io::Result<FileAttr> {
-    unsupported()
+pub fn stat(p: &Path) -> io::Result<FileAttr> {
+    if p.as_os_str().as_encoded_bytes().iter().any(|&b| b == b'*' || b == b'?') {
+        return Err(io::const_error!(io::ErrorKind::InvalidFilename, "path contains wildcards"));
+    }
+
+    if let Ok(Some((_, found))) = Search::first(p) {
+        return Ok(found.attr);
+    }
+
+    // FindFirst never matches a root directory, `.` or `..`, but it can
+    // still search inside them.
+    Search::first(&p.join("*.*"))?;
+    Ok(FileAttr::new(0, ATTRIBUTE_DIRECTORY, DosTime::default()))
 }
 
-pub fn lstat(_p: &Path) -> io::Result<FileAttr> {
-    unsupported()
+pub fn lstat(p: &Path) -> io::Result<FileAttr> {
+    stat(p)
 }
 
 pub fn canonicalize(_p: &Path) -> io::Result<PathBuf> {
     unsupported()
 }
 
-pub fn copy(_from: &Path, _to: &Path) -> io::Result<u64> {
-    unsupported()
+/// Returns the 8.3 alias of `p`, which is `p` itself without long file names.
+pub fn short_path(p: &Path) -> io::Result<PathBuf> {
+    if !long_file_names() {
+        return Ok(p.to_path_buf());
+    }
+    // INT 21h/7160h with CL=1: get the short form of a path, DS:SI to ES:DI.
+    const SHORT_PATH_OFFSET: usize = 0x200;
+    let mut transfer = dos::transfer_buffer();
+    let segment = transfer.segment();
+    let mut regs = RealModeRegisters {
+        eax: 0x7160,
+        ecx: 0x0001,
+        esi: transfer.put_asciiz(0, p.as_os_str().as_encoded_bytes())?.into(),
+        edi: SHORT_PATH_OFFSET as u32,
+        ds: segment,
+        es: segment,
+        ..Default::default()
+    };
+    dos::int21(&mut regs)?;
+    Ok(PathBuf::from(codepage::decode(transfer.get_asciiz(SHORT_PATH_OFFSET))))
+}
+
+pub fn copy(from: &Path, to: &Path) -> io::Result<u64> {
+    let mut reader = crate::fs::File::open(from)?;
+    let attr = stat(from)?;
+    if !attr.file_type().is_file() {
+        return Err(crate::sys_common::fs::NOT_FILE_ERROR);
+    }
+
+    let mut writer = crate::fs::File::create(to)?;
+    let copied = io::copy(&mut reader, &mut writer)?;
+    if let Ok(modified) = attr.modified() {
+        writer.as_inner().set_times(FileTimes { modified: Some(modified) })?;
+    }
+    drop(writer);
+    // Attributes can only be set by path.
+    set_perm(to, attr.perm())?;
+    Ok(copied)
 }
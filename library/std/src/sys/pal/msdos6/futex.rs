@@ -0,0 +1,46 @@
+//! Futexes for the green threads of [`thread`](super::thread).
+//!
+//! There is nothing to block on: a waiting thread checks the futex every time
+//! the scheduler gets back to it and yields in between. Waking is therefore a
+//! no-op, and waits may return spuriously, which the futex users allow for.
+
+use super::thread::Thread;
+use super::time::Instant;
+use crate::sync::atomic::AtomicU32;
+use crate::sync::atomic::Ordering::Relaxed;
+use crate::time::Duration;
+
+/// An atomic for use as a futex that is at least 32-bits but may be larger
+pub type Futex = AtomicU32;
+/// Must be the underlying type of Futex
+pub type Primitive = u32;
+
+/// An atomic for use as a futex that is at least 8-bits but may be larger.
+pub type SmallFutex = AtomicU32;
+/// Must be the underlying type of SmallFutex
+pub type SmallPrimitive = u32;
+
+/// Yields to other threads until the futex no longer holds `expected`.
+///
+/// Returns false on timeout, and true in all other cases.
+pub fn futex_wait(futex: &AtomicU32, expected: u32, timeout: Option<Duration>) -> bool {
+    // Overflows are rounded up to an infinite timeout.
+    let deadline = timeout.and_then(|dur| Instant::now().checked_add_duration(&dur));
+    while futex.load(Relaxed) == expected {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return false;
+        }
+        Thread::yield_now();
+    }
+    true
+}
+
+/// Waiters poll the futex, so there is no one to wake. Like on Windows, this
+/// does not know whether anyone was waiting and returns false.
+#[inline]
+pub fn futex_wake(_futex: &AtomicU32) -> bool {
+    false
+}
+
+#[inline]
+pub fn futex_wake_all(_futex: &AtomicU32) {}
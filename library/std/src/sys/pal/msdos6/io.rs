@@ -1,4 +1,6 @@
+use super::dos;
 use crate::mem;
+use crate::os::msdos6::io::AsRawHandle;
 
 #[derive(Copy, Clone)]
 pub struct IoSlice<'a>(&'a [u8]);
@@ -51,6 +53,14 @@ pub fn as_mut_slice(&mut self) -> &mut [u8] {
     }
 }
 
-pub fn is_terminal<T>(_: &T) -> bool {
-    false
+/// Returns whether `handle` refers to CON, as opposed to a file or another
+/// device such as `NUL` or a printer.
+pub fn is_terminal(handle: &impl AsRawHandle) -> bool {
+    match dos::device_info(handle.as_raw_handle()) {
+        Ok(info) => {
+            info & dos::DEVICE_INFO_IS_DEVICE != 0
+                && info & (dos::DEVICE_INFO_CONSOLE_INPUT | dos::DEVICE_INFO_CONSOLE_OUTPUT) != 0
+        }
+        Err(_) => false,
+    }
 }
@@ -0,0 +1,50 @@
+//! The keyboard BIOS (INT 16h).
+
+use super::dos;
+use super::dpmi::{self, RealModeRegisters};
+use crate::io;
+
+/// Set in the keyboard status byte at 0040:0096h if an enhanced (101/102-key)
+/// keyboard is installed.
+const BIOS_KEYBOARD_STATUS: u32 = 0x496;
+const ENHANCED_KEYBOARD: u8 = 0x10;
+/// Set by INT 16h/01h and 11h if no key is waiting.
+const ZERO_FLAG: u16 = 0x0040;
+
+fn int16(mut regs: RealModeRegisters) -> io::Result<RealModeRegisters> {
+    dos::int86(0x16, &mut regs)?;
+    Ok(regs)
+}
+
+/// Whether the BIOS supports the enhanced functions 10h to 12h, which report
+/// F11, F12 and the keys added by 101-key keyboards.
+pub fn enhanced() -> bool {
+    let status = unsafe { dpmi::linear_to_ptr(BIOS_KEYBOARD_STATUS).read_volatile() };
+    status & ENHANCED_KEYBOARD != 0
+}
+
+/// The function number of the enhanced variant of `function` if there is
+/// one.
+fn function(function: u32) -> u32 {
+    if enhanced() { function + 0x10 } else { function }
+}
+
+/// INT 16h/00h or 10h: waits for a key and returns it as the scan code in
+/// the high byte and the character in the low byte.
+pub fn read() -> io::Result<u16> {
+    Ok(int16(RealModeRegisters { eax: function(0x00) << 8, ..Default::default() })?.eax as u16)
+}
+
+/// INT 16h/01h or 11h: returns the next key without removing it, or `None`
+/// if no key is waiting.
+pub fn peek() -> io::Result<Option<u16>> {
+    let regs = int16(RealModeRegisters { eax: function(0x01) << 8, ..Default::default() })?;
+    Ok(if regs.flags & ZERO_FLAG != 0 { None } else { Some(regs.eax as u16) })
+}
+
+/// INT 16h/02h or 12h: returns the shift flags. The enhanced function adds
+/// the state of the individual Ctrl and Alt keys in the high byte.
+pub fn shift_flags() -> io::Result<u16> {
+    let regs = int16(RealModeRegisters { eax: function(0x02) << 8, ..Default::default() })?;
+    Ok(if enhanced() { regs.eax as u16 } else { regs.eax as u8 as u16 })
+}
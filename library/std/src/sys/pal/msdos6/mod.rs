@@ -2,13 +2,23 @@
 
 pub mod alloc;
 pub mod args;
+pub mod codepage;
+pub mod console;
+pub mod ctrl_break;
+pub mod dos;
+pub mod dpmi;
 pub mod env;
 pub mod fs;
+pub mod futex;
 pub mod io;
+pub mod keyboard;
 pub mod net;
 pub mod os;
 pub mod pipe;
+pub mod port;
 pub mod process;
+pub mod psp;
+pub mod serial;
 pub mod stdio;
 pub mod thread;
 pub mod time;
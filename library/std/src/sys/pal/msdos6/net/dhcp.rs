@@ -0,0 +1,198 @@
+//! A DHCP client (RFC 2131), which gives the interface its addresses when
+//! IPADDR doesn't.
+//!
+//! The address is asked for once, when the interface comes up, and the lease
+//! is never renewed: a program that runs longer than the lease may lose its
+//! address to another machine.
+
+use super::driver::{BROADCAST, MacAddr};
+use super::iface::{Config, Handle, Interface, Socket};
+use super::udp::Datagrams;
+use crate::io;
+use crate::net::{Ipv4Addr, SocketAddrV4};
+use crate::sys::time::Instant;
+use crate::time::Duration;
+
+#[cfg(test)]
+mod tests;
+
+const SERVER_PORT: u16 = 67;
+const CLIENT_PORT: u16 = 68;
+
+const BOOTREQUEST: u8 = 1;
+const BOOTREPLY: u8 = 2;
+const HTYPE_ETHERNET: u8 = 1;
+/// Asks the server to broadcast its replies, as we can't take unicast ones
+/// before we have an address everywhere.
+const FLAG_BROADCAST: u16 = 0x8000;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+/// The length of a message up to the options.
+const HEADER: usize = 236;
+
+const OPTION_PAD: u8 = 0;
+const OPTION_SUBNET_MASK: u8 = 1;
+const OPTION_ROUTER: u8 = 3;
+const OPTION_DNS_SERVER: u8 = 6;
+const OPTION_REQUESTED_ADDRESS: u8 = 50;
+const OPTION_MESSAGE_TYPE: u8 = 53;
+const OPTION_SERVER_ID: u8 = 54;
+const OPTION_PARAMETER_LIST: u8 = 55;
+const OPTION_END: u8 = 255;
+
+const DHCPDISCOVER: u8 = 1;
+const DHCPOFFER: u8 = 2;
+const DHCPREQUEST: u8 = 3;
+const DHCPACK: u8 = 5;
+const DHCPNAK: u8 = 6;
+
+const TRIES: u32 = 3;
+const TIMEOUT: Duration = Duration::from_secs(4);
+
+/// Asks a DHCP server for the addresses of `interface`.
+pub fn configure(interface: &mut Interface) -> io::Result<()> {
+    let mut socket = Datagrams::new(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, CLIENT_PORT));
+    socket.broadcast = true;
+    let handle = interface.insert(Socket::Udp(socket), 1);
+    let config = negotiate(interface, handle);
+    interface.release(handle);
+    interface.link.config = config?;
+    Ok(())
+}
+
+fn negotiate(interface: &mut Interface, handle: Handle) -> io::Result<Config> {
+    let mut xid = [0; 4];
+    crate::sys::random::fill_bytes(&mut xid);
+
+    for _ in 0..TRIES {
+        send(interface, handle, xid, DHCPDISCOVER, None)?;
+        let Some(offer) = receive(interface, handle, xid, &[DHCPOFFER]) else { continue };
+        let Some(server) = offer.server else { continue };
+
+        send(interface, handle, xid, DHCPREQUEST, Some((offer.address, server)))?;
+        match receive(interface, handle, xid, &[DHCPACK, DHCPNAK]) {
+            Some(reply) if reply.kind == DHCPACK => return Ok(reply.config()),
+            _ => {}
+        }
+    }
+    Err(io::const_error!(io::ErrorKind::TimedOut, "no DHCP server gave the interface an address"))
+}
+
+/// Broadcasts a DISCOVER, or a REQUEST for the address a server offered.
+fn send(
+    interface: &mut Interface,
+    handle: Handle,
+    xid: [u8; 4],
+    kind: u8,
+    request: Option<(Ipv4Addr, Ipv4Addr)>,
+) -> io::Result<()> {
+    let (udp, link) = interface.udp(handle);
+    let mut message = vec![0; HEADER];
+    message[..3].copy_from_slice(&[BOOTREQUEST, HTYPE_ETHERNET, 6]);
+    message[4..8].copy_from_slice(&xid);
+    message[10..12].copy_from_slice(&FLAG_BROADCAST.to_be_bytes());
+    message[28..34].copy_from_slice(&link.mac);
+    message.extend_from_slice(&MAGIC_COOKIE);
+    message.extend_from_slice(&[OPTION_MESSAGE_TYPE, 1, kind]);
+    if let Some((address, server)) = request {
+        message.extend_from_slice(&[OPTION_REQUESTED_ADDRESS, 4]);
+        message.extend_from_slice(&address.octets());
+        message.extend_from_slice(&[OPTION_SERVER_ID, 4]);
+        message.extend_from_slice(&server.octets());
+    }
+    message.extend_from_slice(&[
+        OPTION_PARAMETER_LIST,
+        3,
+        OPTION_SUBNET_MASK,
+        OPTION_ROUTER,
+        OPTION_DNS_SERVER,
+        OPTION_END,
+    ]);
+
+    let destination = SocketAddrV4::new(Ipv4Addr::BROADCAST, SERVER_PORT);
+    udp.send(link, BROADCAST, destination, &message).map(drop)
+}
+
+/// Waits for a reply to our `xid` that is one of `kinds`.
+fn receive(interface: &mut Interface, handle: Handle, xid: [u8; 4], kinds: &[u8]) -> Option<Reply> {
+    let deadline = Instant::now().checked_add_duration(&TIMEOUT)?;
+    let mac = interface.link.mac;
+    let mut buf = [0; 1500];
+    interface.block_on(deadline, |interface| {
+        let udp = interface.udp(handle).0;
+        loop {
+            let (len, _) = udp.pop(&mut buf, false)?;
+            match Reply::parse(&buf[..len], xid, mac) {
+                Some(reply) if kinds.contains(&reply.kind) => return Some(reply),
+                _ => {}
+            }
+        }
+    })
+}
+
+struct Reply {
+    kind: u8,
+    address: Ipv4Addr,
+    server: Option<Ipv4Addr>,
+    netmask: Option<Ipv4Addr>,
+    router: Option<Ipv4Addr>,
+    nameserver: Option<Ipv4Addr>,
+}
+
+impl Reply {
+    fn parse(message: &[u8], xid: [u8; 4], mac: MacAddr) -> Option<Reply> {
+        if message.len() < HEADER + 4
+            || message[0] != BOOTREPLY
+            || message[4..8] != xid
+            || message[28..34] != mac
+            || message[HEADER..HEADER + 4] != MAGIC_COOKIE
+        {
+            return None;
+        }
+        let mut reply = Reply {
+            kind: 0,
+            address: address(&message[16..20])?,
+            server: None,
+            netmask: None,
+            router: None,
+            nameserver: None,
+        };
+
+        let mut options = &message[HEADER + 4..];
+        loop {
+            match *options {
+                [] | [OPTION_END, ..] => break,
+                [OPTION_PAD, ref rest @ ..] => options = rest,
+                [code, len, ref rest @ ..] => {
+                    let value = rest.get(..usize::from(len))?;
+                    match code {
+                        OPTION_MESSAGE_TYPE => reply.kind = *value.first()?,
+                        OPTION_SERVER_ID => reply.server = address(value),
+                        OPTION_SUBNET_MASK => reply.netmask = address(value),
+                        // Lists of addresses, in order of preference.
+                        OPTION_ROUTER => reply.router = address(value),
+                        OPTION_DNS_SERVER => reply.nameserver = address(value),
+                        _ => {}
+                    }
+                    options = &rest[usize::from(len)..];
+                }
+                [_] => return None,
+            }
+        }
+        (reply.kind != 0).then_some(reply)
+    }
+
+    fn config(&self) -> Config {
+        Config {
+            address: self.address,
+            netmask: self.netmask.unwrap_or(Ipv4Addr::new(255, 255, 255, 0)),
+            gateway: self.router,
+            nameserver: self.nameserver,
+        }
+    }
+}
+
+/// The first IPv4 address in `value`.
+fn address(value: &[u8]) -> Option<Ipv4Addr> {
+    let octets: [u8; 4] = value.get(..4)?.try_into().ok()?;
+    Some(Ipv4Addr::from(octets))
+}
@@ -0,0 +1,75 @@
+use super::*;
+
+const OUR_MAC: MacAddr = [0x02, 0, 0, 0, 0, 1];
+const XID: [u8; 4] = [1, 2, 3, 4];
+
+/// A DHCPACK for 10.0.0.5 to our MAC, with `options` after the message type.
+fn ack(options: &[u8]) -> Vec<u8> {
+    let mut message = vec![0; HEADER];
+    message[0] = BOOTREPLY;
+    message[4..8].copy_from_slice(&XID);
+    message[16..20].copy_from_slice(&[10, 0, 0, 5]);
+    message[28..34].copy_from_slice(&OUR_MAC);
+    message.extend_from_slice(&MAGIC_COOKIE);
+    message.extend_from_slice(&[53, 1, DHCPACK]);
+    message.extend_from_slice(options);
+    message
+}
+
+#[test]
+fn reply_parse() {
+    let message = ack(&[
+        0, // Pad
+        54, 4, 10, 0, 0, 254, // Server identifier
+        1, 4, 255, 255, 0, 0, // Subnet mask
+        3, 8, 10, 0, 0, 1, 10, 0, 0, 2, // Routers
+        6, 4, 10, 0, 0, 53, // DNS servers
+        12, 3, b'd', b'o', b's', // Host name, which is skipped
+        255,  // End
+        1, 4, 1, 1, 1, 1, // Past the end
+    ]);
+    let reply = Reply::parse(&message, XID, OUR_MAC).unwrap();
+    assert_eq!(reply.kind, DHCPACK);
+    assert_eq!(reply.server, Some(Ipv4Addr::new(10, 0, 0, 254)));
+    let config = reply.config();
+    assert_eq!(config.address, Ipv4Addr::new(10, 0, 0, 5));
+    assert_eq!(config.netmask, Ipv4Addr::new(255, 255, 0, 0));
+    assert_eq!(config.gateway, Some(Ipv4Addr::new(10, 0, 0, 1)));
+    assert_eq!(config.nameserver, Some(Ipv4Addr::new(10, 0, 0, 53)));
+
+    // Without options, the netmask defaults and there is no gateway.
+    let config = Reply::parse(&ack(&[]), XID, OUR_MAC).unwrap().config();
+    assert_eq!(config.netmask, Ipv4Addr::new(255, 255, 255, 0));
+    assert_eq!(config.gateway, None);
+}
+
+#[test]
+fn reply_parse_malformed() {
+    let parse = |message: &[u8]| Reply::parse(message, XID, OUR_MAC);
+
+    // An option longer than what is left, a code without a length, and an
+    // empty message type.
+    assert!(parse(&ack(&[6, 8, 10, 0, 0, 53])).is_none());
+    assert!(parse(&ack(&[6])).is_none());
+    let mut message = ack(&[]);
+    let at = HEADER + 4;
+    message[at..at + 3].copy_from_slice(&[53, 0, 255]);
+    assert!(parse(&message).is_none());
+    // No message type.
+    message[at..at + 3].copy_from_slice(&[0, 0, 255]);
+    assert!(parse(&message).is_none());
+
+    // A short address is taken as none.
+    let reply = parse(&ack(&[54, 2, 10, 0])).unwrap();
+    assert_eq!(reply.server, None);
+
+    // Not a reply, for another transaction, or for another machine, or
+    // without the cookie.
+    let good = ack(&[]);
+    for (at, byte) in [(0, 1), (4, 9), (33, 9), (HEADER, 0)] {
+        let mut message = good.clone();
+        message[at] = byte;
+        assert!(parse(&message).is_none(), "byte {at}");
+    }
+    assert!(parse(&good[..HEADER + 3]).is_none());
+}
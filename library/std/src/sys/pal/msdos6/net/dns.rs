@@ -0,0 +1,206 @@
+//! Host name lookups, with a DNS resolver of our own that asks the
+//! nameserver from DHCP or NAMESERVER for A records.
+//!
+//! There is no HOSTS file and no cache; `localhost` is the only name known
+//! without asking.
+
+use super::iface::with_interface;
+use super::udp::UdpSocket;
+use crate::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use crate::time::Duration;
+use crate::{io, vec};
+
+#[cfg(test)]
+mod tests;
+
+const DNS_PORT: u16 = 53;
+const TYPE_A: u16 = 1;
+const CLASS_IN: u16 = 1;
+/// Recursion desired.
+const FLAG_RD: u16 = 0x0100;
+const RCODE_NXDOMAIN: u8 = 3;
+
+const TRIES: u32 = 3;
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct LookupHost {
+    addrs: vec::IntoIter<SocketAddr>,
+    port: u16,
+}
+
+impl LookupHost {
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Iterator for LookupHost {
+    type Item = SocketAddr;
+    fn next(&mut self) -> Option<SocketAddr> {
+        self.addrs.next()
+    }
+}
+
+impl TryFrom<&str> for LookupHost {
+    type Error = io::Error;
+
+    fn try_from(s: &str) -> io::Result<LookupHost> {
+        let Some((host, port_str)) = s.rsplit_once(':') else {
+            return Err(io::const_error!(io::ErrorKind::InvalidInput, "invalid socket address"));
+        };
+        let Ok(port) = port_str.parse::<u16>() else {
+            return Err(io::const_error!(io::ErrorKind::InvalidInput, "invalid port value"));
+        };
+        (host, port).try_into()
+    }
+}
+
+impl<'a> TryFrom<(&'a str, u16)> for LookupHost {
+    type Error = io::Error;
+
+    fn try_from((host, port): (&'a str, u16)) -> io::Result<LookupHost> {
+        let addresses = if host.eq_ignore_ascii_case("localhost") {
+            vec![Ipv4Addr::LOCALHOST]
+        } else {
+            query(host)?
+        };
+        let addrs: Vec<_> = addresses
+            .into_iter()
+            .map(|address| SocketAddr::V4(SocketAddrV4::new(address, port)))
+            .collect();
+        Ok(LookupHost { addrs: addrs.into_iter(), port })
+    }
+}
+
+/// Asks the nameserver for the IPv4 addresses of `host`.
+fn query(host: &str) -> io::Result<Vec<Ipv4Addr>> {
+    let question = question(host)?;
+    let server = with_interface(|interface| Ok(interface.link.config.nameserver))?
+        .ok_or(io::const_error!(io::ErrorKind::NotFound, "no DNS server is configured"))?;
+    let server = SocketAddr::V4(SocketAddrV4::new(server, DNS_PORT));
+
+    let socket = UdpSocket::bind(Ok(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))))?;
+    socket.connect(Ok(&server))?;
+    socket.set_read_timeout(Some(TIMEOUT))?;
+    let mut buf = [0; 512];
+    for _ in 0..TRIES {
+        let mut id = [0; 2];
+        crate::sys::random::fill_bytes(&mut id);
+        let mut message = Vec::with_capacity(12 + question.len());
+        message.extend_from_slice(&id);
+        message.extend_from_slice(&FLAG_RD.to_be_bytes());
+        // One question, no answers or other records.
+        message.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+        message.extend_from_slice(&question);
+        socket.send(&message)?;
+
+        loop {
+            match socket.recv(&mut buf) {
+                Ok(len) => {
+                    if let Some(result) = answer(&buf[..len], id) {
+                        return result;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => break,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Err(io::const_error!(io::ErrorKind::TimedOut, "no answer from the DNS server"))
+}
+
+/// The question section asking for the A records of `host`.
+fn question(host: &str) -> io::Result<Vec<u8>> {
+    let invalid = || io::const_error!(io::ErrorKind::InvalidInput, "invalid host name");
+    let name = host.strip_suffix('.').unwrap_or(host);
+    if name.is_empty() || name.len() > 253 {
+        return Err(invalid());
+    }
+    let mut question = Vec::with_capacity(name.len() + 6);
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid());
+        }
+        question.push(label.len() as u8);
+        question.extend_from_slice(label.as_bytes());
+    }
+    question.push(0);
+    question.extend_from_slice(&TYPE_A.to_be_bytes());
+    question.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(question)
+}
+
+/// Reads the reply to the query with `id`. Returns `None` if `message` is
+/// not that reply.
+fn answer(message: &[u8], id: [u8; 2]) -> Option<io::Result<Vec<Ipv4Addr>>> {
+    if message.len() < 12 || message[..2] != id || message[2] & 0x80 == 0 {
+        return None;
+    }
+    match message[3] & 0xf {
+        0 => {}
+        RCODE_NXDOMAIN => {
+            return Some(Err(io::const_error!(io::ErrorKind::NotFound, "host not found")));
+        }
+        _ => {
+            return Some(Err(io::const_error!(
+                io::ErrorKind::Other,
+                "the DNS server failed to answer",
+            )));
+        }
+    }
+    let addresses = match records(message) {
+        Some(addresses) => addresses,
+        None => {
+            return Some(Err(io::const_error!(
+                io::ErrorKind::InvalidData,
+                "malformed reply from the DNS server",
+            )));
+        }
+    };
+    if addresses.is_empty() {
+        return Some(Err(io::const_error!(io::ErrorKind::NotFound, "host has no IPv4 address")));
+    }
+    Some(Ok(addresses))
+}
+
+/// The addresses in the A records of the answer section.
+fn records(message: &[u8]) -> Option<Vec<Ipv4Addr>> {
+    let questions = u16::from_be_bytes([message[4], message[5]]);
+    let answers = u16::from_be_bytes([message[6], message[7]]);
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(message, pos)? + 4;
+    }
+    let mut addresses = Vec::new();
+    for _ in 0..answers {
+        pos = skip_name(message, pos)?;
+        let record = message.get(pos..pos + 10)?;
+        let kind = u16::from_be_bytes([record[0], record[1]]);
+        let class = u16::from_be_bytes([record[2], record[3]]);
+        let len = usize::from(u16::from_be_bytes([record[8], record[9]]));
+        let data = message.get(pos + 10..pos + 10 + len)?;
+        // CNAME records come first and are followed by the records of the
+        // name they point to, so only the addresses are of interest.
+        if kind == TYPE_A && class == CLASS_IN {
+            let octets: [u8; 4] = data.try_into().ok()?;
+            addresses.push(Ipv4Addr::from(octets));
+        }
+        pos += 10 + len;
+    }
+    Some(addresses)
+}
+
+/// The position after the name at `pos`, which may end in a pointer.
+fn skip_name(message: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *message.get(pos)?;
+        match len {
+            0 => return Some(pos + 1),
+            len if len & 0xc0 == 0xc0 => {
+                message.get(pos + 1)?;
+                return Some(pos + 2);
+            }
+            len => pos += 1 + usize::from(len),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use super::*;
+
+#[test]
+fn question_for_a_records() {
+    let expected = b"\x07example\x03com\x00\x00\x01\x00\x01";
+    assert_eq!(question("example.com").unwrap(), expected);
+    assert_eq!(question("example.com.").unwrap(), expected);
+
+    let long_label = "a".repeat(64);
+    let long_name = "a.".repeat(127) + "a";
+    for invalid in ["", ".", "a..b", ".example.com", long_label.as_str(), long_name.as_str()] {
+        let err = question(invalid).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{invalid:?}");
+    }
+    assert!(question(&"a".repeat(63)).is_ok());
+}
+
+/// A reply to query 1234h for www.example.com: a CNAME to example.com and
+/// its address, with names compressed as servers do.
+fn reply() -> Vec<u8> {
+    let mut message = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0];
+    // At 12: the question, www.example.com A IN.
+    message.extend_from_slice(b"\x03www\x07example\x03com\x00\x00\x01\x00\x01");
+    // A pointer to the question's name, CNAME IN, a TTL, and example.com as
+    // a pointer into the question's name.
+    message.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0x0e, 0x10, 0, 2, 0xc0, 16]);
+    // A pointer to example.com, A IN, a TTL, and the address.
+    message.extend_from_slice(&[0xc0, 16, 0, 1, 0, 1, 0, 0, 0x0e, 0x10, 0, 4, 93, 184, 216, 34]);
+    message
+}
+
+#[test]
+fn answer_with_compression_pointers() {
+    let message = reply();
+    let addresses = answer(&message, [0x12, 0x34]).unwrap().unwrap();
+    assert_eq!(addresses, [Ipv4Addr::new(93, 184, 216, 34)]);
+
+    // Replies to other queries, and queries, are not for us.
+    assert!(answer(&message, [0x12, 0x35]).is_none());
+    let mut query = message.clone();
+    query[2] &= 0x7f;
+    assert!(answer(&query, [0x12, 0x34]).is_none());
+    assert!(answer(&message[..11], [0x12, 0x34]).is_none());
+}
+
+#[test]
+fn answer_errors() {
+    let mut nxdomain = reply();
+    nxdomain[3] |= 3;
+    let err = answer(&nxdomain, [0x12, 0x34]).unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    // Only the CNAME.
+    let message = reply();
+    let mut cname_only = message[..message.len() - 16].to_vec();
+    cname_only[7] = 1;
+    let err = answer(&cname_only, [0x12, 0x34]).unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    // Cut off in a record, or in a pointer.
+    for len in [message.len() - 1, message.len() - 13, 12 + 21 + 1] {
+        assert!(records(&message[..len]).is_none(), "cut at {len}");
+        let err = answer(&message[..len], [0x12, 0x34]).unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+    // An A record that isn't four bytes long.
+    let mut long = message.clone();
+    let at = long.len() - 5;
+    long[at] = 5;
+    long.push(0);
+    assert!(records(&long).is_none());
+}
@@ -0,0 +1,334 @@
+//! The packet driver interface, as specified by FTP Software (version 1.09).
+//!
+//! A packet driver is a TSR that hooks a software interrupt in 60h–80h and
+//! puts "PKT DRVR" three bytes into its handler. Programs register for the
+//! Ethernet types they want, giving the far address of a receiver. For every
+//! packet, the driver calls the receiver twice from its interrupt handler:
+//! first with AX = 0 to ask where to put CX bytes, then with AX = 1 once they
+//! have been copied there.
+//!
+//! Our receiver is a real-mode callback into `__msdos6_packet_receiver`,
+//! which hands out the slots of a ring in conventional memory. `next_frame`
+//! copies packets out of the ring in order. Everything the driver knows of
+//! us has to be taken back before the program exits, which `close` does.
+
+use super::super::dpmi::{self, RealModeRegisters};
+use super::super::{dos, os};
+use crate::arch::global_asm;
+use crate::ffi::OsStr;
+use crate::io;
+use crate::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use crate::sync::atomic::{AtomicU8, AtomicU16, AtomicU32, AtomicUsize};
+
+pub type MacAddr = [u8; 6];
+
+pub const BROADCAST: MacAddr = [0xff; 6];
+
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_ARP: u16 = 0x0806;
+
+/// The largest frame we send or take, without the frame check sequence.
+pub const MAX_FRAME: usize = 1514;
+/// Drivers are asked not to send shorter frames, but not all of them pad.
+const MIN_FRAME: usize = 60;
+
+const SIGNATURE: &[u8; 8] = b"PKT DRVR";
+const FIRST_INTERRUPT: u8 = 0x60;
+const LAST_INTERRUPT: u8 = 0x80;
+/// The interface class of DIX Ethernet.
+const CLASS_ETHERNET: u8 = 1;
+
+// The receive ring. A slot is free while its length is zero; the receiver
+// fills the slot at `RING_HEAD` and `Driver::next_frame` empties the one at
+// `Driver::tail`.
+const SLOT_SIZE: usize = 1536;
+const SLOTS: usize = 16;
+static RING_SEGMENT: AtomicU16 = AtomicU16::new(0);
+static RING_SELECTOR: AtomicU16 = AtomicU16::new(0);
+static RING_LENGTHS: [AtomicU16; SLOTS] = [const { AtomicU16::new(0) }; SLOTS];
+static RING_HEAD: AtomicUsize = AtomicUsize::new(0);
+
+// The interrupt of the open driver, or 0, and the handles of the types we
+// registered with it.
+static INTERRUPT: AtomicU8 = AtomicU8::new(0);
+static HANDLES: [AtomicU16; 2] = [const { AtomicU16::new(0) }; 2];
+// The real-mode callback the driver calls, as `segment << 16 | offset`.
+static CALLBACK: AtomicU32 = AtomicU32::new(0);
+// The registers of the driver while `__msdos6_packet_receiver` runs.
+static mut RECEIVER_REGS: RealModeRegisters = RealModeRegisters::zeroed();
+
+// The stack `receive` runs on. Real-mode callbacks are entered with
+// interrupts disabled, so it is never in use when the driver calls.
+static STACK: dpmi::InterruptStack<0x1000> = dpmi::InterruptStack::new();
+
+extern "C" {
+    fn __msdos6_packet_receiver();
+}
+
+global_asm!(
+    ".pushsection .text.__msdos6_packet_receiver,\"ax\"",
+    ".globl __msdos6_packet_receiver",
+    "__msdos6_packet_receiver:",
+    // Entered with DS:ESI pointing to the real-mode stack and ES:EDI to
+    // `RECEIVER_REGS`.
+    "push ds",
+    "push es",
+    "pushad",
+    "lea ebx, [{stack}]",
+    "lea eax, [{receive}]",
+    "mov edx, edi",
+    "call __msdos6_interrupt_entry",
+    "popad",
+    "pop es",
+    "pop ds",
+    "iretd",
+    ".popsection",
+    stack = sym STACK,
+    receive = sym receive,
+);
+
+/// Runs in the driver's interrupt handler, so it must only touch atomics and the ring.
+extern "C" fn receive(regs: *mut RealModeRegisters) {
+    let regs = unsafe { &mut *regs };
+
+    // Return to the driver as its far call would: pop IP and CS.
+    let stack = u32::from(regs.ss) * 16 + u32::from(regs.sp);
+    let stack = dpmi::linear_to_ptr(stack).cast::<u16>();
+    unsafe {
+        regs.ip = stack.read_unaligned();
+        regs.cs = stack.add(1).read_unaligned();
+    }
+    regs.sp = regs.sp.wrapping_add(4);
+
+    let head = RING_HEAD.load(Relaxed);
+    if regs.eax as u16 == 0 {
+        // Hand out the next slot, or ES:DI = 0:0 to have the packet dropped.
+        let len = regs.ecx as u16;
+        if usize::from(len) <= SLOT_SIZE && RING_LENGTHS[head].load(Acquire) == 0 {
+            regs.es = RING_SEGMENT.load(Relaxed);
+            regs.edi = (head * SLOT_SIZE) as u32;
+        } else {
+            regs.es = 0;
+            regs.edi = 0;
+        }
+    } else {
+        RING_LENGTHS[head].store(regs.ecx as u16, Release);
+        RING_HEAD.store((head + 1) % SLOTS, Relaxed);
+    }
+}
+
+/// The packet driver we receive from, with IPv4 and ARP registered.
+pub struct Driver {
+    interrupt: u8,
+    mac: MacAddr,
+    tail: usize,
+}
+
+impl Driver {
+    /// Finds the packet driver at `interrupt`, or the first one in 60h–80h,
+    /// and starts receiving from it.
+    pub fn open(interrupt: Option<u8>) -> io::Result<Driver> {
+        let interrupt = match interrupt {
+            Some(interrupt) if has_signature(interrupt) => interrupt,
+            Some(_) => {
+                return Err(io::const_error!(
+                    io::ErrorKind::NotFound,
+                    "no packet driver at the interrupt in PACKETINT",
+                ));
+            }
+            None => (FIRST_INTERRUPT..=LAST_INTERRUPT)
+                .find(|&i| has_signature(i))
+                .ok_or(io::const_error!(io::ErrorKind::NotFound, "no packet driver is loaded"))?,
+        };
+
+        // driver_info: AH = 1, AL = FFh. CH is the interface class.
+        let mut regs = RealModeRegisters { eax: 0x01ff, ..Default::default() };
+        call(interrupt, &mut regs)?;
+        if (regs.ecx >> 8) as u8 != CLASS_ETHERNET {
+            return Err(io::const_error!(
+                io::ErrorKind::Unsupported,
+                "the packet driver is not for Ethernet",
+            ));
+        }
+
+        let ring = dpmi::allocate_dos_memory((SLOTS * SLOT_SIZE / 16) as u16).map_err(|_| {
+            io::const_error!(
+                io::ErrorKind::OutOfMemory,
+                "not enough conventional memory for the packet buffers",
+            )
+        })?;
+        RING_SEGMENT.store(ring.segment, Relaxed);
+        RING_SELECTOR.store(ring.selector, Relaxed);
+        for length in &RING_LENGTHS {
+            length.store(0, Relaxed);
+        }
+        RING_HEAD.store(0, Relaxed);
+
+        dpmi::init_interrupt_entry();
+        let regs = unsafe { &raw mut RECEIVER_REGS };
+        let Some(callback) =
+            (unsafe { dpmi::allocate_real_mode_callback(__msdos6_packet_receiver, regs) })
+        else {
+            close();
+            return Err(io::const_error!(
+                io::ErrorKind::OutOfMemory,
+                "the DPMI host has no real-mode callbacks left",
+            ));
+        };
+        CALLBACK.store(u32::from(callback.0) << 16 | u32::from(callback.1), Relaxed);
+        INTERRUPT.store(interrupt, Relaxed);
+
+        for (handle, ethertype) in HANDLES.iter().zip([ETHERTYPE_IPV4, ETHERTYPE_ARP]) {
+            match access_type(interrupt, ethertype, callback) {
+                Ok(h) => handle.store(h, Relaxed),
+                Err(e) => {
+                    close();
+                    return Err(e);
+                }
+            }
+        }
+
+        // get_address: AH = 6, BX = handle, ES:DI = buffer, CX = its size.
+        let mut transfer = dos::transfer_buffer();
+        let mut regs = RealModeRegisters {
+            eax: 0x0600,
+            ebx: HANDLES[0].load(Relaxed).into(),
+            ecx: 6,
+            es: transfer.segment(),
+            ..Default::default()
+        };
+        if let Err(e) = call(interrupt, &mut regs) {
+            close();
+            return Err(e);
+        }
+        let mut mac = [0; 6];
+        mac.copy_from_slice(&transfer.as_mut_slice()[..6]);
+
+        Ok(Driver { interrupt, mac, tail: 0 })
+    }
+
+    pub fn mac(&self) -> MacAddr {
+        self.mac
+    }
+
+    /// send_pkt: sends `frame`, an Ethernet frame without the checksum.
+    pub fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        assert!(frame.len() <= MAX_FRAME);
+        let mut transfer = dos::transfer_buffer();
+        let buffer = transfer.as_mut_slice();
+        buffer[..frame.len()].copy_from_slice(frame);
+        let len = frame.len().max(MIN_FRAME);
+        buffer[frame.len()..len].fill(0);
+
+        // send_pkt: AH = 4, DS:SI = frame, CX = its length.
+        let mut regs = RealModeRegisters {
+            eax: 0x0400,
+            ecx: len as u32,
+            ds: transfer.segment(),
+            ..Default::default()
+        };
+        call(self.interrupt, &mut regs)
+    }
+
+    /// Moves the oldest received frame into `frame`. Returns false if there is none.
+    pub fn next_frame(&mut self, frame: &mut Vec<u8>) -> bool {
+        let len = RING_LENGTHS[self.tail].load(Acquire);
+        if len == 0 {
+            return false;
+        }
+        let linear = u32::from(RING_SEGMENT.load(Relaxed)) * 16 + (self.tail * SLOT_SIZE) as u32;
+        let slot = dpmi::linear_to_ptr(linear);
+        frame.clear();
+        frame.extend_from_slice(unsafe { crate::slice::from_raw_parts(slot, len.into()) });
+        RING_LENGTHS[self.tail].store(0, Release);
+        self.tail = (self.tail + 1) % SLOTS;
+        true
+    }
+}
+
+impl Drop for Driver {
+    fn drop(&mut self) {
+        close();
+    }
+}
+
+/// The packet driver interrupt named in the PACKETINT variable, in hex like `0x60` or `60`.
+pub fn configured_interrupt() -> Option<u8> {
+    let value = os::getenv(OsStr::new("PACKETINT"))?;
+    let value = value.to_str()?.trim();
+    let digits = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")).unwrap_or(value);
+    u8::from_str_radix(digits, 16).ok()
+}
+
+/// Releases the types we registered and the memory the driver writes to.
+pub fn close() {
+    let interrupt = INTERRUPT.swap(0, Relaxed);
+    if interrupt != 0 {
+        for handle in &HANDLES {
+            // release_type: AH = 3, BX = handle.
+            let handle = handle.swap(0, Relaxed);
+            if handle != 0 {
+                let mut regs =
+                    RealModeRegisters { eax: 0x0300, ebx: handle.into(), ..Default::default() };
+                let _ = call(interrupt, &mut regs);
+            }
+        }
+    }
+    let callback = CALLBACK.swap(0, Relaxed);
+    if callback != 0 {
+        unsafe { dpmi::free_real_mode_callback(((callback >> 16) as u16, callback as u16)) };
+    }
+    let selector = RING_SELECTOR.swap(0, Relaxed);
+    if selector != 0 {
+        unsafe { dpmi::free_dos_memory(selector) };
+    }
+}
+
+fn has_signature(interrupt: u8) -> bool {
+    let (segment, offset) = dpmi::real_mode_vector(interrupt);
+    if segment == 0 && offset == 0 {
+        return false;
+    }
+    let linear = u32::from(segment) * 16 + u32::from(offset.wrapping_add(3));
+    let handler = dpmi::linear_to_ptr(linear);
+    unsafe { crate::slice::from_raw_parts(handler, SIGNATURE.len()) == SIGNATURE }
+}
+
+/// access_type: registers for frames of `ethertype`, to be handed to
+/// `receiver`. Returns the handle for them.
+fn access_type(interrupt: u8, ethertype: u16, (segment, offset): (u16, u16)) -> io::Result<u16> {
+    let mut transfer = dos::transfer_buffer();
+    transfer.as_mut_slice()[..2].copy_from_slice(&ethertype.to_be_bytes());
+
+    // access_type: AH = 2, AL = class, BX = type (any), DL = number (any),
+    // DS:SI = packet type, CX = its length, ES:DI = receiver.
+    let mut regs = RealModeRegisters {
+        eax: 0x0200 | u32::from(CLASS_ETHERNET),
+        ebx: 0xffff,
+        ecx: 2,
+        ds: transfer.segment(),
+        es: segment,
+        edi: offset.into(),
+        ..Default::default()
+    };
+    call(interrupt, &mut regs)?;
+    Ok(regs.eax as u16)
+}
+
+/// Calls the packet driver, which reports errors with the carry flag and a code in DH.
+fn call(interrupt: u8, regs: &mut RealModeRegisters) -> io::Result<()> {
+    dos::int86(interrupt, regs)?;
+    if regs.flags & dos::CARRY_FLAG == 0 {
+        return Ok(());
+    }
+    Err(match (regs.edx >> 8) as u8 {
+        1 => io::const_error!(io::ErrorKind::InvalidInput, "packet driver: invalid handle"),
+        2 | 4 => io::const_error!(io::ErrorKind::NotFound, "packet driver: no such interface"),
+        3 | 5 => io::const_error!(io::ErrorKind::Unsupported, "packet driver: bad packet type"),
+        9 => io::const_error!(io::ErrorKind::OutOfMemory, "packet driver: out of space"),
+        10 => io::const_error!(io::ErrorKind::AddrInUse, "packet driver: type already in use"),
+        11 => io::const_error!(io::ErrorKind::Unsupported, "packet driver: unknown command"),
+        12 => io::const_error!(io::ErrorKind::NetworkDown, "packet driver: cannot send"),
+        _ => io::const_error!(io::ErrorKind::Other, "packet driver error"),
+    })
+}
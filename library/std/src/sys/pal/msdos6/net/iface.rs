@@ -0,0 +1,810 @@
+//! The network interface: the Ethernet card behind the packet driver, with
+//! one IPv4 address, and the sockets using it.
+//!
+//! Nothing runs in the background. Received frames wait in the driver's ring
+//! until someone calls `poll`, which every blocking socket operation does
+//! between yields to other threads, and which also drives the TCP timers.
+
+use super::super::os;
+use super::dhcp;
+#[cfg(not(test))]
+use super::driver::Driver;
+use super::driver::{self, BROADCAST, ETHERTYPE_ARP, ETHERTYPE_IPV4, MacAddr};
+#[cfg(test)]
+use super::loopback::Driver;
+use super::tcp::{self, Listener, Tcp};
+use super::udp::Datagrams;
+use crate::collections::VecDeque;
+use crate::ffi::OsStr;
+use crate::io;
+use crate::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use crate::sync::{Mutex, PoisonError};
+use crate::sys::thread::Thread;
+use crate::sys::time::Instant;
+use crate::time::Duration;
+
+#[cfg(test)]
+mod tests;
+
+pub const PROTOCOL_ICMP: u8 = 1;
+pub const PROTOCOL_TCP: u8 = 6;
+pub const PROTOCOL_UDP: u8 = 17;
+
+const IPV4_HEADER: usize = 20;
+const ETHERNET_HEADER: usize = 14;
+/// The most an IPv4 packet can carry without being fragmented.
+pub const MAX_IP_PAYLOAD: usize = driver::MAX_FRAME - ETHERNET_HEADER - IPV4_HEADER;
+
+pub const DEFAULT_TTL: u8 = 64;
+
+const ARP_REQUEST: u16 = 1;
+const ARP_REPLY: u16 = 2;
+const ARP_CACHE_SIZE: usize = 16;
+const ARP_LIFETIME: Duration = Duration::from_secs(300);
+const ARP_TRIES: u32 = 3;
+const ARP_TIMEOUT: Duration = Duration::from_secs(1);
+
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+
+/// The addresses of the interface, from DHCP or from the environment.
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    pub address: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+    pub gateway: Option<Ipv4Addr>,
+    pub nameserver: Option<Ipv4Addr>,
+}
+
+impl Config {
+    /// No address yet, while DHCP runs.
+    const UNCONFIGURED: Config = Config {
+        address: Ipv4Addr::UNSPECIFIED,
+        netmask: Ipv4Addr::UNSPECIFIED,
+        gateway: None,
+        nameserver: None,
+    };
+
+    /// Reads a static configuration from IPADDR, NETMASK, GATEWAY and
+    /// NAMESERVER. Returns `None` if IPADDR is not set or is `DHCP`.
+    fn from_env() -> io::Result<Option<Config>> {
+        match os::getenv(OsStr::new("IPADDR")) {
+            None => return Ok(None),
+            Some(value) if value.eq_ignore_ascii_case("DHCP") => return Ok(None),
+            Some(_) => {}
+        }
+        Ok(Some(Config {
+            address: env_address("IPADDR")?.unwrap_or(Ipv4Addr::UNSPECIFIED),
+            netmask: env_address("NETMASK")?.unwrap_or(Ipv4Addr::new(255, 255, 255, 0)),
+            gateway: env_address("GATEWAY")?,
+            nameserver: env_address("NAMESERVER")?,
+        }))
+    }
+
+    fn broadcast(&self) -> Ipv4Addr {
+        Ipv4Addr::from_bits(self.address.to_bits() | !self.netmask.to_bits())
+    }
+
+    fn is_on_link(&self, address: Ipv4Addr) -> bool {
+        let mask = self.netmask.to_bits();
+        address.to_bits() & mask == self.address.to_bits() & mask
+    }
+}
+
+fn env_address(name: &str) -> io::Result<Option<Ipv4Addr>> {
+    let Some(value) = os::getenv(OsStr::new(name)) else { return Ok(None) };
+    match value.to_str().and_then(|value| value.trim().parse().ok()) {
+        Some(address) => Ok(Some(address)),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{name} is not an IPv4 address"),
+        )),
+    }
+}
+
+struct ArpEntry {
+    address: Ipv4Addr,
+    mac: MacAddr,
+    updated: Instant,
+}
+
+/// What the interface needs to send: the driver, our addresses and the ARP cache.
+pub struct Link {
+    driver: Driver,
+    pub mac: MacAddr,
+    pub config: Config,
+    arp: Vec<ArpEntry>,
+    /// IPv4 packets sent to ourselves, received on the next `poll`.
+    loopback: VecDeque<Vec<u8>>,
+    ip_id: u16,
+}
+
+/// Where to send a packet on the link.
+pub enum Route {
+    Direct(MacAddr),
+    /// The link address of this neighbour has to be asked for with ARP first.
+    Resolve(Ipv4Addr),
+}
+
+impl Link {
+    pub fn new(driver: Driver, config: Config) -> Link {
+        Link {
+            mac: driver.mac(),
+            driver,
+            config,
+            arp: Vec::new(),
+            loopback: VecDeque::new(),
+            ip_id: 0,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn driver(&mut self) -> &mut Driver {
+        &mut self.driver
+    }
+
+    /// Whether packets to `address` stay on this machine.
+    fn is_local(&self, address: Ipv4Addr) -> bool {
+        address.is_loopback() || (address == self.config.address && !address.is_unspecified())
+    }
+
+    fn is_broadcast(&self, address: Ipv4Addr) -> bool {
+        address.is_broadcast()
+            || (!self.config.address.is_unspecified() && address == self.config.broadcast())
+    }
+
+    /// Whether a packet to `address` is for us.
+    fn accepts(&self, address: Ipv4Addr) -> bool {
+        // Until DHCP has given us an address, the offers may be sent to the
+        // address they offer.
+        self.config.address.is_unspecified() || self.is_local(address) || self.is_broadcast(address)
+    }
+
+    /// The address packets to `destination` are sent from.
+    pub fn source_for(&self, destination: Ipv4Addr) -> Ipv4Addr {
+        if destination.is_loopback() { Ipv4Addr::LOCALHOST } else { self.config.address }
+    }
+
+    pub fn route(&self, destination: Ipv4Addr) -> io::Result<Route> {
+        if self.is_local(destination) {
+            return Ok(Route::Direct(self.mac));
+        }
+        if self.is_broadcast(destination) {
+            return Ok(Route::Direct(BROADCAST));
+        }
+        if destination.is_multicast() {
+            return Err(io::const_error!(
+                io::ErrorKind::Unsupported,
+                "multicast is not supported on this platform",
+            ));
+        }
+        if self.config.address.is_unspecified() {
+            return Err(io::const_error!(
+                io::ErrorKind::NetworkDown,
+                "the network interface has no address",
+            ));
+        }
+        let hop = if self.config.is_on_link(destination) {
+            destination
+        } else {
+            self.config.gateway.ok_or(io::const_error!(
+                io::ErrorKind::NetworkUnreachable,
+                "no gateway is configured for addresses off the local network",
+            ))?
+        };
+        Ok(match self.arp_lookup(hop) {
+            Some(mac) => Route::Direct(mac),
+            None => Route::Resolve(hop),
+        })
+    }
+
+    fn arp_lookup(&self, address: Ipv4Addr) -> Option<MacAddr> {
+        let entry = self.arp.iter().find(|entry| entry.address == address)?;
+        let age = Instant::now().checked_sub_instant(&entry.updated)?;
+        (age < ARP_LIFETIME).then_some(entry.mac)
+    }
+
+    /// Records the link address of `address`. Unless `create`, only an entry
+    /// already in the cache is updated, as RFC 826 has it.
+    fn arp_update(&mut self, address: Ipv4Addr, mac: MacAddr, create: bool) {
+        let now = Instant::now();
+        if let Some(entry) = self.arp.iter_mut().find(|entry| entry.address == address) {
+            entry.mac = mac;
+            entry.updated = now;
+        } else if create {
+            if self.arp.len() == ARP_CACHE_SIZE {
+                let oldest = (0..self.arp.len()).min_by_key(|&i| self.arp[i].updated).unwrap();
+                self.arp.swap_remove(oldest);
+            }
+            self.arp.push(ArpEntry { address, mac, updated: now });
+        }
+    }
+
+    fn send_arp(
+        &mut self,
+        operation: u16,
+        destination: MacAddr,
+        target: (MacAddr, Ipv4Addr),
+    ) -> io::Result<()> {
+        let mut packet = Vec::with_capacity(28);
+        // Ethernet, IPv4, and the lengths of their addresses.
+        packet.extend_from_slice(&[0, 1, 0x08, 0x00, 6, 4]);
+        packet.extend_from_slice(&operation.to_be_bytes());
+        packet.extend_from_slice(&self.mac);
+        packet.extend_from_slice(&self.config.address.octets());
+        packet.extend_from_slice(&target.0);
+        packet.extend_from_slice(&target.1.octets());
+        self.send_frame(destination, ETHERTYPE_ARP, &packet)
+    }
+
+    fn receive_arp(&mut self, packet: &[u8]) {
+        if packet.len() < 28 || packet[..6] != [0, 1, 0x08, 0x00, 6, 4] {
+            return;
+        }
+        let operation = u16::from_be_bytes([packet[6], packet[7]]);
+        let sender_mac: MacAddr = packet[8..14].try_into().unwrap();
+        let sender = Ipv4Addr::new(packet[14], packet[15], packet[16], packet[17]);
+        let target = Ipv4Addr::new(packet[24], packet[25], packet[26], packet[27]);
+
+        let for_us = !self.config.address.is_unspecified() && target == self.config.address;
+        if !sender.is_unspecified() {
+            self.arp_update(sender, sender_mac, for_us);
+        }
+        if for_us && operation == ARP_REQUEST {
+            let _ = self.send_arp(ARP_REPLY, sender_mac, (sender_mac, sender));
+        }
+    }
+
+    fn send_frame(
+        &mut self,
+        destination: MacAddr,
+        ethertype: u16,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(ETHERNET_HEADER + payload.len());
+        frame.extend_from_slice(&destination);
+        frame.extend_from_slice(&self.mac);
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(payload);
+        self.driver.send(&frame)
+    }
+
+    /// Sends an IPv4 packet to `destination` through the neighbour at `mac`.
+    pub fn send_ip(
+        &mut self,
+        mac: MacAddr,
+        (source, destination): (Ipv4Addr, Ipv4Addr),
+        protocol: u8,
+        ttl: u8,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        if payload.len() > MAX_IP_PAYLOAD {
+            return Err(io::const_error!(
+                io::ErrorKind::InvalidInput,
+                "packet too large, IP fragmentation is not supported",
+            ));
+        }
+        self.ip_id = self.ip_id.wrapping_add(1);
+        let mut packet = Vec::with_capacity(IPV4_HEADER + payload.len());
+        packet.extend_from_slice(&[0x45, 0]);
+        packet.extend_from_slice(&((IPV4_HEADER + payload.len()) as u16).to_be_bytes());
+        packet.extend_from_slice(&self.ip_id.to_be_bytes());
+        // Don't fragment.
+        packet.extend_from_slice(&[0x40, 0, ttl, protocol, 0, 0]);
+        packet.extend_from_slice(&source.octets());
+        packet.extend_from_slice(&destination.octets());
+        let sum = checksum(0, &packet);
+        packet[10..12].copy_from_slice(&sum.to_be_bytes());
+        packet.extend_from_slice(payload);
+
+        if self.is_local(destination) {
+            self.loopback.push_back(packet);
+            return Ok(());
+        }
+        self.send_frame(mac, ETHERTYPE_IPV4, &packet)
+    }
+
+    /// Answers pings.
+    fn receive_icmp(&mut self, mac: MacAddr, packet: &Ipv4Packet<'_>) {
+        const ECHO_REPLY: u8 = 0;
+        const ECHO_REQUEST: u8 = 8;
+
+        let message = packet.payload;
+        if message.len() < 8 || message[0] != ECHO_REQUEST || checksum(0, message) != 0 {
+            return;
+        }
+        if self.is_broadcast(packet.destination) {
+            return;
+        }
+        let mut reply = message.to_vec();
+        reply[0] = ECHO_REPLY;
+        reply[2..4].fill(0);
+        let sum = checksum(0, &reply);
+        reply[2..4].copy_from_slice(&sum.to_be_bytes());
+        let addresses = (packet.destination, packet.source);
+        let _ = self.send_ip(mac, addresses, PROTOCOL_ICMP, DEFAULT_TTL, &reply);
+    }
+}
+
+/// A received IPv4 packet.
+pub struct Ipv4Packet<'a> {
+    pub source: Ipv4Addr,
+    pub destination: Ipv4Addr,
+    pub protocol: u8,
+    pub payload: &'a [u8],
+}
+
+impl<'a> Ipv4Packet<'a> {
+    fn parse(packet: &'a [u8]) -> Option<Ipv4Packet<'a>> {
+        let header = usize::from(packet.first()? & 0xf) * 4;
+        if packet[0] >> 4 != 4 || header < IPV4_HEADER || packet.len() < header {
+            return None;
+        }
+        let total = usize::from(u16::from_be_bytes([packet[2], packet[3]]));
+        if total < header || total > packet.len() || checksum(0, &packet[..header]) != 0 {
+            return None;
+        }
+        // Fragments are not reassembled.
+        if u16::from_be_bytes([packet[6], packet[7]]) & 0x3fff != 0 {
+            return None;
+        }
+        Some(Ipv4Packet {
+            source: Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]),
+            destination: Ipv4Addr::new(packet[16], packet[17], packet[18], packet[19]),
+            protocol: packet[9],
+            payload: &packet[header..total],
+        })
+    }
+
+    /// The checksum of the pseudo-header TCP and UDP checksums start from.
+    pub fn pseudo_header_sum(&self) -> u32 {
+        pseudo_header_sum((self.source, self.destination), self.protocol, self.payload.len())
+    }
+}
+
+pub fn pseudo_header_sum(
+    (source, destination): (Ipv4Addr, Ipv4Addr),
+    protocol: u8,
+    len: usize,
+) -> u32 {
+    let mut header = [0; 12];
+    header[..4].copy_from_slice(&source.octets());
+    header[4..8].copy_from_slice(&destination.octets());
+    header[9] = protocol;
+    header[10..].copy_from_slice(&(len as u16).to_be_bytes());
+    sum(0, &header)
+}
+
+/// Adds `data` to a running ones' complement sum.
+pub fn sum(mut sum: u32, data: &[u8]) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+    for chunk in &mut chunks {
+        sum += u32::from(u16::from_be_bytes([chunk[0], chunk[1]]));
+    }
+    if let [last] = chunks.remainder() {
+        sum += u32::from(*last) << 8;
+    }
+    sum
+}
+
+/// The Internet checksum of `data`, continuing from `initial`. Checking
+/// data that includes its checksum gives zero.
+pub fn checksum(initial: u32, data: &[u8]) -> u16 {
+    let mut sum = sum(initial, data);
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Options every kind of socket has.
+#[derive(Copy, Clone)]
+pub struct Options {
+    pub read_timeout: Option<Duration>,
+    pub write_timeout: Option<Duration>,
+    pub nonblocking: bool,
+    pub ttl: u8,
+}
+
+impl Options {
+    pub const fn new() -> Options {
+        Options { read_timeout: None, write_timeout: None, nonblocking: false, ttl: DEFAULT_TTL }
+    }
+}
+
+pub enum Socket {
+    Udp(Datagrams),
+    Tcp(Tcp),
+    Listener(Listener),
+}
+
+struct Entry {
+    /// The number of socket objects and listener queues referring to the socket.
+    refs: usize,
+    socket: Socket,
+}
+
+/// Refers to an entry in the socket table.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Handle(usize);
+
+pub struct Interface {
+    pub link: Link,
+    sockets: Vec<Option<Entry>>,
+    next_port: u16,
+}
+
+static INTERFACE: Mutex<Option<Interface>> = Mutex::new(None);
+
+/// Runs `f` with the interface, bringing it up first if need be.
+pub fn with_interface<R>(f: impl FnOnce(&mut Interface) -> io::Result<R>) -> io::Result<R> {
+    let mut interface = INTERFACE.lock().unwrap_or_else(PoisonError::into_inner);
+    if interface.is_none() {
+        *interface = Some(Interface::up()?);
+    }
+    f(interface.as_mut().unwrap())
+}
+
+/// Polls the network and calls `f` until it returns something, or the
+/// deadline passes. Other threads run in between.
+pub fn wait<R>(
+    deadline: Option<Instant>,
+    nonblocking: bool,
+    mut f: impl FnMut(&mut Interface) -> Option<io::Result<R>>,
+) -> io::Result<R> {
+    loop {
+        if let Some(result) = with_interface(|interface| {
+            interface.poll();
+            Ok(f(interface))
+        })? {
+            return result;
+        }
+        if nonblocking {
+            return Err(io::Error::WOULD_BLOCK);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(io::const_error!(io::ErrorKind::TimedOut, "timed out"));
+        }
+        Thread::yield_now();
+    }
+}
+
+/// The time `timeout` from now, for `wait`.
+pub fn deadline(timeout: Option<Duration>) -> Option<Instant> {
+    timeout.and_then(|timeout| Instant::now().checked_add_duration(&timeout))
+}
+
+/// Finds the link address to send to `destination` with, asking for it with ARP if need be.
+pub fn resolve(destination: Ipv4Addr, deadline: Option<Instant>) -> io::Result<MacAddr> {
+    for _ in 0..ARP_TRIES {
+        let hop = match with_interface(|interface| interface.link.route(destination))? {
+            Route::Direct(mac) => return Ok(mac),
+            Route::Resolve(hop) => hop,
+        };
+        with_interface(|interface| interface.link.send_arp(ARP_REQUEST, BROADCAST, ([0; 6], hop)))?;
+
+        let retry = Instant::now().checked_add_duration(&ARP_TIMEOUT);
+        let until = match (retry, deadline) {
+            (Some(retry), Some(deadline)) => Some(retry.min(deadline)),
+            (retry, deadline) => retry.or(deadline),
+        };
+        match wait(until, false, |interface| interface.link.arp_lookup(hop).map(Ok)) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return Err(e);
+                }
+            }
+            result => return result,
+        }
+    }
+    Err(io::const_error!(io::ErrorKind::HostUnreachable, "no ARP reply from the host"))
+}
+
+/// Drops a reference to a socket, closing it when it was the last.
+pub fn release(handle: Handle) {
+    let _ = with_interface(|interface| {
+        interface.release(handle);
+        Ok(())
+    });
+}
+
+/// Makes the interface unusable before the program exits, so that the
+/// packet driver stops calling into it.
+pub fn shutdown() {
+    driver::close();
+}
+
+impl Interface {
+    fn up() -> io::Result<Interface> {
+        let driver = Driver::open(driver::configured_interrupt())?;
+        let config = Config::from_env()?;
+        let mut port = [0; 2];
+        crate::sys::random::fill_bytes(&mut port);
+        let mut interface = Interface {
+            link: Link::new(driver, config.unwrap_or(Config::UNCONFIGURED)),
+            sockets: Vec::new(),
+            next_port: FIRST_EPHEMERAL_PORT | u16::from_ne_bytes(port),
+        };
+        if config.is_none() {
+            dhcp::configure(&mut interface)?;
+        }
+        Ok(interface)
+    }
+
+    /// Like `wait`, but keeps the interface to itself.
+    pub fn block_on<R>(
+        &mut self,
+        deadline: Instant,
+        mut f: impl FnMut(&mut Interface) -> Option<R>,
+    ) -> Option<R> {
+        loop {
+            self.poll();
+            if let Some(result) = f(self) {
+                return Some(result);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            Thread::yield_now();
+        }
+    }
+
+    /// Handles what was received, and sends what is due.
+    pub fn poll(&mut self) {
+        let mut frame = Vec::new();
+        while self.link.driver.next_frame(&mut frame) {
+            self.receive_frame(&frame);
+        }
+        while let Some(packet) = self.link.loopback.pop_front() {
+            self.receive_ip(self.link.mac, &packet);
+        }
+
+        let now = Instant::now();
+        for entry in self.sockets.iter_mut() {
+            let Some(Entry { refs, socket: Socket::Tcp(tcp) }) = entry else { continue };
+            tcp.poll(now, &mut self.link);
+            if *refs == 0 && tcp.is_closed() {
+                *entry = None;
+            }
+        }
+    }
+
+    fn receive_frame(&mut self, frame: &[u8]) {
+        if frame.len() < ETHERNET_HEADER {
+            return;
+        }
+        let source: MacAddr = frame[6..12].try_into().unwrap();
+        let payload = &frame[ETHERNET_HEADER..];
+        match u16::from_be_bytes([frame[12], frame[13]]) {
+            ETHERTYPE_ARP => self.link.receive_arp(payload),
+            ETHERTYPE_IPV4 => self.receive_ip(source, payload),
+            _ => {}
+        }
+    }
+
+    fn receive_ip(&mut self, mac: MacAddr, packet: &[u8]) {
+        let Some(packet) = Ipv4Packet::parse(packet) else { return };
+        if !self.link.accepts(packet.destination) {
+            return;
+        }
+        match packet.protocol {
+            PROTOCOL_ICMP => self.link.receive_icmp(mac, &packet),
+            PROTOCOL_UDP => self.receive_udp(&packet),
+            PROTOCOL_TCP => self.receive_tcp(mac, &packet),
+            _ => {}
+        }
+    }
+
+    fn receive_udp(&mut self, packet: &Ipv4Packet<'_>) {
+        let data = packet.payload;
+        if data.len() < 8 {
+            return;
+        }
+        let len = usize::from(u16::from_be_bytes([data[4], data[5]]));
+        if len < 8 || len > data.len() {
+            return;
+        }
+        // A zero checksum means the sender didn't compute one.
+        if data[6..8] != [0, 0] {
+            let sum = pseudo_header_sum((packet.source, packet.destination), PROTOCOL_UDP, len);
+            if checksum(sum, &data[..len]) != 0 {
+                return;
+            }
+        }
+        let source = SocketAddrV4::new(packet.source, u16::from_be_bytes([data[0], data[1]]));
+        let port = u16::from_be_bytes([data[2], data[3]]);
+        let socket = self.sockets.iter_mut().flatten().find_map(|entry| match &mut entry.socket {
+            Socket::Udp(udp) if udp.accepts(port, source) => Some(udp),
+            _ => None,
+        });
+        if let Some(udp) = socket {
+            udp.push(source, &data[8..len]);
+        }
+    }
+
+    fn receive_tcp(&mut self, mac: MacAddr, packet: &Ipv4Packet<'_>) {
+        let Some(segment) = tcp::Segment::parse(packet) else { return };
+        if self.link.is_broadcast(packet.destination) {
+            return;
+        }
+        let local = SocketAddrV4::new(packet.destination, segment.destination_port);
+        let remote = SocketAddrV4::new(packet.source, segment.source_port);
+
+        let connection = self.sockets.iter().position(|entry| {
+            matches!(entry, Some(Entry { socket: Socket::Tcp(tcp), .. })
+                if tcp.local == local && tcp.remote == remote)
+        });
+        if let Some(index) = connection {
+            let (tcp, link) = self.tcp(Handle(index));
+            if tcp.receive(&segment, link) {
+                // Now that it is established, hand the connection to `accept`.
+                if let Some(listener) = tcp.listener.take() {
+                    self.listener(listener).backlog.push_back(Handle(index));
+                    self.retain(Handle(index));
+                }
+            }
+            return;
+        }
+
+        let listener = self.sockets.iter().position(|entry| {
+            matches!(entry, Some(Entry { socket: Socket::Listener(listener), .. })
+                if listener.accepts(local))
+        });
+        match listener {
+            Some(index) if segment.is_connection_request() => {
+                let pending = self
+                    .sockets
+                    .iter()
+                    .flatten()
+                    .filter(|entry| {
+                        matches!(&entry.socket,
+                    Socket::Tcp(tcp) if tcp.listener == Some(Handle(index)))
+                    })
+                    .count();
+                let listener = self.listener(Handle(index));
+                if pending + listener.backlog.len() >= tcp::BACKLOG {
+                    return;
+                }
+                // Only the TTL carries over to the accepted connection.
+                let options = Options { ttl: listener.options.ttl, ..Options::new() };
+                let tcp = Tcp::accept(local, remote, mac, &segment, Handle(index), options);
+                let handle = self.insert(Socket::Tcp(tcp), 0);
+                let (tcp, link) = self.tcp(handle);
+                tcp.poll(Instant::now(), link);
+            }
+            _ => tcp::reset(&mut self.link, mac, (local, remote), &segment),
+        }
+    }
+
+    /// Adds a socket to the table, referred to `refs` times.
+    pub fn insert(&mut self, socket: Socket, refs: usize) -> Handle {
+        let entry = Some(Entry { refs, socket });
+        match self.sockets.iter().position(Option::is_none) {
+            Some(index) => {
+                self.sockets[index] = entry;
+                Handle(index)
+            }
+            None => {
+                self.sockets.push(entry);
+                Handle(self.sockets.len() - 1)
+            }
+        }
+    }
+
+    fn entry(&mut self, handle: Handle) -> &mut Entry {
+        self.sockets[handle.0].as_mut().expect("socket handle refers to a closed socket")
+    }
+
+    pub fn retain(&mut self, handle: Handle) {
+        self.entry(handle).refs += 1;
+    }
+
+    pub fn release(&mut self, handle: Handle) {
+        let Some(entry) = &mut self.sockets[handle.0] else {
+            unreachable!("socket handle refers to a closed socket")
+        };
+        entry.refs -= 1;
+        if entry.refs > 0 {
+            return;
+        }
+        match &mut entry.socket {
+            Socket::Tcp(tcp) => {
+                // Let it close in the background; `poll` drops it once closed.
+                tcp.close(&mut self.link);
+                if tcp.is_closed() {
+                    self.sockets[handle.0] = None;
+                }
+            }
+            Socket::Udp(_) => self.sockets[handle.0] = None,
+            Socket::Listener(listener) => {
+                let backlog = crate::mem::take(&mut listener.backlog);
+                self.sockets[handle.0] = None;
+                for connection in backlog {
+                    self.release(connection);
+                }
+                // Connections still being set up go away with it.
+                for entry in self.sockets.iter_mut() {
+                    if let Some(Entry { socket: Socket::Tcp(tcp), .. }) = entry {
+                        if tcp.listener == Some(handle) {
+                            tcp.abort(&mut self.link);
+                            *entry = None;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn socket(&mut self, handle: Handle) -> &mut Socket {
+        &mut self.entry(handle).socket
+    }
+
+    pub fn tcp(&mut self, handle: Handle) -> (&mut Tcp, &mut Link) {
+        match &mut self.sockets[handle.0] {
+            Some(Entry { socket: Socket::Tcp(tcp), .. }) => (tcp, &mut self.link),
+            _ => unreachable!("not a TCP socket"),
+        }
+    }
+
+    pub fn udp(&mut self, handle: Handle) -> (&mut Datagrams, &mut Link) {
+        match &mut self.sockets[handle.0] {
+            Some(Entry { socket: Socket::Udp(udp), .. }) => (udp, &mut self.link),
+            _ => unreachable!("not a UDP socket"),
+        }
+    }
+
+    pub fn listener(&mut self, handle: Handle) -> &mut Listener {
+        match self.socket(handle) {
+            Socket::Listener(listener) => listener,
+            _ => unreachable!("not a TCP listener"),
+        }
+    }
+
+    fn port_in_use(&self, port: u16, protocol: u8) -> bool {
+        self.sockets.iter().flatten().any(|entry| match &entry.socket {
+            Socket::Udp(udp) => protocol == PROTOCOL_UDP && udp.local.port() == port,
+            Socket::Tcp(tcp) => protocol == PROTOCOL_TCP && tcp.local.port() == port,
+            Socket::Listener(listener) => protocol == PROTOCOL_TCP && listener.local.port() == port,
+        })
+    }
+
+    /// Picks the local address for a socket bound to `address`.
+    pub fn bind(&mut self, address: SocketAddr, protocol: u8) -> io::Result<SocketAddrV4> {
+        let address = ipv4(address)?;
+        let ip = *address.ip();
+        if !ip.is_unspecified() && !self.link.is_local(ip) {
+            return Err(io::const_error!(
+                io::ErrorKind::AddrNotAvailable,
+                "address is not assigned to this machine",
+            ));
+        }
+        let port = match address.port() {
+            0 => self.ephemeral_port(protocol),
+            port if self.port_in_use(port, protocol) => {
+                return Err(io::const_error!(io::ErrorKind::AddrInUse, "address in use"));
+            }
+            port => port,
+        };
+        Ok(SocketAddrV4::new(ip, port))
+    }
+
+    pub fn ephemeral_port(&mut self, protocol: u8) -> u16 {
+        loop {
+            let port = self.next_port;
+            self.next_port = self.next_port.checked_add(1).unwrap_or(FIRST_EPHEMERAL_PORT);
+            if !self.port_in_use(port, protocol) {
+                return port;
+            }
+        }
+    }
+}
+
+/// Only IPv4 is supported.
+pub fn ipv4(address: SocketAddr) -> io::Result<SocketAddrV4> {
+    match address {
+        SocketAddr::V4(address) => Ok(address),
+        SocketAddr::V6(_) => Err(io::const_error!(
+            io::ErrorKind::Unsupported,
+            "IPv6 is not supported on this platform",
+        )),
+    }
+}
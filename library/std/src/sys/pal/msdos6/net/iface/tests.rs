@@ -0,0 +1,65 @@
+use super::*;
+
+const OUR_MAC: MacAddr = [0x02, 0, 0, 0, 0, 1];
+const PEER_MAC: MacAddr = [0x02, 0, 0, 0, 0, 2];
+const OUR_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+const PEER_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+
+fn link() -> Link {
+    let config = Config {
+        address: OUR_IP,
+        netmask: Ipv4Addr::new(255, 255, 255, 0),
+        gateway: None,
+        nameserver: None,
+    };
+    Link::new(Driver::new(OUR_MAC), config)
+}
+
+#[test]
+fn checksum() {
+    // The example of RFC 1071.
+    let data = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+    assert_eq!(checksum(0, &data), 0x220d);
+    let mut with_sum = data.to_vec();
+    with_sum.extend_from_slice(&0x220d_u16.to_be_bytes());
+    assert_eq!(checksum(0, &with_sum), 0);
+    // An odd byte is padded with a zero.
+    assert_eq!(checksum(0, &[0x12]), !0x1200);
+}
+
+#[test]
+fn ipv4_packet_parse() {
+    let mut link = link();
+    link.send_ip(PEER_MAC, (OUR_IP, PEER_IP), PROTOCOL_UDP, 64, b"payload").unwrap();
+    let frame = link.driver().sent.pop_front().unwrap();
+    let packet = &frame[14..];
+
+    let parsed = Ipv4Packet::parse(packet).unwrap();
+    assert_eq!((parsed.source, parsed.destination), (OUR_IP, PEER_IP));
+    assert_eq!(parsed.protocol, PROTOCOL_UDP);
+    assert_eq!(parsed.payload, b"payload");
+    // Ethernet padding after the packet isn't part of the payload.
+    let mut padded = packet.to_vec();
+    padded.extend_from_slice(&[0; 8]);
+    assert_eq!(Ipv4Packet::parse(&padded).unwrap().payload, b"payload");
+
+    assert!(Ipv4Packet::parse(&[]).is_none());
+    assert!(Ipv4Packet::parse(&packet[..19]).is_none());
+    // A header checksum that doesn't add up.
+    let mut corrupt = packet.to_vec();
+    corrupt[8] ^= 1;
+    assert!(Ipv4Packet::parse(&corrupt).is_none());
+    // IPv6, a total length past the end, and a fragment, with the header
+    // checksum fixed up.
+    let mutate = |at: usize, byte: u8| {
+        let mut packet = packet.to_vec();
+        packet[at] = byte;
+        packet[10..12].fill(0);
+        let sum = checksum(0, &packet[..20]);
+        packet[10..12].copy_from_slice(&sum.to_be_bytes());
+        packet
+    };
+    assert!(Ipv4Packet::parse(&mutate(0, 0x65)).is_none());
+    assert!(Ipv4Packet::parse(&mutate(3, 0xff)).is_none());
+    assert!(Ipv4Packet::parse(&mutate(7, 1)).is_none());
+}
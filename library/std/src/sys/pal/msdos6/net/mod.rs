@@ -0,0 +1,72 @@
+//! IPv4 networking over a packet driver, with a TCP/IP stack of our own.
+//!
+//! DOS has no sockets. Instead, an Ethernet card comes with a packet driver
+//! that sends and receives raw frames, and programs bring their own TCP/IP.
+//! The interface comes up on first use, with the driver at the interrupt in
+//! the PACKETINT variable or else the first one found in 60h–80h, and its
+//! addresses from these variables:
+//!
+//! * IPADDR: the address of this machine, or `DHCP` (the default) to ask a
+//!   DHCP server for everything below.
+//! * NETMASK: the mask of the local network, 255.255.255.0 by default.
+//! * GATEWAY: the router to addresses off the local network.
+//! * NAMESERVER: the DNS server host names are looked up with.
+//!
+//! Not supported: IPv6, multicast, IP fragments, out-of-order TCP segments
+//! (they are dropped and sent again by the peer), and renewing DHCP leases.
+
+mod dhcp;
+mod dns;
+// Tests run the stack on `loopback` instead.
+#[cfg_attr(test, allow(dead_code))]
+mod driver;
+mod iface;
+#[cfg(test)]
+mod loopback;
+mod tcp;
+
+mod tcpstream;
+pub use tcpstream::TcpStream;
+
+mod tcplistener;
+pub use tcplistener::TcpListener;
+
+mod udp;
+pub use dns::LookupHost;
+pub use iface::shutdown;
+pub use udp::UdpSocket;
+
+#[allow(nonstandard_style)]
+pub mod netc {
+    pub const AF_INET: u8 = 0;
+    pub const AF_INET6: u8 = 1;
+    pub type sa_family_t = u8;
+
+    #[derive(Copy, Clone)]
+    pub struct in_addr {
+        pub s_addr: u32,
+    }
+
+    #[derive(Copy, Clone)]
+    pub struct sockaddr_in {
+        #[allow(dead_code)]
+        pub sin_family: sa_family_t,
+        pub sin_port: u16,
+        pub sin_addr: in_addr,
+    }
+
+    #[derive(Copy, Clone)]
+    pub struct in6_addr {
+        pub s6_addr: [u8; 16],
+    }
+
+    #[derive(Copy, Clone)]
+    pub struct sockaddr_in6 {
+        #[allow(dead_code)]
+        pub sin6_family: sa_family_t,
+        pub sin6_port: u16,
+        pub sin6_addr: in6_addr,
+        pub sin6_flowinfo: u32,
+        pub sin6_scope_id: u32,
+    }
+}
@@ -0,0 +1,727 @@
+//! TCP, after RFC 793, cut down to what a DOS program needs.
+//!
+//! Segments are sent as soon as there is data and window for them, and every
+//! segment that carries data or a FIN is acknowledged right away. Segments
+//! that arrive out of order are dropped and acknowledged, so the sender goes
+//! back to the first byte we are missing. Unacknowledged data is sent again
+//! from `snd_una` on when the retransmission timer runs out, which doubles
+//! every time up to `MAX_RTO`.
+
+use super::driver::MacAddr;
+use super::iface::{self, Handle, Ipv4Packet, Link, Options, PROTOCOL_TCP};
+use crate::collections::VecDeque;
+use crate::io;
+use crate::net::SocketAddrV4;
+use crate::sys::time::Instant;
+use crate::time::Duration;
+
+#[cfg(test)]
+mod tests;
+
+const FIN: u8 = 0x01;
+const SYN: u8 = 0x02;
+const RST: u8 = 0x04;
+const PSH: u8 = 0x08;
+const ACK: u8 = 0x10;
+
+const HEADER: usize = 20;
+const OPTION_END: u8 = 0;
+const OPTION_NOP: u8 = 1;
+const OPTION_MSS: u8 = 2;
+
+/// The segment size we take, which fills an Ethernet frame.
+const OUR_MSS: u16 = (iface::MAX_IP_PAYLOAD - HEADER) as u16;
+/// The segment size to send if the peer doesn't say.
+const DEFAULT_MSS: u16 = 536;
+
+const SEND_BUFFER: usize = 16 * 1024;
+const RECEIVE_BUFFER: usize = 16 * 1024;
+
+const INITIAL_RTO: Duration = Duration::from_secs(1);
+const MAX_RTO: Duration = Duration::from_secs(60);
+/// Retransmissions of the same segment before the connection is given up.
+const MAX_RETRIES: u32 = 8;
+/// How long a closed connection stays in TIME-WAIT. Far shorter than the
+/// 2 MSL of RFC 793, which would keep sockets around for minutes.
+const TIME_WAIT: Duration = Duration::from_secs(10);
+
+/// Connections a listener holds before they are accepted, including those
+/// still being set up.
+pub const BACKLOG: usize = 8;
+
+/// Whether `a` comes before `b`, in sequence number space.
+fn before(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+fn before_or_at(a: u32, b: u32) -> bool {
+    !before(b, a)
+}
+
+/// A received segment.
+pub struct Segment<'a> {
+    pub source_port: u16,
+    pub destination_port: u16,
+    seq: u32,
+    ack: u32,
+    flags: u8,
+    window: u16,
+    mss: Option<u16>,
+    payload: &'a [u8],
+}
+
+impl<'a> Segment<'a> {
+    pub fn parse(packet: &Ipv4Packet<'a>) -> Option<Segment<'a>> {
+        let data = packet.payload;
+        if data.len() < HEADER || iface::checksum(packet.pseudo_header_sum(), data) != 0 {
+            return None;
+        }
+        let header = usize::from(data[12] >> 4) * 4;
+        if header < HEADER || header > data.len() {
+            return None;
+        }
+
+        let mut mss = None;
+        let mut options = &data[HEADER..header];
+        while let [kind, rest @ ..] = options {
+            match *kind {
+                OPTION_END => break,
+                OPTION_NOP => options = rest,
+                _ => {
+                    let [len, ..] = *rest else { break };
+                    let len = usize::from(len);
+                    if len < 2 || len > options.len() {
+                        break;
+                    }
+                    if *kind == OPTION_MSS && len == 4 {
+                        mss = Some(u16::from_be_bytes([options[2], options[3]]));
+                    }
+                    options = &options[len..];
+                }
+            }
+        }
+
+        let word = |at: usize| u32::from_be_bytes(data[at..at + 4].try_into().unwrap());
+        Some(Segment {
+            source_port: u16::from_be_bytes([data[0], data[1]]),
+            destination_port: u16::from_be_bytes([data[2], data[3]]),
+            seq: word(4),
+            ack: word(8),
+            flags: data[13],
+            window: u16::from_be_bytes([data[14], data[15]]),
+            mss,
+            payload: &data[header..],
+        })
+    }
+
+    /// A SYN that opens a connection.
+    pub fn is_connection_request(&self) -> bool {
+        self.flags & (SYN | ACK | RST) == SYN
+    }
+
+    /// How much sequence space the segment takes.
+    fn len(&self) -> u32 {
+        self.payload.len() as u32
+            + u32::from(self.flags & SYN != 0)
+            + u32::from(self.flags & FIN != 0)
+    }
+}
+
+/// A segment to send.
+struct Outgoing<'a> {
+    seq: u32,
+    ack: u32,
+    flags: u8,
+    window: u16,
+    mss: Option<u16>,
+    payload: &'a [u8],
+}
+
+fn send(
+    link: &mut Link,
+    mac: MacAddr,
+    (local, remote): (SocketAddrV4, SocketAddrV4),
+    ttl: u8,
+    segment: Outgoing<'_>,
+) -> io::Result<()> {
+    let header = if segment.mss.is_some() { HEADER + 4 } else { HEADER };
+    let mut data = Vec::with_capacity(header + segment.payload.len());
+    data.extend_from_slice(&local.port().to_be_bytes());
+    data.extend_from_slice(&remote.port().to_be_bytes());
+    data.extend_from_slice(&segment.seq.to_be_bytes());
+    data.extend_from_slice(&segment.ack.to_be_bytes());
+    data.extend_from_slice(&[((header / 4) as u8) << 4, segment.flags]);
+    data.extend_from_slice(&segment.window.to_be_bytes());
+    // The checksum, then the urgent pointer.
+    data.extend_from_slice(&[0; 4]);
+    if let Some(mss) = segment.mss {
+        data.extend_from_slice(&[OPTION_MSS, 4]);
+        data.extend_from_slice(&mss.to_be_bytes());
+    }
+    data.extend_from_slice(segment.payload);
+
+    let addresses = (*local.ip(), *remote.ip());
+    let sum = iface::pseudo_header_sum(addresses, PROTOCOL_TCP, data.len());
+    let sum = iface::checksum(sum, &data);
+    data[16..18].copy_from_slice(&sum.to_be_bytes());
+    link.send_ip(mac, addresses, PROTOCOL_TCP, ttl, &data)
+}
+
+/// Answers a segment that belongs to no connection, as RFC 793 has it.
+pub fn reset(
+    link: &mut Link,
+    mac: MacAddr,
+    addresses: (SocketAddrV4, SocketAddrV4),
+    segment: &Segment<'_>,
+) {
+    if segment.flags & RST != 0 {
+        return;
+    }
+    let reply = if segment.flags & ACK != 0 {
+        Outgoing { seq: segment.ack, ack: 0, flags: RST, window: 0, mss: None, payload: &[] }
+    } else {
+        let ack = segment.seq.wrapping_add(segment.len());
+        Outgoing { seq: 0, ack, flags: RST | ACK, window: 0, mss: None, payload: &[] }
+    };
+    let _ = send(link, mac, addresses, iface::DEFAULT_TTL, reply);
+}
+
+/// Picks an initial sequence number. RFC 6528 asks for it not to be guessable.
+fn initial_sequence_number() -> u32 {
+    let mut bytes = [0; 4];
+    crate::sys::random::fill_bytes(&mut bytes);
+    u32::from_ne_bytes(bytes)
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum State {
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait1,
+    FinWait2,
+    CloseWait,
+    Closing,
+    LastAck,
+    TimeWait,
+    Closed,
+}
+
+/// Why a connection ended early.
+#[derive(Copy, Clone, Debug)]
+pub enum Failure {
+    Refused,
+    Reset,
+    TimedOut,
+}
+
+impl Failure {
+    pub fn error(self) -> io::Error {
+        match self {
+            Failure::Refused => {
+                io::const_error!(io::ErrorKind::ConnectionRefused, "connection refused")
+            }
+            Failure::Reset => io::const_error!(io::ErrorKind::ConnectionReset, "connection reset"),
+            Failure::TimedOut => {
+                io::const_error!(io::ErrorKind::TimedOut, "connection timed out")
+            }
+        }
+    }
+}
+
+/// A TCP connection.
+pub struct Tcp {
+    pub state: State,
+    pub local: SocketAddrV4,
+    pub remote: SocketAddrV4,
+    /// The link address to send to, of the peer or the gateway to it.
+    mac: MacAddr,
+    pub options: Options,
+    pub nodelay: bool,
+    /// The listener the connection came in on, until it is established.
+    pub listener: Option<Handle>,
+    /// Why the connection ended, which reads and writes keep reporting.
+    pub failure: Option<Failure>,
+    /// The same, until `take_error` reports it.
+    pub pending_error: Option<Failure>,
+
+    iss: u32,
+    snd_una: u32,
+    snd_nxt: u32,
+    /// The highest `snd_nxt` has been, which retransmission goes back from.
+    snd_max: u32,
+    snd_wnd: u32,
+    mss: usize,
+    /// The bytes from `snd_una` on, sent or not.
+    send_buffer: VecDeque<u8>,
+    /// Whether a FIN follows the data in `send_buffer`.
+    fin_queued: bool,
+    /// Whether that FIN has been sent since the last retransmission.
+    fin_sent: bool,
+
+    rcv_nxt: u32,
+    receive_buffer: VecDeque<u8>,
+    fin_received: bool,
+    read_shutdown: bool,
+    /// Whether what we received has to be acknowledged.
+    ack_due: bool,
+
+    rto: Duration,
+    retransmit_at: Option<Instant>,
+    retries: u32,
+    time_wait_until: Option<Instant>,
+}
+
+impl Tcp {
+    fn new(
+        state: State,
+        (local, remote): (SocketAddrV4, SocketAddrV4),
+        mac: MacAddr,
+        options: Options,
+    ) -> Tcp {
+        let iss = initial_sequence_number();
+        Tcp {
+            state,
+            local,
+            remote,
+            mac,
+            options,
+            nodelay: false,
+            listener: None,
+            failure: None,
+            pending_error: None,
+            iss,
+            snd_una: iss,
+            snd_nxt: iss,
+            snd_max: iss,
+            snd_wnd: 0,
+            mss: DEFAULT_MSS.into(),
+            send_buffer: VecDeque::new(),
+            fin_queued: false,
+            fin_sent: false,
+            rcv_nxt: 0,
+            receive_buffer: VecDeque::new(),
+            fin_received: false,
+            read_shutdown: false,
+            ack_due: false,
+            rto: INITIAL_RTO,
+            retransmit_at: None,
+            retries: 0,
+            time_wait_until: None,
+        }
+    }
+
+    /// A connection to `remote`, which `poll` opens with a SYN.
+    pub fn connect(local: SocketAddrV4, remote: SocketAddrV4, mac: MacAddr) -> Tcp {
+        Tcp::new(State::SynSent, (local, remote), mac, Options::new())
+    }
+
+    /// A connection for the SYN `segment` that came in on `listener`, which
+    /// `poll` answers with a SYN-ACK.
+    pub fn accept(
+        local: SocketAddrV4,
+        remote: SocketAddrV4,
+        mac: MacAddr,
+        segment: &Segment<'_>,
+        listener: Handle,
+        options: Options,
+    ) -> Tcp {
+        let mut tcp = Tcp::new(State::SynReceived, (local, remote), mac, options);
+        tcp.listener = Some(listener);
+        tcp.rcv_nxt = segment.seq.wrapping_add(1);
+        tcp.snd_wnd = segment.window.into();
+        tcp.set_mss(segment.mss);
+        tcp
+    }
+
+    fn set_mss(&mut self, mss: Option<u16>) {
+        self.mss = usize::from(mss.unwrap_or(DEFAULT_MSS).clamp(1, OUR_MSS));
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state == State::Closed
+    }
+
+    pub fn is_established(&self) -> bool {
+        !matches!(self.state, State::SynSent | State::SynReceived | State::Closed)
+    }
+
+    fn window(&self) -> u16 {
+        (RECEIVE_BUFFER - self.receive_buffer.len()).min(u16::MAX.into()) as u16
+    }
+
+    fn send(&self, link: &mut Link, seq: u32, flags: u8, payload: &[u8]) {
+        let mss = (flags & SYN != 0).then_some(OUR_MSS);
+        let segment =
+            Outgoing { seq, ack: self.rcv_nxt, flags, window: self.window(), mss, payload };
+        // A segment the driver failed to send is as good as lost on the way.
+        let _ = send(link, self.mac, (self.local, self.remote), self.options.ttl, segment);
+    }
+
+    fn fail(&mut self, failure: Failure) {
+        self.failure = Some(failure);
+        self.pending_error = Some(failure);
+        self.state = State::Closed;
+        self.send_buffer.clear();
+        self.retransmit_at = None;
+    }
+
+    /// Resets the connection.
+    pub fn abort(&mut self, link: &mut Link) {
+        self.reset(Failure::Reset, link);
+    }
+
+    fn reset(&mut self, failure: Failure, link: &mut Link) {
+        if !matches!(self.state, State::SynSent | State::Closed | State::TimeWait) {
+            self.send(link, self.snd_nxt, RST | ACK, &[]);
+        }
+        self.fail(failure);
+    }
+
+    /// Sends what is due.
+    pub fn poll(&mut self, now: Instant, link: &mut Link) {
+        if self.time_wait_until.is_some_and(|until| now >= until) {
+            self.state = State::Closed;
+        }
+
+        if self.retransmit_at.is_some_and(|at| now >= at) {
+            if self.snd_una == self.snd_max {
+                self.retransmit_at = None;
+            } else if self.retries == MAX_RETRIES {
+                self.reset(Failure::TimedOut, link);
+                return;
+            } else {
+                // Go back to the first byte that wasn't acknowledged.
+                self.retries += 1;
+                self.rto = (self.rto * 2).min(MAX_RTO);
+                self.snd_nxt = self.snd_una;
+                self.fin_sent = false;
+                self.retransmit_at = now.checked_add_duration(&self.rto);
+            }
+        }
+
+        self.output(now, link);
+    }
+
+    /// Records that everything before `snd_nxt` went out, and starts the
+    /// retransmission timer if it isn't running.
+    fn sent(&mut self, now: Instant) {
+        if before(self.snd_max, self.snd_nxt) {
+            self.snd_max = self.snd_nxt;
+        }
+        self.ack_due = false;
+        if self.retransmit_at.is_none() {
+            self.retransmit_at = now.checked_add_duration(&self.rto);
+        }
+    }
+
+    fn output(&mut self, now: Instant, link: &mut Link) {
+        match self.state {
+            State::Closed | State::TimeWait => {}
+            State::SynSent | State::SynReceived => {
+                if self.snd_nxt == self.iss {
+                    let flags = if self.state == State::SynSent { SYN } else { SYN | ACK };
+                    self.send(link, self.iss, flags, &[]);
+                    self.snd_nxt = self.iss.wrapping_add(1);
+                    self.sent(now);
+                }
+            }
+            _ => {
+                while !self.fin_sent {
+                    let sent = self.snd_nxt.wrapping_sub(self.snd_una) as usize;
+                    let unsent = self.send_buffer.len() - sent;
+                    // With nothing in flight, an empty window is probed a byte at a time.
+                    let window = match self.snd_wnd {
+                        0 if sent == 0 => 1,
+                        window => window as usize,
+                    };
+                    let len = unsent.min(window.saturating_sub(sent)).min(self.mss);
+                    if len > 0 {
+                        let payload: Vec<u8> =
+                            self.send_buffer.range(sent..sent + len).copied().collect();
+                        self.send(link, self.snd_nxt, ACK | PSH, &payload);
+                        self.snd_nxt = self.snd_nxt.wrapping_add(len as u32);
+                        self.sent(now);
+                    } else if unsent == 0 && self.fin_queued {
+                        self.send(link, self.snd_nxt, FIN | ACK, &[]);
+                        self.snd_nxt = self.snd_nxt.wrapping_add(1);
+                        self.fin_sent = true;
+                        self.sent(now);
+                        self.state = match self.state {
+                            State::Established => State::FinWait1,
+                            State::CloseWait => State::LastAck,
+                            state => state,
+                        };
+                    } else {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if self.ack_due {
+            self.send(link, self.snd_nxt, ACK, &[]);
+            self.ack_due = false;
+        }
+    }
+
+    /// Whether `seq` is in the receive window, which is never empty here.
+    fn in_window(&self, seq: u32, window: u32) -> bool {
+        before_or_at(self.rcv_nxt, seq) && before(seq, self.rcv_nxt.wrapping_add(window.max(1)))
+    }
+
+    /// The acceptability test of RFC 793, section 3.3.
+    fn is_acceptable(&self, segment: &Segment<'_>) -> bool {
+        let window = u32::from(self.window());
+        match segment.len() {
+            0 if window == 0 => segment.seq == self.rcv_nxt,
+            0 => self.in_window(segment.seq, window),
+            _ if window == 0 => false,
+            len => {
+                self.in_window(segment.seq, window)
+                    || self.in_window(segment.seq.wrapping_add(len - 1), window)
+            }
+        }
+    }
+
+    /// Handles a segment for this connection. Returns whether it just made
+    /// a connection from a listener established.
+    pub fn receive(&mut self, segment: &Segment<'_>, link: &mut Link) -> bool {
+        let now = Instant::now();
+        let flags = segment.flags;
+
+        if self.state == State::Closed {
+            return false;
+        }
+        if self.state == State::SynSent {
+            let acceptable = segment.ack == self.iss.wrapping_add(1);
+            if flags & ACK != 0 && !acceptable {
+                reset(link, self.mac, (self.local, self.remote), segment);
+            } else if flags & RST != 0 {
+                if flags & ACK != 0 {
+                    self.fail(Failure::Refused);
+                }
+            } else if flags & SYN != 0 && flags & ACK != 0 {
+                self.rcv_nxt = segment.seq.wrapping_add(1);
+                self.snd_una = segment.ack;
+                self.snd_wnd = segment.window.into();
+                self.set_mss(segment.mss);
+                self.state = State::Established;
+                self.retransmit_at = None;
+                self.retries = 0;
+                self.ack_due = true;
+                self.output(now, link);
+            }
+            return false;
+        }
+
+        if !self.is_acceptable(segment) {
+            if flags & RST == 0 {
+                self.ack_due = true;
+                self.output(now, link);
+            }
+            return false;
+        }
+        if flags & RST != 0 {
+            self.fail(Failure::Reset);
+            return false;
+        }
+        if flags & SYN != 0 || flags & ACK == 0 {
+            // A SYN in the window is answered with an ACK, as RFC 5961 suggests.
+            if flags & SYN != 0 {
+                self.ack_due = true;
+                self.output(now, link);
+            }
+            return false;
+        }
+
+        let mut established = false;
+        if self.state == State::SynReceived {
+            if segment.ack != self.iss.wrapping_add(1) {
+                reset(link, self.mac, (self.local, self.remote), segment);
+                return false;
+            }
+            self.snd_una = segment.ack;
+            self.state = State::Established;
+            self.retransmit_at = None;
+            self.retries = 0;
+            established = true;
+        } else if before(self.snd_una, segment.ack) && before_or_at(segment.ack, self.snd_max) {
+            let acked = segment.ack.wrapping_sub(self.snd_una) as usize;
+            // The FIN takes the sequence number after the data.
+            let fin_acked = self.fin_queued && acked > self.send_buffer.len();
+            self.send_buffer.drain(..acked.min(self.send_buffer.len()));
+            self.snd_una = segment.ack;
+            if before(self.snd_nxt, segment.ack) {
+                // Acknowledged while we were going back to retransmit.
+                self.snd_nxt = segment.ack;
+            }
+            if fin_acked {
+                self.fin_sent = true;
+                match self.state {
+                    State::FinWait1 => self.state = State::FinWait2,
+                    State::Closing => self.enter_time_wait(now),
+                    State::LastAck => self.state = State::Closed,
+                    _ => {}
+                }
+            }
+            self.retries = 0;
+            self.rto = INITIAL_RTO;
+            self.retransmit_at = None;
+            if self.snd_una != self.snd_max {
+                self.retransmit_at = now.checked_add_duration(&self.rto);
+            }
+        } else if before(self.snd_max, segment.ack) {
+            // An acknowledgement for something we haven't sent.
+            self.ack_due = true;
+            self.output(now, link);
+            return false;
+        }
+        if before_or_at(self.snd_una, segment.ack) {
+            self.snd_wnd = segment.window.into();
+        }
+
+        // Data we already have is cut off. Data past a gap is dropped, and the
+        // acknowledgement tells the sender where the gap starts.
+        let mut payload = segment.payload;
+        let mut in_order = true;
+        if before(segment.seq, self.rcv_nxt) {
+            let seen = (self.rcv_nxt.wrapping_sub(segment.seq) as usize).min(payload.len());
+            payload = &payload[seen..];
+        } else if segment.seq != self.rcv_nxt {
+            payload = &[];
+            in_order = false;
+        }
+        if !segment.payload.is_empty() || flags & FIN != 0 {
+            self.ack_due = true;
+        }
+        if matches!(self.state, State::Established | State::FinWait1 | State::FinWait2) {
+            let take = payload.len().min(usize::from(self.window()));
+            if !self.read_shutdown {
+                self.receive_buffer.extend(&payload[..take]);
+            }
+            self.rcv_nxt = self.rcv_nxt.wrapping_add(take as u32);
+            payload = &payload[take..];
+        }
+
+        let fin_seq = segment.seq.wrapping_add(segment.payload.len() as u32);
+        if flags & FIN != 0 && in_order && payload.is_empty() && fin_seq == self.rcv_nxt {
+            self.rcv_nxt = self.rcv_nxt.wrapping_add(1);
+            self.fin_received = true;
+            match self.state {
+                State::Established => self.state = State::CloseWait,
+                State::FinWait1 => self.state = State::Closing,
+                State::FinWait2 => self.enter_time_wait(now),
+                _ => {}
+            }
+        }
+        if self.state == State::TimeWait {
+            // The peer sent its FIN again; stay until it stops.
+            self.enter_time_wait(now);
+        }
+
+        self.output(now, link);
+        established
+    }
+
+    fn enter_time_wait(&mut self, now: Instant) {
+        self.state = State::TimeWait;
+        self.retransmit_at = None;
+        self.time_wait_until = now.checked_add_duration(&TIME_WAIT);
+    }
+
+    /// Reads or peeks at what was received. Returns `None` if there is
+    /// nothing yet.
+    pub fn read(
+        &mut self,
+        buf: &mut [u8],
+        peek: bool,
+        link: &mut Link,
+    ) -> Option<io::Result<usize>> {
+        if !self.receive_buffer.is_empty() {
+            let len = buf.len().min(self.receive_buffer.len());
+            for (dst, src) in buf.iter_mut().zip(self.receive_buffer.range(..len)) {
+                *dst = *src;
+            }
+            if !peek {
+                let was_small = usize::from(self.window()) < self.mss;
+                self.receive_buffer.drain(..len);
+                // Tell the peer once there is room for a full segment again.
+                if was_small && usize::from(self.window()) >= self.mss && self.is_established() {
+                    self.ack_due = true;
+                    self.output(Instant::now(), link);
+                }
+            }
+            return Some(Ok(len));
+        }
+        if self.fin_received || self.read_shutdown {
+            return Some(Ok(0));
+        }
+        match (self.failure, self.state) {
+            (Some(failure), _) => Some(Err(failure.error())),
+            (None, State::Closed) => Some(Ok(0)),
+            _ => None,
+        }
+    }
+
+    /// Queues as much of `buf` as fits. Returns `None` if nothing does yet.
+    pub fn write(&mut self, buf: &[u8], link: &mut Link) -> Option<io::Result<usize>> {
+        if let Some(failure) = self.failure {
+            return Some(Err(failure.error()));
+        }
+        if self.fin_queued || !matches!(self.state, State::Established | State::CloseWait) {
+            return Some(Err(io::const_error!(
+                io::ErrorKind::BrokenPipe,
+                "the connection is closed for writing",
+            )));
+        }
+        let len = buf.len().min(SEND_BUFFER - self.send_buffer.len());
+        if len == 0 && !buf.is_empty() {
+            return None;
+        }
+        self.send_buffer.extend(&buf[..len]);
+        self.output(Instant::now(), link);
+        Some(Ok(len))
+    }
+
+    pub fn shutdown_read(&mut self) {
+        self.read_shutdown = true;
+        self.receive_buffer.clear();
+    }
+
+    /// Sends a FIN once the data queued so far is out.
+    pub fn shutdown_write(&mut self, link: &mut Link) {
+        if matches!(self.state, State::SynReceived | State::Established | State::CloseWait) {
+            self.fin_queued = true;
+            self.output(Instant::now(), link);
+        }
+    }
+
+    /// Closes the connection once the application is done with it.
+    pub fn close(&mut self, link: &mut Link) {
+        self.shutdown_read();
+        match self.state {
+            State::SynSent => self.state = State::Closed,
+            _ => self.shutdown_write(link),
+        }
+    }
+}
+
+/// A listening socket.
+pub struct Listener {
+    pub local: SocketAddrV4,
+    pub options: Options,
+    /// Established connections waiting for `accept`.
+    pub backlog: VecDeque<Handle>,
+}
+
+impl Listener {
+    pub fn new(local: SocketAddrV4) -> Listener {
+        Listener { local, options: Options::new(), backlog: VecDeque::new() }
+    }
+
+    pub fn accepts(&self, local: SocketAddrV4) -> bool {
+        self.local.port() == local.port()
+            && (self.local.ip().is_unspecified() || self.local.ip() == local.ip())
+    }
+}
@@ -0,0 +1,247 @@
+use super::super::iface::Config;
+use super::super::loopback::Driver;
+use super::*;
+use crate::net::Ipv4Addr;
+
+const OUR_MAC: MacAddr = [0x02, 0, 0, 0, 0, 1];
+const PEER_MAC: MacAddr = [0x02, 0, 0, 0, 0, 2];
+const OUR_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+const PEER_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+const LOCAL: SocketAddrV4 = SocketAddrV4::new(OUR_IP, 49152);
+const REMOTE: SocketAddrV4 = SocketAddrV4::new(PEER_IP, 80);
+const PEER_ISS: u32 = 1000;
+
+fn link() -> Link {
+    let config = Config {
+        address: OUR_IP,
+        netmask: Ipv4Addr::new(255, 255, 255, 0),
+        gateway: None,
+        nameserver: None,
+    };
+    Link::new(Driver::new(OUR_MAC), config)
+}
+
+fn after(duration: Duration) -> Instant {
+    Instant::now().checked_add_duration(&duration).unwrap()
+}
+
+/// The IPv4 packet in a frame we sent. Checking it is left to the tests of
+/// `Ipv4Packet::parse`.
+fn ipv4(packet: &[u8]) -> Ipv4Packet<'_> {
+    let header = usize::from(packet[0] & 0xf) * 4;
+    let len = usize::from(u16::from_be_bytes([packet[2], packet[3]]));
+    let address =
+        |at: usize| Ipv4Addr::new(packet[at], packet[at + 1], packet[at + 2], packet[at + 3]);
+    Ipv4Packet {
+        source: address(12),
+        destination: address(16),
+        protocol: packet[9],
+        payload: &packet[header..len],
+    }
+}
+
+/// The TCP segments sent since the last call, as (seq, ack, flags, payload).
+fn sent(link: &mut Link) -> Vec<(u32, u32, u8, Vec<u8>)> {
+    link.driver()
+        .sent
+        .drain(..)
+        .map(|frame| {
+            assert_eq!(frame[..6], PEER_MAC);
+            let packet = ipv4(&frame[14..]);
+            assert_eq!((packet.source, packet.destination), (OUR_IP, PEER_IP));
+            assert_eq!(packet.protocol, PROTOCOL_TCP);
+            let segment = Segment::parse(&packet).expect("not a TCP segment");
+            (segment.seq, segment.ack, segment.flags, segment.payload.to_vec())
+        })
+        .collect()
+}
+
+/// Hands `tcp` a segment from the peer.
+fn receive(tcp: &mut Tcp, link: &mut Link, seq: u32, ack: u32, flags: u8, payload: &[u8]) {
+    let mut data = Vec::new();
+    data.extend_from_slice(&REMOTE.port().to_be_bytes());
+    data.extend_from_slice(&LOCAL.port().to_be_bytes());
+    data.extend_from_slice(&seq.to_be_bytes());
+    data.extend_from_slice(&ack.to_be_bytes());
+    data.extend_from_slice(&[0x50, flags, 0x20, 0x00, 0, 0, 0, 0]);
+    data.extend_from_slice(payload);
+    let sum = iface::pseudo_header_sum((PEER_IP, OUR_IP), PROTOCOL_TCP, data.len());
+    let sum = iface::checksum(sum, &data);
+    data[16..18].copy_from_slice(&sum.to_be_bytes());
+
+    let packet =
+        Ipv4Packet { source: PEER_IP, destination: OUR_IP, protocol: PROTOCOL_TCP, payload: &data };
+    let segment = Segment::parse(&packet).expect("bad test segment");
+    tcp.receive(&segment, link);
+}
+
+/// Opens a connection to the peer. Returns it with its initial sequence number.
+fn establish(link: &mut Link) -> (Tcp, u32) {
+    let mut tcp = Tcp::connect(LOCAL, REMOTE, PEER_MAC);
+    tcp.poll(Instant::now(), link);
+    let [(iss, _, flags, _)] = sent(link)[..] else { panic!("expected a SYN") };
+    assert_eq!(flags, SYN);
+    receive(&mut tcp, link, PEER_ISS, iss.wrapping_add(1), SYN | ACK, &[]);
+    (tcp, iss)
+}
+
+#[test]
+fn segment_parse() {
+    let mut link = link();
+    let mut tcp = Tcp::connect(LOCAL, REMOTE, PEER_MAC);
+    tcp.poll(Instant::now(), &mut link);
+    let frame = link.driver().sent.pop_front().unwrap();
+    let packet = ipv4(&frame[14..]);
+
+    let segment = Segment::parse(&packet).unwrap();
+    assert_eq!((segment.source_port, segment.destination_port), (LOCAL.port(), REMOTE.port()));
+    assert_eq!(segment.flags, SYN);
+    assert!(segment.is_connection_request());
+    assert!(segment.mss.is_some());
+    assert!(segment.payload.is_empty());
+
+    // A flipped bit fails the checksum.
+    let mut data = packet.payload.to_vec();
+    data[4] ^= 1;
+    let corrupt = Ipv4Packet { payload: &data, ..packet };
+    assert!(Segment::parse(&corrupt).is_none());
+    // So does a segment from somewhere else, through the pseudo-header.
+    let elsewhere = Ipv4Packet { source: Ipv4Addr::new(10, 0, 0, 3), ..packet };
+    assert!(Segment::parse(&elsewhere).is_none());
+}
+
+#[test]
+fn handshake() {
+    let mut link = link();
+    let (tcp, iss) = establish(&mut link);
+    assert_eq!(tcp.state, State::Established);
+    assert!(tcp.is_established());
+    assert_eq!(sent(&mut link), [(iss.wrapping_add(1), PEER_ISS + 1, ACK, vec![])]);
+}
+
+#[test]
+fn handshake_refused() {
+    let mut link = link();
+    let mut tcp = Tcp::connect(LOCAL, REMOTE, PEER_MAC);
+    tcp.poll(Instant::now(), &mut link);
+    let [(iss, ..)] = sent(&mut link)[..] else { panic!("expected a SYN") };
+
+    // A RST that doesn't acknowledge our SYN is ignored.
+    receive(&mut tcp, &mut link, 0, iss.wrapping_add(7), RST | ACK, &[]);
+    assert_eq!(tcp.state, State::SynSent);
+    receive(&mut tcp, &mut link, 0, iss.wrapping_add(1), RST | ACK, &[]);
+    assert_eq!(tcp.state, State::Closed);
+    let mut buf = [0; 8];
+    let err = tcp.read(&mut buf, false, &mut link).unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+}
+
+#[test]
+fn data_and_retransmission() {
+    let mut link = link();
+    let (mut tcp, iss) = establish(&mut link);
+    sent(&mut link);
+
+    assert_eq!(tcp.write(b"hello", &mut link).unwrap().unwrap(), 5);
+    assert_eq!(
+        sent(&mut link),
+        [(iss.wrapping_add(1), PEER_ISS + 1, ACK | PSH, b"hello".to_vec())]
+    );
+
+    // Nothing is sent again before the timer runs out, and then all of it is.
+    tcp.poll(Instant::now(), &mut link);
+    assert!(sent(&mut link).is_empty());
+    tcp.poll(after(INITIAL_RTO * 2), &mut link);
+    assert_eq!(
+        sent(&mut link),
+        [(iss.wrapping_add(1), PEER_ISS + 1, ACK | PSH, b"hello".to_vec())]
+    );
+
+    // Once acknowledged, it is not sent again.
+    receive(&mut tcp, &mut link, PEER_ISS + 1, iss.wrapping_add(6), ACK, b"world");
+    assert_eq!(sent(&mut link), [(iss.wrapping_add(6), PEER_ISS + 6, ACK, vec![])]);
+    tcp.poll(after(Duration::from_secs(600)), &mut link);
+    assert!(sent(&mut link).is_empty());
+
+    let mut buf = [0; 8];
+    assert_eq!(tcp.read(&mut buf, false, &mut link).unwrap().unwrap(), 5);
+    assert_eq!(&buf[..5], b"world");
+
+    // A segment past a gap is dropped, and the ACK says where the gap starts.
+    receive(&mut tcp, &mut link, PEER_ISS + 10, iss.wrapping_add(6), ACK, b"later");
+    assert_eq!(sent(&mut link), [(iss.wrapping_add(6), PEER_ISS + 6, ACK, vec![])]);
+    assert!(tcp.read(&mut buf, false, &mut link).is_none());
+}
+
+#[test]
+fn retransmission_gives_up() {
+    let mut link = link();
+    let (mut tcp, _) = establish(&mut link);
+    tcp.write(b"hello", &mut link).unwrap().unwrap();
+    sent(&mut link);
+
+    // Every poll is past the timer, which never exceeds a minute.
+    let mut now = Instant::now();
+    for _ in 0..MAX_RETRIES {
+        now = now.checked_add_duration(&Duration::from_secs(120)).unwrap();
+        tcp.poll(now, &mut link);
+        assert_eq!(sent(&mut link).len(), 1);
+    }
+    now = now.checked_add_duration(&Duration::from_secs(120)).unwrap();
+    tcp.poll(now, &mut link);
+    let [(_, _, flags, _)] = sent(&mut link)[..] else { panic!("expected a RST") };
+    assert_eq!(flags, RST | ACK);
+    assert_eq!(tcp.state, State::Closed);
+    // `take_error` reports the failure once, and writes every time.
+    assert!(matches!(tcp.pending_error.take(), Some(Failure::TimedOut)));
+    assert!(tcp.pending_error.is_none());
+    let err = tcp.write(b"more", &mut link).unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn active_close_and_time_wait() {
+    let mut link = link();
+    let (mut tcp, iss) = establish(&mut link);
+    sent(&mut link);
+
+    tcp.close(&mut link);
+    assert_eq!(sent(&mut link), [(iss.wrapping_add(1), PEER_ISS + 1, FIN | ACK, vec![])]);
+    assert_eq!(tcp.state, State::FinWait1);
+    receive(&mut tcp, &mut link, PEER_ISS + 1, iss.wrapping_add(2), ACK, &[]);
+    assert_eq!(tcp.state, State::FinWait2);
+
+    receive(&mut tcp, &mut link, PEER_ISS + 1, iss.wrapping_add(2), FIN | ACK, &[]);
+    assert_eq!(tcp.state, State::TimeWait);
+    assert_eq!(sent(&mut link), [(iss.wrapping_add(2), PEER_ISS + 2, ACK, vec![])]);
+
+    // The peer's FIN sent again is acknowledged again.
+    receive(&mut tcp, &mut link, PEER_ISS + 1, iss.wrapping_add(2), FIN | ACK, &[]);
+    assert_eq!(sent(&mut link), [(iss.wrapping_add(2), PEER_ISS + 2, ACK, vec![])]);
+
+    tcp.poll(after(TIME_WAIT / 2), &mut link);
+    assert_eq!(tcp.state, State::TimeWait);
+    tcp.poll(after(TIME_WAIT * 2), &mut link);
+    assert!(tcp.is_closed());
+    assert!(sent(&mut link).is_empty());
+}
+
+#[test]
+fn passive_close() {
+    let mut link = link();
+    let (mut tcp, iss) = establish(&mut link);
+    sent(&mut link);
+
+    receive(&mut tcp, &mut link, PEER_ISS + 1, iss.wrapping_add(1), FIN | ACK, b"bye");
+    assert_eq!(tcp.state, State::CloseWait);
+    assert_eq!(sent(&mut link), [(iss.wrapping_add(1), PEER_ISS + 5, ACK, vec![])]);
+    let mut buf = [0; 8];
+    assert_eq!(tcp.read(&mut buf, false, &mut link).unwrap().unwrap(), 3);
+    assert_eq!(tcp.read(&mut buf, false, &mut link).unwrap().unwrap(), 0);
+
+    tcp.close(&mut link);
+    assert_eq!(tcp.state, State::LastAck);
+    assert_eq!(sent(&mut link), [(iss.wrapping_add(1), PEER_ISS + 5, FIN | ACK, vec![])]);
+    receive(&mut tcp, &mut link, PEER_ISS + 5, iss.wrapping_add(2), ACK, &[]);
+    assert!(tcp.is_closed());
+}
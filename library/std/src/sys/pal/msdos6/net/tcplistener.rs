@@ -0,0 +1,88 @@
+use super::iface::{self, Handle, PROTOCOL_TCP, Socket, wait, with_interface};
+use super::tcp::Listener;
+use super::tcpstream::TcpStream;
+use super::udp::check_ttl;
+use crate::net::SocketAddr;
+use crate::sys::unsupported;
+use crate::{fmt, io};
+
+pub struct TcpListener {
+    handle: Handle,
+}
+
+impl TcpListener {
+    pub fn bind(addr: io::Result<&SocketAddr>) -> io::Result<TcpListener> {
+        let addr = *addr?;
+        with_interface(|interface| {
+            let local = interface.bind(addr, PROTOCOL_TCP)?;
+            let handle = interface.insert(Socket::Listener(Listener::new(local)), 1);
+            Ok(TcpListener { handle })
+        })
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut Listener) -> R) -> io::Result<R> {
+        with_interface(|interface| Ok(f(interface.listener(self.handle))))
+    }
+
+    pub fn socket_addr(&self) -> io::Result<SocketAddr> {
+        self.with(|listener| SocketAddr::V4(listener.local))
+    }
+
+    pub fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        let nonblocking = self.with(|listener| listener.options.nonblocking)?;
+        wait(None, nonblocking, |interface| {
+            // The reference the backlog held goes to the stream.
+            let handle = interface.listener(self.handle).backlog.pop_front()?;
+            let remote = interface.tcp(handle).0.remote;
+            Some(Ok((TcpStream::from_handle(handle), SocketAddr::V4(remote))))
+        })
+    }
+
+    pub fn duplicate(&self) -> io::Result<TcpListener> {
+        with_interface(|interface| {
+            interface.retain(self.handle);
+            Ok(TcpListener { handle: self.handle })
+        })
+    }
+
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        let ttl = check_ttl(ttl)?;
+        self.with(|listener| listener.options.ttl = ttl)
+    }
+
+    pub fn ttl(&self) -> io::Result<u32> {
+        self.with(|listener| listener.options.ttl.into())
+    }
+
+    pub fn set_only_v6(&self, _: bool) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn only_v6(&self) -> io::Result<bool> {
+        Ok(false)
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        Ok(None)
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.with(|listener| listener.options.nonblocking = nonblocking)
+    }
+}
+
+impl Drop for TcpListener {
+    fn drop(&mut self) {
+        iface::release(self.handle);
+    }
+}
+
+impl fmt::Debug for TcpListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut res = f.debug_struct("TcpListener");
+        if let Ok(addr) = self.socket_addr() {
+            res.field("addr", &addr);
+        }
+        res.finish()
+    }
+}
@@ -0,0 +1,206 @@
+use super::iface::{
+    self, Handle, PROTOCOL_TCP, Socket, deadline, ipv4, resolve, wait, with_interface,
+};
+use super::tcp::{Failure, Tcp};
+use super::udp::{check_timeout, check_ttl};
+use crate::fmt;
+use crate::io::{self, BorrowedCursor, IoSlice, IoSliceMut};
+use crate::net::{Shutdown, SocketAddr, SocketAddrV4};
+use crate::sys::time::Instant;
+use crate::sys::unsupported;
+use crate::time::Duration;
+
+pub struct TcpStream {
+    handle: Handle,
+}
+
+impl TcpStream {
+    pub(super) fn from_handle(handle: Handle) -> TcpStream {
+        TcpStream { handle }
+    }
+
+    pub fn connect(addr: io::Result<&SocketAddr>) -> io::Result<TcpStream> {
+        TcpStream::connect_until(addr?, None)
+    }
+
+    pub fn connect_timeout(addr: &SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+        check_timeout(Some(timeout))?;
+        TcpStream::connect_until(addr, deadline(Some(timeout)))
+    }
+
+    fn connect_until(addr: &SocketAddr, deadline: Option<Instant>) -> io::Result<TcpStream> {
+        let remote = ipv4(*addr)?;
+        if remote.ip().is_unspecified() || remote.ip().is_broadcast() || remote.port() == 0 {
+            return Err(io::const_error!(
+                io::ErrorKind::InvalidInput,
+                "cannot connect to this address",
+            ));
+        }
+        let mac = resolve(*remote.ip(), deadline)?;
+        let stream = with_interface(|interface| {
+            let port = interface.ephemeral_port(PROTOCOL_TCP);
+            let local = SocketAddrV4::new(interface.link.source_for(*remote.ip()), port);
+            let handle = interface.insert(Socket::Tcp(Tcp::connect(local, remote, mac)), 1);
+            let (tcp, link) = interface.tcp(handle);
+            tcp.poll(Instant::now(), link);
+            Ok(TcpStream { handle })
+        })?;
+        wait(deadline, false, |interface| {
+            let tcp = interface.tcp(stream.handle).0;
+            if tcp.is_established() {
+                Some(Ok(()))
+            } else if tcp.is_closed() {
+                Some(Err(tcp.failure.unwrap_or(Failure::Refused).error()))
+            } else {
+                None
+            }
+        })?;
+        Ok(stream)
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut Tcp) -> R) -> io::Result<R> {
+        with_interface(|interface| Ok(f(interface.tcp(self.handle).0)))
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        check_timeout(timeout)?;
+        self.with(|tcp| tcp.options.read_timeout = timeout)
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        check_timeout(timeout)?;
+        self.with(|tcp| tcp.options.write_timeout = timeout)
+    }
+
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.with(|tcp| tcp.options.read_timeout)
+    }
+
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.with(|tcp| tcp.options.write_timeout)
+    }
+
+    fn receive(&self, buf: &mut [u8], peek: bool) -> io::Result<usize> {
+        let options = self.with(|tcp| tcp.options)?;
+        wait(deadline(options.read_timeout), options.nonblocking, |interface| {
+            let (tcp, link) = interface.tcp(self.handle);
+            tcp.read(buf, peek, link)
+        })
+    }
+
+    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.receive(buf, true)
+    }
+
+    pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.receive(buf, false)
+    }
+
+    pub fn read_buf(&self, cursor: BorrowedCursor<'_>) -> io::Result<()> {
+        crate::io::default_read_buf(|buf| self.read(buf), cursor)
+    }
+
+    pub fn read_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        crate::io::default_read_vectored(|buf| self.read(buf), bufs)
+    }
+
+    pub fn is_read_vectored(&self) -> bool {
+        false
+    }
+
+    pub fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        let options = self.with(|tcp| tcp.options)?;
+        wait(deadline(options.write_timeout), options.nonblocking, |interface| {
+            let (tcp, link) = interface.tcp(self.handle);
+            tcp.write(buf, link)
+        })
+    }
+
+    pub fn write_vectored(&self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        crate::io::default_write_vectored(|buf| self.write(buf), bufs)
+    }
+
+    pub fn is_write_vectored(&self) -> bool {
+        false
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.with(|tcp| SocketAddr::V4(tcp.remote))
+    }
+
+    pub fn socket_addr(&self) -> io::Result<SocketAddr> {
+        self.with(|tcp| SocketAddr::V4(tcp.local))
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        with_interface(|interface| {
+            let (tcp, link) = interface.tcp(self.handle);
+            if matches!(how, Shutdown::Read | Shutdown::Both) {
+                tcp.shutdown_read();
+            }
+            if matches!(how, Shutdown::Write | Shutdown::Both) {
+                tcp.shutdown_write(link);
+            }
+            Ok(())
+        })
+    }
+
+    pub fn duplicate(&self) -> io::Result<TcpStream> {
+        with_interface(|interface| {
+            interface.retain(self.handle);
+            Ok(TcpStream { handle: self.handle })
+        })
+    }
+
+    pub fn set_linger(&self, _: Option<Duration>) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        unsupported()
+    }
+
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        self.with(|tcp| tcp.nodelay = nodelay)
+    }
+
+    pub fn nodelay(&self) -> io::Result<bool> {
+        self.with(|tcp| tcp.nodelay)
+    }
+
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        let ttl = check_ttl(ttl)?;
+        self.with(|tcp| tcp.options.ttl = ttl)
+    }
+
+    pub fn ttl(&self) -> io::Result<u32> {
+        self.with(|tcp| tcp.options.ttl.into())
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.with(|tcp| tcp.pending_error.take().map(Failure::error))
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.with(|tcp| tcp.options.nonblocking = nonblocking)
+    }
+}
+
+impl Drop for TcpStream {
+    fn drop(&mut self) {
+        iface::release(self.handle);
+    }
+}
+
+impl fmt::Debug for TcpStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut res = f.debug_struct("TcpStream");
+        if let Ok(addr) = self.socket_addr() {
+            res.field("addr", &addr);
+        }
+        if let Ok(peer) = self.peer_addr() {
+            res.field("peer", &peer);
+        }
+        res.finish()
+    }
+}
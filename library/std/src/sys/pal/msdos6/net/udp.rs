@@ -0,0 +1,299 @@
+use super::driver::MacAddr;
+use super::iface::{
+    self, Handle, Link, Options, PROTOCOL_UDP, Socket, deadline, ipv4, resolve, wait,
+    with_interface,
+};
+use crate::collections::VecDeque;
+use crate::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4};
+use crate::sys::unsupported;
+use crate::time::Duration;
+use crate::{fmt, io};
+
+/// Datagrams received and not yet read are dropped past this many.
+const QUEUE_LIMIT: usize = 32;
+
+/// The state of a UDP socket in the socket table.
+pub struct Datagrams {
+    pub local: SocketAddrV4,
+    pub peer: Option<SocketAddrV4>,
+    pub options: Options,
+    pub broadcast: bool,
+    queue: VecDeque<(SocketAddrV4, Vec<u8>)>,
+}
+
+impl Datagrams {
+    pub fn new(local: SocketAddrV4) -> Datagrams {
+        Datagrams {
+            local,
+            peer: None,
+            options: Options::new(),
+            broadcast: false,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Whether a datagram from `source` to our `port` is for this socket.
+    pub fn accepts(&self, port: u16, source: SocketAddrV4) -> bool {
+        self.local.port() == port && self.peer.is_none_or(|peer| peer == source)
+    }
+
+    pub fn push(&mut self, source: SocketAddrV4, data: &[u8]) {
+        if self.queue.len() < QUEUE_LIMIT {
+            self.queue.push_back((source, data.to_vec()));
+        }
+    }
+
+    /// Takes or peeks at the oldest datagram, cutting it to the size of `buf`.
+    pub fn pop(&mut self, buf: &mut [u8], peek: bool) -> Option<(usize, SocketAddrV4)> {
+        let (source, data) = self.queue.front()?;
+        let (source, len) = (*source, buf.len().min(data.len()));
+        buf[..len].copy_from_slice(&data[..len]);
+        if !peek {
+            self.queue.pop_front();
+        }
+        Some((len, source))
+    }
+
+    pub fn send(
+        &self,
+        link: &mut Link,
+        mac: MacAddr,
+        destination: SocketAddrV4,
+        data: &[u8],
+    ) -> io::Result<usize> {
+        let source = match *self.local.ip() {
+            ip if ip.is_unspecified() => link.source_for(*destination.ip()),
+            ip => ip,
+        };
+        let len = 8 + data.len();
+        if len > iface::MAX_IP_PAYLOAD {
+            return Err(io::const_error!(
+                io::ErrorKind::InvalidInput,
+                "datagram too large, IP fragmentation is not supported",
+            ));
+        }
+        let mut datagram = Vec::with_capacity(len);
+        datagram.extend_from_slice(&self.local.port().to_be_bytes());
+        datagram.extend_from_slice(&destination.port().to_be_bytes());
+        datagram.extend_from_slice(&(len as u16).to_be_bytes());
+        datagram.extend_from_slice(&[0, 0]);
+        datagram.extend_from_slice(data);
+
+        let addresses = (source, *destination.ip());
+        let sum = iface::pseudo_header_sum(addresses, PROTOCOL_UDP, len);
+        // A checksum that comes out as zero is sent as all ones, as zero means none.
+        let sum = match iface::checksum(sum, &datagram) {
+            0 => 0xffff,
+            sum => sum,
+        };
+        datagram[6..8].copy_from_slice(&sum.to_be_bytes());
+        link.send_ip(mac, addresses, PROTOCOL_UDP, self.options.ttl, &datagram)?;
+        Ok(data.len())
+    }
+}
+
+pub struct UdpSocket {
+    handle: Handle,
+}
+
+impl UdpSocket {
+    pub fn bind(addr: io::Result<&SocketAddr>) -> io::Result<UdpSocket> {
+        let addr = *addr?;
+        with_interface(|interface| {
+            let local = interface.bind(addr, PROTOCOL_UDP)?;
+            let handle = interface.insert(Socket::Udp(Datagrams::new(local)), 1);
+            Ok(UdpSocket { handle })
+        })
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut Datagrams) -> R) -> io::Result<R> {
+        with_interface(|interface| Ok(f(interface.udp(self.handle).0)))
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.with(|udp| udp.peer)?
+            .map(SocketAddr::V4)
+            .ok_or(io::const_error!(io::ErrorKind::NotConnected, "socket is not connected"))
+    }
+
+    pub fn socket_addr(&self) -> io::Result<SocketAddr> {
+        self.with(|udp| SocketAddr::V4(udp.local))
+    }
+
+    fn receive(&self, buf: &mut [u8], peek: bool) -> io::Result<(usize, SocketAddr)> {
+        let options = self.with(|udp| udp.options)?;
+        wait(deadline(options.read_timeout), options.nonblocking, |interface| {
+            let (len, source) = interface.udp(self.handle).0.pop(buf, peek)?;
+            Some(Ok((len, SocketAddr::V4(source))))
+        })
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.receive(buf, false)
+    }
+
+    pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.receive(buf, true)
+    }
+
+    pub fn send_to(&self, buf: &[u8], addr: &SocketAddr) -> io::Result<usize> {
+        let destination = ipv4(*addr)?;
+        let (options, broadcast) = self.with(|udp| (udp.options, udp.broadcast))?;
+        if destination.ip().is_broadcast() && !broadcast {
+            return Err(io::const_error!(
+                io::ErrorKind::PermissionDenied,
+                "sending to the broadcast address needs `set_broadcast(true)`",
+            ));
+        }
+        let mac = resolve(*destination.ip(), deadline(options.write_timeout))?;
+        with_interface(|interface| {
+            let (udp, link) = interface.udp(self.handle);
+            udp.send(link, mac, destination, buf)
+        })
+    }
+
+    pub fn duplicate(&self) -> io::Result<UdpSocket> {
+        with_interface(|interface| {
+            interface.retain(self.handle);
+            Ok(UdpSocket { handle: self.handle })
+        })
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        check_timeout(timeout)?;
+        self.with(|udp| udp.options.read_timeout = timeout)
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        check_timeout(timeout)?;
+        self.with(|udp| udp.options.write_timeout = timeout)
+    }
+
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.with(|udp| udp.options.read_timeout)
+    }
+
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.with(|udp| udp.options.write_timeout)
+    }
+
+    pub fn set_broadcast(&self, broadcast: bool) -> io::Result<()> {
+        self.with(|udp| udp.broadcast = broadcast)
+    }
+
+    pub fn broadcast(&self) -> io::Result<bool> {
+        self.with(|udp| udp.broadcast)
+    }
+
+    pub fn set_multicast_loop_v4(&self, _: bool) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn multicast_loop_v4(&self) -> io::Result<bool> {
+        unsupported()
+    }
+
+    pub fn set_multicast_ttl_v4(&self, _: u32) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn multicast_ttl_v4(&self) -> io::Result<u32> {
+        unsupported()
+    }
+
+    pub fn set_multicast_loop_v6(&self, _: bool) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn multicast_loop_v6(&self) -> io::Result<bool> {
+        unsupported()
+    }
+
+    pub fn join_multicast_v4(&self, _: &Ipv4Addr, _: &Ipv4Addr) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn join_multicast_v6(&self, _: &Ipv6Addr, _: u32) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn leave_multicast_v4(&self, _: &Ipv4Addr, _: &Ipv4Addr) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn leave_multicast_v6(&self, _: &Ipv6Addr, _: u32) -> io::Result<()> {
+        unsupported()
+    }
+
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        let ttl = check_ttl(ttl)?;
+        self.with(|udp| udp.options.ttl = ttl)
+    }
+
+    pub fn ttl(&self) -> io::Result<u32> {
+        self.with(|udp| udp.options.ttl.into())
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        Ok(None)
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.with(|udp| udp.options.nonblocking = nonblocking)
+    }
+
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv_from(buf).map(|(len, _)| len)
+    }
+
+    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.peek_from(buf).map(|(len, _)| len)
+    }
+
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.send_to(buf, &self.peer_addr()?)
+    }
+
+    pub fn connect(&self, addr: io::Result<&SocketAddr>) -> io::Result<()> {
+        let peer = ipv4(*addr?)?;
+        self.with(|udp| {
+            udp.peer = Some(peer);
+            // Datagrams from anyone else that are still queued go, too.
+            udp.queue.retain(|(source, _)| *source == peer);
+        })
+    }
+}
+
+impl Drop for UdpSocket {
+    fn drop(&mut self) {
+        iface::release(self.handle);
+    }
+}
+
+impl fmt::Debug for UdpSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut res = f.debug_struct("UdpSocket");
+        if let Ok(addr) = self.socket_addr() {
+            res.field("addr", &addr);
+        }
+        res.finish()
+    }
+}
+
+/// Zero timeouts are refused, like on other platforms.
+pub fn check_timeout(timeout: Option<Duration>) -> io::Result<()> {
+    if timeout == Some(Duration::ZERO) {
+        return Err(io::const_error!(
+            io::ErrorKind::InvalidInput,
+            "cannot set a 0 duration timeout",
+        ));
+    }
+    Ok(())
+}
+
+pub fn check_ttl(ttl: u32) -> io::Result<u8> {
+    u8::try_from(ttl)
+        .ok()
+        .filter(|&ttl| ttl > 0)
+        .ok_or(io::const_error!(io::ErrorKind::InvalidInput, "TTL must be between 1 and 255"))
+}
@@ -1,60 +1,227 @@
-use super::unsupported;
+use core::slice::memchr;
+
+use super::dpmi::RealModeRegisters;
+use super::{codepage, dos, fs, psp, unsupported};
 use crate::error::Error as StdError;
 use crate::ffi::{OsStr, OsString};
-use crate::marker::PhantomData;
 use crate::path::{self, PathBuf};
-use crate::{fmt, io};
+use crate::sync::Mutex;
+use crate::sys::os_str::Buf;
+use crate::sys_common::FromInner;
+use crate::{fmt, io, iter, slice, vec};
 
 pub fn errno() -> i32 {
-    0
+    dos::last_error().into()
 }
 
-pub fn error_string(_errno: i32) -> String {
-    "operation successful".to_string()
+/// Returns the message MS-DOS 6 has for extended error code `errno`.
+pub fn error_string(errno: i32) -> String {
+    let message = match errno {
+        0x00 => "operation successful",
+        0x01 => "invalid function",
+        0x02 => "file not found",
+        0x03 => "path not found",
+        0x04 => "too many open files",
+        0x05 => "access denied",
+        0x06 => "invalid handle",
+        0x07 => "memory control blocks destroyed",
+        0x08 => "insufficient memory",
+        0x09 => "invalid memory block address",
+        0x0a => "invalid environment",
+        0x0b => "invalid format",
+        0x0c => "invalid access code",
+        0x0d => "invalid data",
+        0x0f => "invalid drive",
+        0x10 => "attempt to remove current directory",
+        0x11 => "not same device",
+        0x12 => "no more files",
+        0x13 => "disk write-protected",
+        0x14 => "unknown unit",
+        0x15 => "drive not ready",
+        0x16 => "unknown command",
+        0x17 => "data error (CRC)",
+        0x18 => "bad request structure length",
+        0x19 => "seek error",
+        0x1a => "unknown media type",
+        0x1b => "sector not found",
+        0x1c => "printer out of paper",
+        0x1d => "write fault",
+        0x1e => "read fault",
+        0x1f => "general failure",
+        0x20 => "sharing violation",
+        0x21 => "lock violation",
+        0x22 => "invalid disk change",
+        0x23 => "FCB unavailable",
+        0x24 => "sharing buffer overflow",
+        0x25 => "code page mismatch",
+        0x26 => "cannot complete file operation (out of input)",
+        0x27 => "insufficient disk space",
+        0x32 => "network request not supported",
+        0x33 => "remote computer not listening",
+        0x34 => "duplicate name on network",
+        0x35 => "network name not found",
+        0x36 => "network busy",
+        0x37 => "network device no longer exists",
+        0x38 => "network BIOS command limit exceeded",
+        0x39 => "network adapter hardware error",
+        0x3a => "incorrect response from network",
+        0x3b => "unexpected network error",
+        0x3c => "incompatible remote adapter",
+        0x3d => "print queue full",
+        0x3e => "not enough space for print file",
+        0x3f => "print file deleted",
+        0x40 => "network name deleted",
+        0x41 => "network access denied",
+        0x42 => "network device type incorrect",
+        0x43 => "network name not found",
+        0x44 => "network name limit exceeded",
+        0x45 => "network BIOS session limit exceeded",
+        0x46 => "sharing temporarily paused",
+        0x47 => "network request not accepted",
+        0x48 => "network print or disk redirection paused",
+        0x49 => "invalid network version",
+        0x4a => "account expired",
+        0x4b => "password expired",
+        0x4c => "login attempt invalid at this time",
+        0x4d => "disk limit exceeded on network node",
+        0x4e => "not logged in to network node",
+        0x50 => "file exists",
+        0x52 => "cannot make directory",
+        0x53 => "fail on INT 24h",
+        0x54 => "too many redirections",
+        0x55 => "duplicate redirection",
+        0x56 => "invalid password",
+        0x57 => "invalid parameter",
+        0x58 => "network write fault",
+        0x59 => "function not supported on network",
+        0x5a => "required system component not installed",
+        _ => return format!("unknown DOS error {errno:#04x}"),
+    };
+    message.to_string()
 }
 
 pub fn getcwd() -> io::Result<PathBuf> {
-    unsupported()
+    drive_current_dir(current_drive()?)
 }
 
-pub fn chdir(_: &path::Path) -> io::Result<()> {
-    unsupported()
+/// INT 21h/19h: returns the letter of the current drive.
+pub fn current_drive() -> io::Result<u8> {
+    let mut regs = RealModeRegisters { eax: 0x1900, ..Default::default() };
+    dos::int21(&mut regs)?;
+    Ok(b'A' + regs.eax as u8)
+}
+
+/// Returns the current directory of `drive`, an uppercase drive letter. DOS
+/// keeps one for every drive, which is what paths like `C:FOO` refer to.
+pub fn drive_current_dir(drive: u8) -> io::Result<PathBuf> {
+    // INT 21h/47h or 7147h: get the current directory of drive DL, without
+    // the drive or the leading backslash.
+    let mut transfer = dos::transfer_buffer();
+    let mut regs = RealModeRegisters {
+        eax: fs::path_function(0x47),
+        edx: u32::from(drive - b'A' + 1),
+        ds: transfer.segment(),
+        ..Default::default()
+    };
+    dos::int21(&mut regs)?;
+    let dir = codepage::decode(transfer.get_asciiz(0));
+    Ok(PathBuf::from(format!("{}:\\{dir}", char::from(drive))))
+}
+
+pub fn chdir(p: &path::Path) -> io::Result<()> {
+    let bytes = p.as_os_str().as_encoded_bytes();
+    let (drive, dir) = match bytes {
+        [letter, b':', dir @ ..] if letter.is_ascii_alphabetic() => {
+            (Some(letter.to_ascii_uppercase() - b'A'), dir)
+        }
+        _ => (None, bytes),
+    };
+
+    if !dir.is_empty() {
+        // INT 21h/3Bh: set the current directory of the drive named in the path.
+        dos::path_call(
+            p,
+            RealModeRegisters { eax: fs::path_function(0x3b), ..Default::default() },
+        )?;
+    }
+    if let Some(drive) = drive {
+        // INT 21h/0Eh: select the current drive. It doesn't report errors, so
+        // check with INT 21h/19h that it took.
+        let mut regs = RealModeRegisters { eax: 0x0e00, edx: drive.into(), ..Default::default() };
+        dos::int21(&mut regs)?;
+        let mut regs = RealModeRegisters { eax: 0x1900, ..Default::default() };
+        dos::int21(&mut regs)?;
+        if regs.eax as u8 != drive {
+            return Err(io::Error::from_raw_os_error(dos::ERROR_INVALID_DRIVE.into()));
+        }
+    }
+    Ok(())
 }
 
-pub struct SplitPaths<'a>(!, PhantomData<&'a ()>);
+const PATH_SEPARATOR: u8 = b';';
 
-pub fn split_paths(_unparsed: &OsStr) -> SplitPaths<'_> {
-    panic!("unsupported")
+pub struct SplitPaths<'a> {
+    iter: iter::Map<slice::Split<'a, u8, fn(&u8) -> bool>, fn(&'a [u8]) -> PathBuf>,
+}
+
+pub fn split_paths(unparsed: &OsStr) -> SplitPaths<'_> {
+    fn bytes_to_path(b: &[u8]) -> PathBuf {
+        PathBuf::from(OsString::from_inner(Buf { inner: b.to_vec() }))
+    }
+    fn is_separator(b: &u8) -> bool {
+        *b == PATH_SEPARATOR
+    }
+    let unparsed = unparsed.as_encoded_bytes();
+    SplitPaths {
+        iter: unparsed
+            .split(is_separator as fn(&u8) -> bool)
+            .map(bytes_to_path as fn(&[u8]) -> PathBuf),
+    }
 }
 
 impl<'a> Iterator for SplitPaths<'a> {
     type Item = PathBuf;
     fn next(&mut self) -> Option<PathBuf> {
-        self.0
+        self.iter.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
     }
 }
 
 #[derive(Debug)]
 pub struct JoinPathsError;
 
-pub fn join_paths<I, T>(_paths: I) -> Result<OsString, JoinPathsError>
+pub fn join_paths<I, T>(paths: I) -> Result<OsString, JoinPathsError>
 where
     I: Iterator<Item = T>,
     T: AsRef<OsStr>,
 {
-    Err(JoinPathsError)
+    let mut joined = Vec::new();
+
+    for (i, path) in paths.enumerate() {
+        let path = path.as_ref().as_encoded_bytes();
+        if i > 0 {
+            joined.push(PATH_SEPARATOR)
+        }
+        if path.contains(&PATH_SEPARATOR) {
+            return Err(JoinPathsError);
+        }
+        joined.extend_from_slice(path);
+    }
+    Ok(OsString::from_inner(Buf { inner: joined }))
 }
 
 impl fmt::Display for JoinPathsError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        "not supported on this platform yet".fmt(f)
+        write!(f, "path segment contains separator `{}`", char::from(PATH_SEPARATOR))
     }
 }
 
 impl StdError for JoinPathsError {
     #[allow(deprecated)]
     fn description(&self) -> &str {
-        "not supported on this platform yet"
+        "failed to join paths"
     }
 }
 
@@ -62,57 +229,128 @@ pub fn current_exe() -> io::Result<PathBuf> {
     unsupported()
 }
 
-pub struct Env(!);
+// The environment DOS passed us is copied here by `init_environment`, so that
+// changes made with `set_var` stay private to this process and can still be
+// handed on to children. Variables keep the order they had in the block.
+static ENV: Mutex<Vec<(OsString, OsString)>> = Mutex::new(Vec::new());
+
+pub fn init_environment() {
+    let mut env = ENV.lock().unwrap();
+    for var in psp::environment().split(|&b| b == 0) {
+        // Like COMMAND.COM, allow variable names starting with an equals
+        // sign and skip malformed entries.
+        let Some(pos) = var.get(1..).and_then(|v| memchr::memchr(b'=', v)).map(|p| p + 1) else {
+            continue;
+        };
+        env.push((
+            OsString::from(codepage::decode(&var[..pos])),
+            OsString::from(codepage::decode(&var[pos + 1..])),
+        ));
+    }
+}
+
+/// DOS treats variable names case-insensitively; `SET path=...` changes `PATH`.
+fn same_name(a: &OsStr, b: &OsStr) -> bool {
+    a.as_encoded_bytes().eq_ignore_ascii_case(b.as_encoded_bytes())
+}
+
+pub struct Env {
+    iter: vec::IntoIter<(OsString, OsString)>,
+}
+
+// FIXME(https://github.com/rust-lang/rust/issues/114583): Remove this when <OsStr as Debug>::fmt matches <str as Debug>::fmt.
+pub struct EnvStrDebug<'a> {
+    slice: &'a [(OsString, OsString)],
+}
+
+impl fmt::Debug for EnvStrDebug<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { slice } = self;
+        f.debug_list()
+            .entries(slice.iter().map(|(a, b)| (a.to_string_lossy(), b.to_string_lossy())))
+            .finish()
+    }
+}
 
 impl Env {
-    // FIXME(https://github.com/rust-lang/rust/issues/114583): Remove this when <OsStr as Debug>::fmt matches <str as Debug>::fmt.
     pub fn str_debug(&self) -> impl fmt::Debug + '_ {
-        let Self(inner) = self;
-        match *inner {}
+        let Self { iter } = self;
+        EnvStrDebug { slice: iter.as_slice() }
     }
 }
 
 impl fmt::Debug for Env {
-    fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self(inner) = self;
-        match *inner {}
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { iter } = self;
+        f.debug_list().entries(iter.as_slice()).finish()
     }
 }
 
+impl !Send for Env {}
+impl !Sync for Env {}
+
 impl Iterator for Env {
     type Item = (OsString, OsString);
     fn next(&mut self) -> Option<(OsString, OsString)> {
-        let Self(inner) = self;
-        match *inner {}
+        self.iter.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
     }
 }
 
 pub fn env() -> Env {
-    panic!("not supported on this platform")
+    Env { iter: ENV.lock().unwrap().clone().into_iter() }
 }
 
-pub fn getenv(_: &OsStr) -> Option<OsString> {
-    None
+pub fn getenv(k: &OsStr) -> Option<OsString> {
+    let env = ENV.lock().unwrap();
+    env.iter().find(|(key, _)| same_name(key, k)).map(|(_, value)| value.clone())
 }
 
-pub unsafe fn setenv(_: &OsStr, _: &OsStr) -> io::Result<()> {
-    Err(io::const_error!(io::ErrorKind::Unsupported, "cannot set env vars on this platform"))
+pub unsafe fn setenv(k: &OsStr, v: &OsStr) -> io::Result<()> {
+    // The environment block children get separates names from values with
+    // `=` and ends each variable with a NUL.
+    let key = k.as_encoded_bytes();
+    if key.is_empty() || key.contains(&b'=') || key.contains(&0) {
+        return Err(io::const_error!(
+            io::ErrorKind::InvalidInput,
+            "environment variable names must be non-empty and not contain `=` or NUL",
+        ));
+    }
+    if v.as_encoded_bytes().contains(&0) {
+        return Err(io::const_error!(
+            io::ErrorKind::InvalidInput,
+            "environment variable values must not contain NUL",
+        ));
+    }
+    let mut env = ENV.lock().unwrap();
+    match env.iter_mut().find(|(key, _)| same_name(key, k)) {
+        Some((_, value)) => *value = v.to_owned(),
+        None => env.push((k.to_owned(), v.to_owned())),
+    }
+    Ok(())
 }
 
-pub unsafe fn unsetenv(_: &OsStr) -> io::Result<()> {
-    Err(io::const_error!(io::ErrorKind::Unsupported, "cannot unset env vars on this platform"))
+pub unsafe fn unsetenv(k: &OsStr) -> io::Result<()> {
+    ENV.lock().unwrap().retain(|(key, _)| !same_name(key, k));
+    Ok(())
 }
 
 pub fn temp_dir() -> PathBuf {
-    panic!("no filesystem on this platform")
+    getenv("TEMP".as_ref())
+        .or_else(|| getenv("TMP".as_ref()))
+        .map(PathBuf::from)
+        .or_else(|| getcwd().ok())
+        .unwrap_or_else(|| PathBuf::from("C:\\"))
 }
 
 pub fn home_dir() -> Option<PathBuf> {
     None
 }
 
-pub fn exit(_code: i32) -> ! {
-    crate::intrinsics::abort()
+pub fn exit(code: i32) -> ! {
+    dos::exit(code as u8)
 }
 
 pub fn getpid() -> u32 {
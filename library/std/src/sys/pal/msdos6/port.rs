@@ -0,0 +1,26 @@
+//! x86 port I/O.
+//!
+//! DPMI hosts run clients with I/O permission for the legacy PC devices, or
+//! virtualize them, so the hardware can be programmed directly.
+
+use crate::arch::asm;
+
+/// Reads a byte from `port`.
+///
+/// # Safety
+///
+/// Reading a port can have side effects on the device behind it.
+pub unsafe fn inb(port: u16) -> u8 {
+    let value: u8;
+    unsafe { asm!("in al, dx", out("al") value, in("dx") port, options(nomem, nostack)) };
+    value
+}
+
+/// Writes a byte to `port`.
+///
+/// # Safety
+///
+/// Writing a port reprograms the device behind it.
+pub unsafe fn outb(port: u16, value: u8) {
+    unsafe { asm!("out dx, al", in("dx") port, in("al") value, options(nomem, nostack)) };
+}
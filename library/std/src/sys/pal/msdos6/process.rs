@@ -1,12 +1,14 @@
+use super::dpmi::{self, RealModeRegisters};
+use super::stdio::{STDERR_HANDLE, STDIN_HANDLE, STDOUT_HANDLE};
+use super::{codepage, dos, os};
 pub use crate::ffi::OsString as EnvKey;
 use crate::ffi::{OsStr, OsString};
 use crate::num::NonZero;
-use crate::path::Path;
+use crate::path::{Path, PathBuf};
 use crate::sys::fs::File;
 use crate::sys::pipe::AnonPipe;
-use crate::sys::unsupported;
 use crate::sys_common::process::{CommandEnv, CommandEnvs};
-use crate::{fmt, io};
+use crate::{env, fmt, io, ptr};
 
 ////////////////////////////////////////////////////////////////////////////////
 // Command
@@ -38,8 +40,61 @@ pub enum Stdio {
     MakePipe,
     ParentStdout,
     ParentStderr,
-    #[allow(dead_code)] // This variant exists only for the Debug impl
     InheritFile(File),
+    InheritPipe(AnonPipe),
+}
+
+impl Stdio {
+    /// Opens what the child's handle `target` should refer to, or returns
+    /// `None` if it inherits ours. Pipes also return the parent's end.
+    fn to_child_stdio(&self, target: u16) -> io::Result<(Option<File>, Option<AnonPipe>)> {
+        let duplicate = |handle| dos::duplicate(handle).map(File::from_handle);
+        match self {
+            Stdio::Inherit => Ok((None, None)),
+            Stdio::Null => {
+                let null = AnonPipe::null(target != STDIN_HANDLE)?;
+                Ok((Some(null.file().duplicate()?), None))
+            }
+            // The child runs to completion before the parent could write
+            // anything to it.
+            Stdio::MakePipe if target == STDIN_HANDLE => Err(io::const_error!(
+                io::ErrorKind::Unsupported,
+                "DOS children run before the parent can write to their stdin",
+            )),
+            Stdio::MakePipe => {
+                let pipe = AnonPipe::temp_file()?;
+                Ok((Some(pipe.file().duplicate()?), Some(pipe)))
+            }
+            Stdio::ParentStdout => Ok((Some(duplicate(STDOUT_HANDLE)?), None)),
+            Stdio::ParentStderr => Ok((Some(duplicate(STDERR_HANDLE)?), None)),
+            Stdio::InheritFile(file) => Ok((Some(file.duplicate()?), None)),
+            Stdio::InheritPipe(pipe) => Ok((Some(pipe.file().duplicate()?), None)),
+        }
+    }
+}
+
+/// One of our standard handles, pointed elsewhere while a child runs.
+struct Redirection {
+    target: u16,
+    saved: u16,
+}
+
+impl Redirection {
+    fn new(target: u16, source: u16) -> io::Result<Redirection> {
+        let saved = dos::duplicate(target)?;
+        if let Err(err) = dos::force_duplicate(source, target) {
+            let _ = dos::close(saved);
+            return Err(err);
+        }
+        Ok(Redirection { target, saved })
+    }
+}
+
+impl Drop for Redirection {
+    fn drop(&mut self) {
+        let _ = dos::force_duplicate(self.saved, self.target);
+        let _ = dos::close(self.saved);
+    }
 }
 
 impl Command {
@@ -97,22 +152,260 @@ pub fn get_current_dir(&self) -> Option<&Path> {
         self.cwd.as_ref().map(|cs| Path::new(cs))
     }
 
+    /// Runs the program to completion, as DOS can only run one program at a
+    /// time; the returned `Process` has already exited.
     pub fn spawn(
         &mut self,
-        _default: Stdio,
-        _needs_stdin: bool,
+        default: Stdio,
+        needs_stdin: bool,
     ) -> io::Result<(Process, StdioPipes)> {
-        unsupported()
+        let null = Stdio::Null;
+        let default_stdin = if needs_stdin { &default } else { &null };
+        let stdin = self.stdin.as_ref().unwrap_or(default_stdin);
+        let stdout = self.stdout.as_ref().unwrap_or(&default);
+        let stderr = self.stderr.as_ref().unwrap_or(&default);
+
+        let program = resolve_program(&self.program)?;
+        let mut tail = make_command_tail(&self.args[1..])?;
+        let is_batch_file = program.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("bat"));
+        let program = if is_batch_file {
+            // Batch files are run by the command interpreter.
+            let mut command = b" /C ".to_vec();
+            command.extend_from_slice(&codepage::encode(program.as_os_str().as_encoded_bytes())?);
+            command.extend_from_slice(&tail);
+            tail = command;
+            env::var_os("COMSPEC").map_or_else(|| PathBuf::from("C:\\COMMAND.COM"), PathBuf::from)
+        } else {
+            program
+        };
+        if tail.len() > MAX_COMMAND_TAIL {
+            return Err(io::const_error!(
+                io::ErrorKind::InvalidInput,
+                "command line is too long for DOS",
+            ));
+        }
+        let env = EnvironmentBlock::new(&self.env)?;
+
+        // Everything the child gets is opened before any of our own handles
+        // are redirected, so that `ParentStdout` and `ParentStderr` still
+        // refer to the real ones.
+        let (stdin_source, stdin_pipe) = stdin.to_child_stdio(STDIN_HANDLE)?;
+        let (stdout_source, stdout_pipe) = stdout.to_child_stdio(STDOUT_HANDLE)?;
+        let (stderr_source, stderr_pipe) = stderr.to_child_stdio(STDERR_HANDLE)?;
+        let mut redirections = Vec::new();
+        for (target, source) in [
+            (STDIN_HANDLE, &stdin_source),
+            (STDOUT_HANDLE, &stdout_source),
+            (STDERR_HANDLE, &stderr_source),
+        ] {
+            if let Some(source) = source {
+                redirections.push(Redirection::new(target, source.handle())?);
+            }
+        }
+
+        // DOS keeps a current directory for every drive, so a `cwd` on
+        // another drive changes that drive's too, and both are put back.
+        let saved_cwd = match &self.cwd {
+            Some(cwd) => {
+                let saved = os::getcwd()?;
+                let other_drive = match cwd.as_encoded_bytes() {
+                    [letter, b':', ..] if letter.is_ascii_alphabetic() => {
+                        Some(letter.to_ascii_uppercase())
+                    }
+                    _ => None,
+                }
+                .filter(|&drive| saved.as_os_str().as_encoded_bytes().first() != Some(&drive));
+                let saved_other = other_drive.map(os::drive_current_dir).transpose()?;
+                os::chdir(Path::new(cwd))?;
+                Some((saved, saved_other))
+            }
+            None => None,
+        };
+        let status = exec(&program, &tail, &env).and_then(|()| {
+            // INT 21h/4Dh: get the return code of the child.
+            let mut regs = RealModeRegisters { eax: 0x4d00, ..Default::default() };
+            dos::int21(&mut regs)?;
+            Ok(ExitStatus(regs.eax as u16))
+        });
+        drop(redirections);
+        if let Some((saved, saved_other)) = saved_cwd {
+            // Ours goes last, as it selects the current drive. The child has
+            // run by now, so its status and output are returned even if the
+            // directories can't be put back; a caller retrying on an error
+            // would run it again.
+            if let Some(other) = saved_other {
+                let _ = os::chdir(&other);
+            }
+            let _ = os::chdir(&saved);
+        }
+        let status = status?;
+
+        for pipe in [&stdout_pipe, &stderr_pipe].into_iter().flatten() {
+            pipe.rewind()?;
+        }
+        let pipes = StdioPipes { stdin: stdin_pipe, stdout: stdout_pipe, stderr: stderr_pipe };
+        Ok((Process { status }, pipes))
     }
 
     pub fn output(&mut self) -> io::Result<(ExitStatus, Vec<u8>, Vec<u8>)> {
-        unsupported()
+        let (mut process, pipes) = self.spawn(Stdio::MakePipe, false)?;
+        let status = process.wait()?;
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        if let Some(pipe) = pipes.stdout {
+            pipe.read_to_end(&mut stdout)?;
+        }
+        if let Some(pipe) = pipes.stderr {
+            pipe.read_to_end(&mut stderr)?;
+        }
+        Ok((status, stdout, stderr))
     }
 }
 
+/// The longest command tail that fits in the PSP, without its length byte and CR.
+const MAX_COMMAND_TAIL: usize = 126;
+
+/// Extensions DOS can run, in the order COMMAND.COM tries them.
+const EXTENSIONS: [&str; 3] = ["COM", "EXE", "BAT"];
+
+/// Finds the file to run like COMMAND.COM does: a name without an extension
+/// gets each of the executable ones, and a name without a directory is
+/// looked for in the current directory and then along `PATH`.
+fn resolve_program(program: &OsStr) -> io::Result<PathBuf> {
+    let program = Path::new(program);
+    let find_in = |dir: Option<PathBuf>| {
+        let path = dir.map_or_else(|| program.to_path_buf(), |dir| dir.join(program));
+        if path.extension().is_some() {
+            path.is_file().then_some(path)
+        } else {
+            EXTENSIONS.iter().map(|ext| path.with_extension(ext)).find(|path| path.is_file())
+        }
+    };
+
+    let has_directory =
+        program.as_os_str().as_encoded_bytes().iter().any(|&b| matches!(b, b'\\' | b'/' | b':'));
+    let found = if has_directory {
+        find_in(None)
+    } else {
+        find_in(None).or_else(|| {
+            let path = env::var_os("PATH")?;
+            env::split_paths(&path)
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .find_map(|dir| find_in(Some(dir)))
+        })
+    };
+    found.ok_or(io::const_error!(io::ErrorKind::NotFound, "program not found"))
+}
+
+/// Builds the command tail from the arguments, quoting the ones that contain
+/// spaces so that they are split up the same way std reads them.
+fn make_command_tail(args: &[OsString]) -> io::Result<Vec<u8>> {
+    let mut tail = Vec::new();
+    for arg in args {
+        let arg = codepage::encode(arg.as_encoded_bytes())?;
+        if arg.iter().any(|&b| matches!(b, b'"' | b'\r' | b'\0')) {
+            return Err(io::const_error!(
+                io::ErrorKind::InvalidInput,
+                "DOS arguments cannot contain quotes, carriage returns or nul bytes",
+            ));
+        }
+        // Like COMMAND.COM, start every argument with a space.
+        tail.push(b' ');
+        if arg.is_empty() || arg.iter().any(|&b| b == b' ' || b == b'\t') {
+            tail.push(b'"');
+            tail.extend_from_slice(&arg);
+            tail.push(b'"');
+        } else {
+            tail.extend_from_slice(&arg);
+        }
+    }
+    Ok(tail)
+}
+
+/// An environment block for the child in conventional memory, where DOS
+/// can copy it from.
+struct EnvironmentBlock {
+    block: dpmi::DosMemoryBlock,
+}
+
+impl EnvironmentBlock {
+    fn new(env: &CommandEnv) -> io::Result<EnvironmentBlock> {
+        let mut bytes = Vec::new();
+        for (key, value) in env.capture() {
+            bytes.extend_from_slice(&codepage::encode(key.as_encoded_bytes())?);
+            bytes.push(b'=');
+            bytes.extend_from_slice(&codepage::encode(value.as_encoded_bytes())?);
+            bytes.push(0);
+        }
+        // An empty environment still needs the NUL ending the list.
+        if bytes.is_empty() {
+            bytes.push(0);
+        }
+        bytes.push(0);
+
+        let paragraphs = u16::try_from(bytes.len().div_ceil(16)).map_err(|_| {
+            io::const_error!(io::ErrorKind::InvalidInput, "environment is too large for DOS")
+        })?;
+        let block = dpmi::allocate_dos_memory(paragraphs)
+            .map_err(|_| io::const_error!(io::ErrorKind::OutOfMemory, "out of DOS memory"))?;
+        unsafe {
+            let dest = dpmi::linear_to_ptr(u32::from(block.segment) << 4);
+            ptr::copy_nonoverlapping(bytes.as_ptr(), dest, bytes.len());
+        }
+        Ok(EnvironmentBlock { block })
+    }
+}
+
+impl Drop for EnvironmentBlock {
+    fn drop(&mut self) {
+        unsafe { dpmi::free_dos_memory(self.block.selector) };
+    }
+}
+
+/// INT 21h/4B00h: loads and runs `program`, returning once it has exited.
+fn exec(program: &Path, tail: &[u8], env: &EnvironmentBlock) -> io::Result<()> {
+    // Layout of the transfer buffer during the call.
+    const PARAMETER_BLOCK: usize = 0x100;
+    const COMMAND_TAIL: usize = 0x110;
+    const FCB1: usize = 0x190;
+    const FCB2: usize = 0x1b0;
+    const PROGRAM: usize = 0x200;
+
+    let mut transfer = dos::transfer_buffer();
+    let segment = transfer.segment();
+    let far = |offset: usize| (u32::from(segment) << 16) | offset as u32;
+
+    let buffer = transfer.as_mut_slice();
+    buffer[COMMAND_TAIL] = tail.len() as u8;
+    buffer[COMMAND_TAIL + 1..][..tail.len()].copy_from_slice(tail);
+    buffer[COMMAND_TAIL + 1 + tail.len()] = b'\r';
+    // Blank unopened FCBs: the default drive and an empty name.
+    for fcb in [FCB1, FCB2] {
+        buffer[fcb] = 0;
+        buffer[fcb + 1..fcb + 12].fill(b' ');
+        buffer[fcb + 12..fcb + 0x20].fill(0);
+    }
+    let block = &mut buffer[PARAMETER_BLOCK..][..14];
+    block[0..2].copy_from_slice(&env.block.segment.to_le_bytes());
+    block[2..6].copy_from_slice(&far(COMMAND_TAIL).to_le_bytes());
+    block[6..10].copy_from_slice(&far(FCB1).to_le_bytes());
+    block[10..14].copy_from_slice(&far(FCB2).to_le_bytes());
+    let program = transfer.put_asciiz(PROGRAM, program.as_os_str().as_encoded_bytes())?;
+
+    let mut regs = RealModeRegisters {
+        eax: 0x4b00,
+        ebx: PARAMETER_BLOCK as u32,
+        edx: program.into(),
+        ds: segment,
+        es: segment,
+        ..Default::default()
+    };
+    dos::int21(&mut regs)
+}
+
 impl From<AnonPipe> for Stdio {
     fn from(pipe: AnonPipe) -> Stdio {
-        pipe.diverge()
+        Stdio::InheritPipe(pipe)
     }
 }
 
@@ -198,59 +491,69 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     }
 }
 
+/// The word INT 21h/4Dh returns: the exit code in the low byte, and how the
+/// program ended in the high byte.
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
-#[non_exhaustive]
-pub struct ExitStatus();
+pub struct ExitStatus(u16);
+
+/// Termination types in the high byte of an [`ExitStatus`].
+const TERMINATED_NORMALLY: u8 = 0;
+const TERMINATED_BY_CTRL_C: u8 = 1;
+const TERMINATED_BY_CRITICAL_ERROR: u8 = 2;
+const TERMINATED_AND_STAYED_RESIDENT: u8 = 3;
 
 impl ExitStatus {
     pub fn exit_ok(&self) -> Result<(), ExitStatusError> {
-        Ok(())
+        match NonZero::new(self.0) {
+            Some(status) if self.code() != Some(0) => Err(ExitStatusError(status)),
+            _ => Ok(()),
+        }
     }
 
     pub fn code(&self) -> Option<i32> {
-        Some(0)
+        match self.termination_type() {
+            TERMINATED_NORMALLY | TERMINATED_AND_STAYED_RESIDENT => Some((self.0 & 0xff).into()),
+            _ => None,
+        }
     }
-}
 
-impl fmt::Display for ExitStatus {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "<dummy exit status>")
+    fn termination_type(&self) -> u8 {
+        (self.0 >> 8) as u8
     }
 }
 
-pub struct ExitStatusError(!);
-
-impl Clone for ExitStatusError {
-    fn clone(&self) -> ExitStatusError {
-        self.0
+impl From<u16> for ExitStatus {
+    fn from(status: u16) -> ExitStatus {
+        ExitStatus(status)
     }
 }
 
-impl Copy for ExitStatusError {}
-
-impl PartialEq for ExitStatusError {
-    fn eq(&self, _other: &ExitStatusError) -> bool {
-        self.0
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.termination_type() {
+            TERMINATED_NORMALLY => write!(f, "exit code: {}", self.0 & 0xff),
+            TERMINATED_BY_CTRL_C => write!(f, "terminated by Ctrl-C"),
+            TERMINATED_BY_CRITICAL_ERROR => write!(f, "terminated by a critical error"),
+            TERMINATED_AND_STAYED_RESIDENT => {
+                write!(f, "exit code: {} (stayed resident)", self.0 & 0xff)
+            }
+            _ => write!(f, "unrecognized exit status: {:#06x}", self.0),
+        }
     }
 }
 
-impl Eq for ExitStatusError {}
-
-impl fmt::Debug for ExitStatusError {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0
-    }
-}
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct ExitStatusError(NonZero<u16>);
 
 impl Into<ExitStatus> for ExitStatusError {
     fn into(self) -> ExitStatus {
-        self.0
+        ExitStatus(self.0.get())
     }
 }
 
 impl ExitStatusError {
     pub fn code(self) -> Option<NonZero<i32>> {
-        self.0
+        ExitStatus(self.0.get()).code().and_then(NonZero::new)
     }
 }
 
@@ -272,23 +575,26 @@ fn from(code: u8) -> Self {
     }
 }
 
-pub struct Process(!);
+/// A child that has already run to completion.
+pub struct Process {
+    status: ExitStatus,
+}
 
 impl Process {
     pub fn id(&self) -> u32 {
-        self.0
+        0
     }
 
     pub fn kill(&mut self) -> io::Result<()> {
-        self.0
+        Ok(())
     }
 
     pub fn wait(&mut self) -> io::Result<ExitStatus> {
-        self.0
+        Ok(self.status)
     }
 
     pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
-        self.0
+        Ok(Some(self.status))
     }
 }
 
@@ -0,0 +1,110 @@
+//! The Program Segment Prefix DOS built for this process.
+//!
+//! `_start` in `crt0_msdos6.o` records where the PSP lives before calling
+//! `main`, and [`init`] copies that here so the rest of std doesn't have to
+//! know about the startup object.
+
+use super::dpmi;
+use crate::slice;
+use crate::sync::atomic::AtomicU16;
+use crate::sync::atomic::Ordering::Relaxed;
+
+extern "C" {
+    // Recorded by `_start` in `crt0_msdos6.o`.
+    static __msdos6_psp_segment: u16;
+    static __msdos6_psp_selector: u16;
+}
+
+static SEGMENT: AtomicU16 = AtomicU16::new(0);
+static SELECTOR: AtomicU16 = AtomicU16::new(0);
+
+/// Offset of the environment segment, which the DPMI host has turned into a selector.
+const ENVIRONMENT: usize = 0x2c;
+/// Offset of the length-prefixed command tail.
+const COMMAND_TAIL: usize = 0x80;
+/// The largest environment DOS can hand out.
+const MAX_ENVIRONMENT_SIZE: usize = 0x8000;
+
+// SAFETY: must be called only once during runtime initialization.
+pub unsafe fn init() {
+    unsafe {
+        SEGMENT.store(__msdos6_psp_segment, Relaxed);
+        SELECTOR.store(__msdos6_psp_selector, Relaxed);
+    }
+}
+
+/// Real-mode segment of the PSP.
+pub fn segment() -> u16 {
+    SEGMENT.load(Relaxed)
+}
+
+/// Selector the DPMI host created for the PSP.
+pub fn selector() -> u16 {
+    SELECTOR.load(Relaxed)
+}
+
+fn as_ptr() -> Option<*const u8> {
+    match segment() {
+        0 => None,
+        segment => Some(dpmi::linear_to_ptr(u32::from(segment) << 4)),
+    }
+}
+
+/// The command tail at PSP:0080h, without its length prefix and terminating CR.
+pub fn command_tail() -> &'static [u8] {
+    let Some(psp) = as_ptr() else { return &[] };
+    unsafe {
+        let tail = psp.add(COMMAND_TAIL);
+        let len = usize::from(tail.read()).min(0x7f);
+        let tail = slice::from_raw_parts(tail.add(1), len);
+        match tail.iter().position(|&b| b == b'\r') {
+            Some(end) => &tail[..end],
+            None => tail,
+        }
+    }
+}
+
+/// The selector of the environment block, as stored at PSP:002Ch.
+pub fn environment_selector() -> u16 {
+    let Some(psp) = as_ptr() else { return 0 };
+    unsafe { psp.add(ENVIRONMENT).cast::<u16>().read_unaligned() }
+}
+
+/// The whole environment block: the `NAME=value` strings, the empty string
+/// ending them, and on DOS 3.0 and later the program path behind that.
+fn environment_block() -> &'static [u8] {
+    let selector = environment_selector();
+    if selector == 0 {
+        return &[];
+    }
+    let Some(base) = dpmi::segment_base(selector) else { return &[] };
+    let limit = dpmi::segment_limit(selector).map_or(0, |limit| limit as usize + 1);
+    unsafe { slice::from_raw_parts(dpmi::linear_to_ptr(base), limit.min(MAX_ENVIRONMENT_SIZE)) }
+}
+
+/// The `NAME=value` strings of the environment, each terminated by a NUL,
+/// followed by the NUL that ends the list.
+pub fn environment() -> &'static [u8] {
+    let block = environment_block();
+    if block.first() == Some(&0) {
+        return &block[..1];
+    }
+    match block.windows(2).position(|w| w == [0, 0]) {
+        Some(end) => &block[..end + 2],
+        None => &[],
+    }
+}
+
+/// The full path of the running program, which DOS stores behind the
+/// environment strings.
+pub fn program_path() -> Option<&'static [u8]> {
+    let block = environment_block();
+    let rest = &block[environment().len()..];
+    // A count of the strings that follow, always 1 in practice.
+    let (count, path) = rest.split_first_chunk::<2>()?;
+    if u16::from_le_bytes(*count) == 0 {
+        return None;
+    }
+    let end = path.iter().position(|&b| b == 0)?;
+    Some(&path[..end])
+}
@@ -0,0 +1,520 @@
+//! Serial ports: the 8250 and 16550 UARTs of COM1 to COM4.
+//!
+//! The BIOS only offers polled I/O, which loses bytes as soon as the program
+//! is busy elsewhere. Instead, the UART interrupts whenever it has received a
+//! byte or can take more to send, and `dispatch` moves bytes between it and a
+//! ring buffer for each direction. COM1 and COM3 share IRQ 4, and COM2 and
+//! COM4 share IRQ 3.
+//!
+//! The DPMI host calls our protected-mode handler for the IRQ whether it
+//! arrives in protected mode or in real mode during a DOS call. The memory
+//! the handler touches is not locked, which a host that pages to disk would
+//! need. Like in `ctrl_break`, the vectors have to be put back before the
+//! program exits, which `restore` does.
+
+use super::dpmi;
+use super::port::{inb, outb};
+use super::thread::Thread;
+use super::time::Instant;
+use crate::arch::global_asm;
+use crate::cell::UnsafeCell;
+use crate::io;
+use crate::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use crate::sync::atomic::{AtomicBool, AtomicU8, AtomicU16, AtomicU32, AtomicUsize};
+use crate::time::Duration;
+
+/// The BIOS data area words holding the I/O ports of COM1 to COM4, or 0.
+const BIOS_COM_PORTS: u32 = 0x400;
+const PORTS: usize = 4;
+
+// UART registers, from the base port. With `LCR_DLAB` set, the first two are
+// the divisor latch instead.
+const DATA: u16 = 0;
+const IER: u16 = 1;
+const IIR: u16 = 2;
+const FCR: u16 = 2;
+const LCR: u16 = 3;
+const MCR: u16 = 4;
+const LSR: u16 = 5;
+const MSR: u16 = 6;
+
+const IER_RECEIVE: u8 = 0x01;
+const IER_TRANSMIT: u8 = 0x02;
+const IER_LINE_STATUS: u8 = 0x04;
+
+const IIR_NONE_PENDING: u8 = 0x01;
+const IIR_CAUSE: u8 = 0x0e;
+const IIR_TRANSMIT: u8 = 0x02;
+const IIR_RECEIVE: u8 = 0x04;
+const IIR_LINE_STATUS: u8 = 0x06;
+/// Data has sat in the receive FIFO for a while without reaching the trigger level.
+const IIR_TIMEOUT: u8 = 0x0c;
+/// Set when the FIFOs are enabled, which only works on a 16550A or later.
+const IIR_FIFO: u8 = 0xc0;
+
+/// Enables and clears both FIFOs, interrupting once 8 bytes are received.
+const FCR_ENABLE: u8 = 0x87;
+const FIFO_SIZE: u8 = 16;
+
+const LCR_DLAB: u8 = 0x80;
+
+/// DTR and RTS, and OUT2, which connects the UART's interrupt line to the PIC on PCs.
+const MCR_OPEN: u8 = 0x0b;
+
+const LSR_DATA_READY: u8 = 0x01;
+const LSR_OVERRUN: u8 = 0x02;
+const LSR_PARITY: u8 = 0x04;
+const LSR_FRAMING: u8 = 0x08;
+const LSR_BREAK: u8 = 0x10;
+const LSR_ERRORS: u8 = LSR_OVERRUN | LSR_PARITY | LSR_FRAMING | LSR_BREAK;
+/// Both the holding and the shift register are empty: the last byte is out.
+const LSR_TRANSMITTER_EMPTY: u8 = 0x40;
+
+/// The UART clock divided by 16, the baud rate of divisor 1.
+pub const MAX_BAUD_RATE: u32 = 115_200;
+
+const PIC_COMMAND: u16 = 0x20;
+const PIC_MASK: u16 = 0x21;
+const PIC_EOI: u8 = 0x20;
+/// The interrupt the master PIC raises for IRQ 0.
+const PIC_BASE_VECTOR: u8 = 0x08;
+
+const RING_SIZE: usize = 2048;
+
+/// A single-producer, single-consumer byte queue. One side of it is the
+/// interrupt handler, the other the program.
+struct Ring {
+    data: UnsafeCell<[u8; RING_SIZE]>,
+    // Bytes written and read so far, wrapping.
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+unsafe impl Sync for Ring {}
+
+impl Ring {
+    const fn new() -> Ring {
+        Ring {
+            data: UnsafeCell::new([0; RING_SIZE]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.head.load(Acquire).wrapping_sub(self.tail.load(Acquire))
+    }
+
+    fn push(&self, byte: u8) -> bool {
+        let head = self.head.load(Relaxed);
+        if head.wrapping_sub(self.tail.load(Acquire)) == RING_SIZE {
+            return false;
+        }
+        unsafe { (*self.data.get())[head % RING_SIZE] = byte };
+        self.head.store(head.wrapping_add(1), Release);
+        true
+    }
+
+    fn pop(&self) -> Option<u8> {
+        let tail = self.tail.load(Relaxed);
+        if tail == self.head.load(Acquire) {
+            return None;
+        }
+        let byte = unsafe { (*self.data.get())[tail % RING_SIZE] };
+        self.tail.store(tail.wrapping_add(1), Release);
+        Some(byte)
+    }
+
+    /// Drops what is queued. Only for the consumer.
+    fn clear(&self) {
+        self.tail.store(self.head.load(Acquire), Release);
+    }
+}
+
+struct Uart {
+    /// The base I/O port while the port is open, or 0.
+    base: AtomicU16,
+    irq: AtomicU8,
+    /// How many bytes the transmitter takes at once.
+    fifo_depth: AtomicU8,
+    receive: Ring,
+    transmit: Ring,
+    /// Line status error bits seen since the last `take_error`.
+    errors: AtomicU8,
+    // The interrupt enable and modem control registers from before we opened it.
+    saved_ier: AtomicU8,
+    saved_mcr: AtomicU8,
+}
+
+impl Uart {
+    const fn new() -> Uart {
+        Uart {
+            base: AtomicU16::new(0),
+            irq: AtomicU8::new(0),
+            fifo_depth: AtomicU8::new(1),
+            receive: Ring::new(),
+            transmit: Ring::new(),
+            errors: AtomicU8::new(0),
+            saved_ier: AtomicU8::new(0),
+            saved_mcr: AtomicU8::new(0),
+        }
+    }
+}
+
+static UARTS: [Uart; PORTS] = [const { Uart::new() }; PORTS];
+
+/// The handler of IRQ 3 or 4, while a port using it is open.
+struct Irq {
+    users: AtomicU8,
+    old_selector: AtomicU16,
+    old_offset: AtomicU32,
+    /// Whether the IRQ was masked in the PIC before we unmasked it.
+    was_masked: AtomicBool,
+}
+
+impl Irq {
+    const fn new() -> Irq {
+        Irq {
+            users: AtomicU8::new(0),
+            old_selector: AtomicU16::new(0),
+            old_offset: AtomicU32::new(0),
+            was_masked: AtomicBool::new(false),
+        }
+    }
+}
+
+const FIRST_IRQ: u8 = 3;
+static IRQS: [Irq; 2] = [const { Irq::new() }; 2];
+
+// Interrupts stay disabled while `dispatch` runs, so one stack does for both
+// IRQs and is never in use when one comes in.
+static STACK: dpmi::InterruptStack<0x1000> = dpmi::InterruptStack::new();
+
+extern "C" {
+    fn __msdos6_irq3();
+    fn __msdos6_irq4();
+}
+
+global_asm!(
+    ".pushsection .text.__msdos6_serial_irq,\"ax\"",
+    ".globl __msdos6_irq3",
+    "__msdos6_irq3:",
+    "push ds",
+    "push es",
+    "pushad",
+    "mov edx, 3",
+    "jmp __msdos6_serial_irq",
+    ".globl __msdos6_irq4",
+    "__msdos6_irq4:",
+    "push ds",
+    "push es",
+    "pushad",
+    "mov edx, 4",
+    "__msdos6_serial_irq:",
+    "lea ebx, [{stack}]",
+    "lea eax, [{dispatch}]",
+    "call __msdos6_interrupt_entry",
+    "popad",
+    "pop es",
+    "pop ds",
+    "iretd",
+    ".popsection",
+    stack = sym STACK,
+    dispatch = sym dispatch,
+);
+
+extern "C" fn dispatch(irq: u32) {
+    for uart in &UARTS {
+        let base = uart.base.load(Relaxed);
+        if base != 0 && u32::from(uart.irq.load(Relaxed)) == irq {
+            unsafe { service(uart, base) };
+        }
+    }
+    unsafe { outb(PIC_COMMAND, PIC_EOI) };
+}
+
+/// Handles everything the UART at `base` is interrupting for.
+unsafe fn service(uart: &Uart, base: u16) {
+    loop {
+        let iir = unsafe { inb(base + IIR) };
+        if iir & IIR_NONE_PENDING != 0 {
+            return;
+        }
+        match iir & IIR_CAUSE {
+            IIR_RECEIVE | IIR_TIMEOUT | IIR_LINE_STATUS => loop {
+                // Reading the line status clears its error bits.
+                let lsr = unsafe { inb(base + LSR) };
+                uart.errors.fetch_or(lsr & LSR_ERRORS, Relaxed);
+                if lsr & LSR_DATA_READY == 0 {
+                    break;
+                }
+                let byte = unsafe { inb(base + DATA) };
+                if !uart.receive.push(byte) {
+                    uart.errors.fetch_or(LSR_OVERRUN, Relaxed);
+                }
+            },
+            IIR_TRANSMIT => {
+                for _ in 0..uart.fifo_depth.load(Relaxed) {
+                    match uart.transmit.pop() {
+                        Some(byte) => unsafe { outb(base + DATA, byte) },
+                        None => {
+                            // Nothing left to send; `write` turns this back on.
+                            unsafe { outb(base + IER, IER_RECEIVE | IER_LINE_STATUS) };
+                            break;
+                        }
+                    }
+                }
+            }
+            // A modem status change, cleared by reading it.
+            _ => unsafe {
+                inb(base + MSR);
+            },
+        }
+    }
+}
+
+/// An open COM port.
+pub struct SerialPort {
+    index: usize,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl SerialPort {
+    /// Opens COM`number`, with `divisor` for the baud rate and `line_control`
+    /// giving the data bits, parity and stop bits as in the LCR.
+    pub fn open(number: u8, divisor: u16, line_control: u8, fifo: bool) -> io::Result<SerialPort> {
+        let index = usize::from(number.wrapping_sub(1));
+        if index >= PORTS {
+            return Err(io::const_error!(
+                io::ErrorKind::InvalidInput,
+                "serial ports are numbered 1 to 4",
+            ));
+        }
+        // Checked before probing, as reading the IIR of an open port would
+        // acknowledge an interrupt its handler is waiting for.
+        let uart = &UARTS[index];
+        if uart.base.load(Relaxed) != 0 {
+            return Err(io::const_error!(io::ErrorKind::ResourceBusy, "serial port already open"));
+        }
+        let address = BIOS_COM_PORTS + 2 * index as u32;
+        let base = unsafe { dpmi::linear_to_ptr(address).cast::<u16>().read_volatile() };
+        // Nothing answers on a port without a UART, so it reads as all ones.
+        if base == 0 || unsafe { inb(base + IIR) } == 0xff {
+            return Err(io::const_error!(io::ErrorKind::NotFound, "no such serial port"));
+        }
+
+        unsafe {
+            uart.saved_ier.store(inb(base + IER), Relaxed);
+            uart.saved_mcr.store(inb(base + MCR), Relaxed);
+            outb(base + IER, 0);
+            outb(base + LCR, LCR_DLAB);
+            outb(base + DATA, divisor as u8);
+            outb(base + IER, (divisor >> 8) as u8);
+            outb(base + LCR, line_control & !LCR_DLAB);
+
+            outb(base + FCR, if fifo { FCR_ENABLE } else { 0 });
+            let has_fifo = fifo && inb(base + IIR) & IIR_FIFO == IIR_FIFO;
+            uart.fifo_depth.store(if has_fifo { FIFO_SIZE } else { 1 }, Relaxed);
+
+            // Drop whatever came in before.
+            inb(base + LSR);
+            inb(base + DATA);
+            inb(base + MSR);
+        }
+        uart.receive.clear();
+        uart.transmit.clear();
+        uart.errors.store(0, Relaxed);
+
+        // COM1 and COM3 are on IRQ 4, COM2 and COM4 on IRQ 3.
+        let irq = if index % 2 == 0 { 4 } else { 3 };
+        uart.irq.store(irq, Relaxed);
+        if let Err(e) = hook_irq(irq) {
+            unsafe { outb(base + IER, uart.saved_ier.load(Relaxed)) };
+            return Err(e);
+        }
+        uart.base.store(base, Relaxed);
+        unsafe {
+            outb(base + MCR, MCR_OPEN);
+            outb(base + IER, IER_RECEIVE | IER_LINE_STATUS);
+        }
+        Ok(SerialPort { index, read_timeout: None, write_timeout: None })
+    }
+
+    fn uart(&self) -> &'static Uart {
+        &UARTS[self.index]
+    }
+
+    pub fn number(&self) -> u8 {
+        self.index as u8 + 1
+    }
+
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.write_timeout = timeout;
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    pub fn bytes_to_read(&self) -> usize {
+        self.uart().receive.len()
+    }
+
+    pub fn bytes_to_write(&self) -> usize {
+        self.uart().transmit.len()
+    }
+
+    pub fn clear_input(&mut self) {
+        self.uart().receive.clear();
+    }
+
+    /// Returns the first of the line errors seen since the last call.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        let errors = self.uart().errors.swap(0, Relaxed);
+        if errors & LSR_OVERRUN != 0 {
+            Some(io::const_error!(io::ErrorKind::Other, "received data was lost to an overrun"))
+        } else if errors & LSR_PARITY != 0 {
+            Some(io::const_error!(io::ErrorKind::InvalidData, "parity error"))
+        } else if errors & LSR_FRAMING != 0 {
+            Some(io::const_error!(io::ErrorKind::InvalidData, "framing error"))
+        } else if errors & LSR_BREAK != 0 {
+            Some(io::const_error!(io::ErrorKind::InvalidData, "break received"))
+        } else {
+            None
+        }
+    }
+
+    /// Waits for at least one byte, then reads what has arrived.
+    pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let ring = &self.uart().receive;
+        wait_until(self.read_timeout, || ring.len() > 0)?;
+        let mut len = 0;
+        while len < buf.len() {
+            let Some(byte) = ring.pop() else { break };
+            buf[len] = byte;
+            len += 1;
+        }
+        Ok(len)
+    }
+
+    /// Waits for room in the transmit buffer, then queues as much as fits.
+    pub fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let uart = self.uart();
+        wait_until(self.write_timeout, || uart.transmit.len() < RING_SIZE)?;
+        let mut len = 0;
+        while len < buf.len() && uart.transmit.push(buf[len]) {
+            len += 1;
+        }
+        // Some 8250s only interrupt when the transmit interrupt goes from
+        // off to on, even with the holding register already empty.
+        let base = uart.base.load(Relaxed);
+        unsafe {
+            outb(base + IER, IER_RECEIVE | IER_LINE_STATUS);
+            outb(base + IER, IER_RECEIVE | IER_LINE_STATUS | IER_TRANSMIT);
+        }
+        Ok(len)
+    }
+
+    /// Waits until everything queued has left the UART.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let uart = self.uart();
+        let base = uart.base.load(Relaxed);
+        wait_until(self.write_timeout, || {
+            uart.transmit.len() == 0 && unsafe { inb(base + LSR) } & LSR_TRANSMITTER_EMPTY != 0
+        })
+    }
+}
+
+impl Drop for SerialPort {
+    fn drop(&mut self) {
+        close(self.index);
+    }
+}
+
+/// Yields to other threads until `ready` returns true or `timeout` passes.
+fn wait_until(timeout: Option<Duration>, mut ready: impl FnMut() -> bool) -> io::Result<()> {
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add_duration(&timeout));
+    while !ready() {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(io::const_error!(io::ErrorKind::TimedOut, "timed out"));
+        }
+        Thread::yield_now();
+    }
+    Ok(())
+}
+
+fn hook_irq(irq: u8) -> io::Result<()> {
+    let state = &IRQS[usize::from(irq - FIRST_IRQ)];
+    if state.users.load(Relaxed) == 0 {
+        dpmi::init_interrupt_entry();
+        let vector = PIC_BASE_VECTOR + irq;
+        let (selector, offset) = dpmi::protected_mode_vector(vector);
+        state.old_selector.store(selector, Relaxed);
+        state.old_offset.store(offset, Relaxed);
+        let handler = if irq == 3 { __msdos6_irq3 } else { __msdos6_irq4 };
+        let handler = (dpmi::code_selector(), handler as usize as u32);
+        if !unsafe { dpmi::set_protected_mode_vector(vector, handler) } {
+            return Err(io::const_error!(
+                io::ErrorKind::Other,
+                "the DPMI host refused to set the IRQ vector",
+            ));
+        }
+        unsafe {
+            let mask = inb(PIC_MASK);
+            state.was_masked.store(mask & (1 << irq) != 0, Relaxed);
+            outb(PIC_MASK, mask & !(1 << irq));
+        }
+    }
+    state.users.fetch_add(1, Relaxed);
+    Ok(())
+}
+
+fn unhook_irq(irq: u8) {
+    let state = &IRQS[usize::from(irq - FIRST_IRQ)];
+    if state.users.fetch_sub(1, Relaxed) != 1 {
+        return;
+    }
+    unsafe {
+        if state.was_masked.load(Relaxed) {
+            outb(PIC_MASK, inb(PIC_MASK) | (1 << irq));
+        }
+        let old = (state.old_selector.load(Relaxed), state.old_offset.load(Relaxed));
+        dpmi::set_protected_mode_vector(PIC_BASE_VECTOR + irq, old);
+    }
+}
+
+/// Silences the UART of port `index` and gives its IRQ back.
+fn close(index: usize) {
+    let uart = &UARTS[index];
+    let base = uart.base.swap(0, Relaxed);
+    if base == 0 {
+        return;
+    }
+    unsafe {
+        outb(base + IER, uart.saved_ier.load(Relaxed));
+        outb(base + MCR, uart.saved_mcr.load(Relaxed));
+    }
+    unhook_irq(uart.irq.load(Relaxed));
+}
+
+/// Closes any ports still open, putting back the IRQ vectors and PIC mask.
+pub fn restore() {
+    for index in 0..PORTS {
+        close(index);
+    }
+}
@@ -1,5 +1,9 @@
+use super::dos;
 use crate::io;
-use crate::arch::asm;
+
+pub const STDIN_HANDLE: u16 = 0;
+pub const STDOUT_HANDLE: u16 = 1;
+pub const STDERR_HANDLE: u16 = 2;
 
 pub struct Stdin;
 pub struct Stdout;
@@ -12,8 +16,10 @@ pub const fn new() -> Stdin {
 }
 
 impl io::Read for Stdin {
-    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
-        Ok(0)
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // When stdin is CON, DOS reads a whole line with its usual editing
+        // keys before returning any of it.
+        dos::read(STDIN_HANDLE, buf)
     }
 }
 
@@ -25,15 +31,7 @@ pub const fn new() -> Stdout {
 
 impl io::Write for Stdout {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        for c in buf {
-            unsafe { asm!(
-                "mov ah, 0x02",
-                "int 0x21",
-                in("dl") *c as i8
-            ); }
-        }
-
-        Ok(buf.len())
+        dos::write(STDOUT_HANDLE, buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -49,15 +47,7 @@ pub const fn new() -> Stderr {
 
 impl io::Write for Stderr {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        for c in buf {
-            unsafe { asm!(
-                "mov ah, 0x02",
-                "int 0x21",
-                in("dl") *c as i8
-            ); }
-        }
-
-        Ok(buf.len())
+        dos::write(STDERR_HANDLE, buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -65,12 +55,14 @@ fn flush(&mut self) -> io::Result<()> {
     }
 }
 
-pub const STDIN_BUF_SIZE: usize = 0;
+// DOS never reads more than a line from CON, so this only needs to be large
+// enough for files and pipes.
+pub const STDIN_BUF_SIZE: usize = 512;
 
-pub fn is_ebadf(_err: &io::Error) -> bool {
-    true
+pub fn is_ebadf(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(dos::ERROR_INVALID_HANDLE.into())
 }
 
-pub fn panic_output() -> Option<Vec<u8>> {
-    None
+pub fn panic_output() -> Option<impl io::Write> {
+    Some(Stderr::new())
 }
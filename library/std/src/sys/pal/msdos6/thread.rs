@@ -1,36 +1,256 @@
-use super::unsupported;
+//! Cooperative green threads.
+//!
+//! DOS runs one program at a time and has no threads, so they are scheduled
+//! here. Every thread but the main one gets a stack on the heap, and a thread
+//! runs until it yields, which blocking calls like `sleep`, `join` and the
+//! futex-based locks do while they wait. There is no preemption: a thread
+//! that never yields keeps the others from running. Ready threads take turns
+//! in the order they last yielded.
+
+use super::dpmi::RealModeRegisters;
+use super::time::Instant;
+use super::{dos, unsupported};
+use crate::arch::global_asm;
+use crate::cell::UnsafeCell;
+use crate::collections::VecDeque;
 use crate::ffi::CStr;
 use crate::io;
 use crate::num::NonZero;
+use crate::sync::Arc;
+use crate::sync::atomic::AtomicBool;
+use crate::sync::atomic::Ordering::{Acquire, Relaxed, Release};
 use crate::time::Duration;
 
-pub struct Thread(!);
+pub struct Thread {
+    finished: Arc<AtomicBool>,
+}
 
 pub const DEFAULT_MIN_STACK_SIZE: usize = 64 * 1024;
 
+/// A thread, while it is not running.
+struct Task {
+    /// The stack pointer saved by `__msdos6_switch`.
+    sp: usize,
+    /// The stack, or `None` for the main thread, which keeps the one `_start`
+    /// set up. Only held so that it is freed with the task.
+    #[allow(dead_code)]
+    stack: Option<Vec<u8>>,
+    /// The values of thread-local keys, indexed by key.
+    tls: Vec<*mut u8>,
+}
+
+impl Task {
+    fn main() -> Box<Task> {
+        Box::new(Task { sp: 0, stack: None, tls: Vec::new() })
+    }
+}
+
+struct Scheduler {
+    /// The running thread. `None` until the main thread is first asked for.
+    current: Option<Box<Task>>,
+    /// Threads ready to run, in the order they will.
+    ready: VecDeque<Box<Task>>,
+    /// A thread that has exited, kept until we are off its stack.
+    exited: Option<Box<Task>>,
+}
+
+struct SchedulerCell(UnsafeCell<Scheduler>);
+
+// Only the running thread touches the scheduler, and interrupt handlers
+// never do.
+unsafe impl Sync for SchedulerCell {}
+
+static SCHEDULER: SchedulerCell = SchedulerCell(UnsafeCell::new(Scheduler {
+    current: None,
+    ready: VecDeque::new(),
+    exited: None,
+}));
+
+/// Runs `f` with the scheduler. `f` must not switch threads.
+fn with_scheduler<R>(f: impl FnOnce(&mut Scheduler) -> R) -> R {
+    f(unsafe { &mut *SCHEDULER.0.get() })
+}
+
+/// Runs `f` with the thread-local key values of the running thread.
+pub fn with_tls<R>(f: impl FnOnce(&mut Vec<*mut u8>) -> R) -> R {
+    with_scheduler(|scheduler| f(&mut scheduler.current.get_or_insert_with(Task::main).tls))
+}
+
+extern "C" {
+    /// Saves the callee-saved registers on the stack and the stack pointer in
+    /// `*from`, then switches to the stack at `to` and restores the registers
+    /// saved there.
+    fn __msdos6_switch(from: *mut usize, to: usize);
+    /// Where a new thread starts, with its `Start` in EBX.
+    fn __msdos6_thread_entry() -> !;
+}
+
+global_asm!(
+    ".pushsection .text.__msdos6_switch,\"ax\"",
+    ".globl __msdos6_switch",
+    "__msdos6_switch:",
+    "mov eax, dword ptr [esp + 4]",
+    "mov ecx, dword ptr [esp + 8]",
+    "push ebp",
+    "push ebx",
+    "push esi",
+    "push edi",
+    "mov dword ptr [eax], esp",
+    "mov esp, ecx",
+    "pop edi",
+    "pop esi",
+    "pop ebx",
+    "pop ebp",
+    "ret",
+    ".popsection",
+    ".pushsection .text.__msdos6_thread_entry,\"ax\"",
+    ".globl __msdos6_thread_entry",
+    "__msdos6_thread_entry:",
+    // Entered by the `ret` of `__msdos6_switch` with ESP at the top of the
+    // stack. Keep it 16-byte aligned at the call.
+    "sub esp, 12",
+    "push ebx",
+    "call {thread_start}",
+    "ud2",
+    ".popsection",
+    thread_start = sym thread_start,
+);
+
+/// What a new thread runs, and how it tells `join` it is done.
+struct Start {
+    main: Box<dyn FnOnce()>,
+    finished: Arc<AtomicBool>,
+}
+
+extern "C" fn thread_start(start: *mut Start) -> ! {
+    let Start { main, finished } = *unsafe { Box::from_raw(start) };
+    main();
+
+    // Run the destructors of thread-local values while they can still be
+    // reached, then let `join` return.
+    unsafe { crate::sys::thread_local::key::destroy_tls() };
+    finished.store(true, Release);
+
+    // The main thread never exits this way, so there is always a thread to
+    // switch to, even if it is only waiting for this one.
+    unsafe { switch(true) };
+    rtabort!("an exited thread was resumed");
+}
+
+/// Moves the running thread to the back of the ready queue, or drops it if it
+/// has `exited`, and switches to the first ready thread. Returns false without
+/// switching if no other thread is ready.
+unsafe fn switch(exited: bool) -> bool {
+    let switch = with_scheduler(|scheduler| {
+        // Whichever thread exited last is not running now, so its stack can go.
+        drop(scheduler.exited.take());
+
+        let next = scheduler.ready.pop_front()?;
+        let to = next.sp;
+        let mut prev = scheduler.current.replace(next).unwrap_or_else(Task::main);
+        // The task is boxed, so `sp` stays where it is when the box moves.
+        let from = &raw mut prev.sp;
+        if exited {
+            scheduler.exited = Some(prev);
+        } else {
+            scheduler.ready.push_back(prev);
+        }
+        Some((from, to))
+    });
+
+    let Some((from, to)) = switch else { return false };
+    unsafe { __msdos6_switch(from, to) };
+    true
+}
+
+/// The number of registers `__msdos6_switch` pops before it returns.
+const SAVED_REGISTERS: usize = 4;
+
 impl Thread {
     // unsafe: see thread::Builder::spawn_unchecked for safety requirements
-    pub unsafe fn new(_stack: usize, _p: Box<dyn FnOnce()>) -> io::Result<Thread> {
-        unsupported()
+    pub unsafe fn new(stack: usize, p: Box<dyn FnOnce()>) -> io::Result<Thread> {
+        let size = stack.max(DEFAULT_MIN_STACK_SIZE).next_multiple_of(16);
+        let mut stack = Vec::new();
+        stack.try_reserve_exact(size).map_err(|_| {
+            io::const_error!(io::ErrorKind::OutOfMemory, "not enough memory for the thread's stack")
+        })?;
+        stack.resize(size, 0);
+
+        let finished = Arc::new(AtomicBool::new(false));
+        let start = Box::into_raw(Box::new(Start { main: p, finished: finished.clone() }));
+
+        // Lay out the stack as `__msdos6_switch` leaves it: EDI, ESI, EBX and
+        // EBP, then the address to return to. EBX holds the `Start`.
+        let top = (stack.as_mut_ptr() as usize + size) & !15;
+        let frame = (top as *mut usize).wrapping_sub(SAVED_REGISTERS + 1);
+        let saved = [0, 0, start as usize, 0, __msdos6_thread_entry as usize];
+        unsafe { frame.copy_from_nonoverlapping(saved.as_ptr(), saved.len()) };
+
+        let task = Box::new(Task { sp: frame as usize, stack: Some(stack), tls: Vec::new() });
+        with_scheduler(|scheduler| scheduler.ready.push_back(task));
+        Ok(Thread { finished })
     }
 
+    /// Lets the other ready threads run, or the host if there are none.
     pub fn yield_now() {
-        // do nothing
+        if !unsafe { switch(false) } {
+            release_time_slice();
+        }
     }
 
     pub fn set_name(_name: &CStr) {
         // nope
     }
 
-    pub fn sleep(_dur: Duration) {
-        panic!("can't sleep");
+    pub fn sleep(dur: Duration) {
+        let start = Instant::now();
+
+        // INT 15h/86h: wait CX:DX microseconds. This stops every thread, so
+        // only use it while no other thread is ready. Not every BIOS has it,
+        // and some DOS boxes refuse it, so stop trying after the first failure.
+        let mut micros = dur.as_nanos().div_ceil(1000);
+        while micros > 0 && BIOS_WAIT.load(Relaxed) && !other_threads_ready() {
+            let chunk = micros.min(u32::MAX.into()) as u32;
+            let mut regs = RealModeRegisters {
+                eax: 0x8600,
+                ecx: chunk >> 16,
+                edx: chunk & 0xffff,
+                ..Default::default()
+            };
+            if dos::int86(0x15, &mut regs).is_err() || regs.flags & dos::CARRY_FLAG != 0 {
+                BIOS_WAIT.store(false, Relaxed);
+                break;
+            }
+            micros -= u128::from(chunk);
+        }
+
+        // Otherwise watch the clock, which counts BIOS ticks and PIT clocks.
+        while Instant::now().checked_sub_instant(&start).is_some_and(|slept| slept < dur) {
+            Thread::yield_now();
+        }
     }
 
     pub fn join(self) {
-        self.0
+        while !self.finished.load(Acquire) {
+            Thread::yield_now();
+        }
     }
 }
 
+fn other_threads_ready() -> bool {
+    with_scheduler(|scheduler| !scheduler.ready.is_empty())
+}
+
+static BIOS_WAIT: AtomicBool = AtomicBool::new(true);
+
+/// INT 2Fh/1680h: tells a multitasking host such as Windows or OS/2 that the
+/// program is idle, so it can give the rest of the time slice to someone
+/// else. Plain DOS ignores it.
+fn release_time_slice() {
+    let mut regs = RealModeRegisters { eax: 0x1680, ..Default::default() };
+    let _ = dos::int86(0x2f, &mut regs);
+}
+
 pub fn available_parallelism() -> io::Result<NonZero<usize>> {
     unsupported()
 }
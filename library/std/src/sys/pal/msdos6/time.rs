@@ -1,3 +1,7 @@
+use super::dpmi::{self, RealModeRegisters};
+use super::{dos, port};
+use crate::sync::atomic::Ordering::Relaxed;
+use crate::sync::atomic::{AtomicU32, AtomicU64};
 use crate::time::Duration;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
@@ -10,7 +14,7 @@
 
 impl Instant {
     pub fn now() -> Instant {
-        panic!("time not implemented on this platform")
+        Instant(Duration::from_nanos(monotonic_nanos()))
     }
 
     pub fn checked_sub_instant(&self, other: &Instant) -> Option<Duration> {
@@ -27,8 +31,43 @@ pub fn checked_sub_duration(&self, other: &Duration) -> Option<Instant> {
 }
 
 impl SystemTime {
+    /// Reads the DOS clock, which keeps local time; like file timestamps, it
+    /// is taken to be UTC.
     pub fn now() -> SystemTime {
-        panic!("time not implemented on this platform")
+        // INT 21h/2Ah: get the date. INT 21h/2Ch: get the time. The date is
+        // read again in case midnight passed in between. They only fail if the
+        // DPMI host can't reach DOS at all, which nothing can recover from.
+        let get_date = || {
+            let mut regs = RealModeRegisters { eax: 0x2a00, ..Default::default() };
+            if dos::int21(&mut regs).is_err() {
+                rtabort!("failed to read the DOS date");
+            }
+            (regs.ecx as u16, (regs.edx >> 8) as u8, regs.edx as u8)
+        };
+        let get_time = || {
+            let mut regs = RealModeRegisters { eax: 0x2c00, ..Default::default() };
+            if dos::int21(&mut regs).is_err() {
+                rtabort!("failed to read the DOS time");
+            }
+            ((regs.ecx >> 8) as u8, regs.ecx as u8, (regs.edx >> 8) as u8, regs.edx as u8)
+        };
+        let mut date = get_date();
+        let mut time = get_time();
+        loop {
+            let again = get_date();
+            if again == date {
+                break;
+            }
+            date = again;
+            time = get_time();
+        }
+
+        let (year, month, day) = date;
+        let (hour, minute, second, hundredths) = time;
+        let days = days_from_civil(year.into(), month.into(), day.into());
+        let secs =
+            days * 86400 + i64::from(hour) * 3600 + i64::from(minute) * 60 + i64::from(second);
+        SystemTime(Duration::new(secs as u64, u32::from(hundredths) * 10_000_000))
     }
 
     pub fn sub_time(&self, other: &SystemTime) -> Result<Duration, Duration> {
@@ -43,3 +82,123 @@ pub fn checked_sub_duration(&self, other: &Duration) -> Option<SystemTime> {
         Some(SystemTime(self.0.checked_sub(*other)?))
     }
 }
+
+impl SystemTime {
+    /// Converts the packed date and time DOS keeps for files.
+    ///
+    /// DOS has no notion of time zones, so the local time it stores is taken to be UTC.
+    pub(crate) fn from_dos(date: u16, time: u16) -> Option<SystemTime> {
+        let year = 1980 + i64::from(date >> 9);
+        let month = (date >> 5) & 0xf;
+        let day = date & 0x1f;
+        let (hour, minute, second) = (time >> 11, (time >> 5) & 0x3f, (time & 0x1f) * 2);
+        if !(1..=12).contains(&month) || day == 0 || hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        let days = days_from_civil(year, month.into(), day.into());
+        let secs =
+            days * 86400 + i64::from(hour) * 3600 + i64::from(minute) * 60 + i64::from(second);
+        Some(SystemTime(Duration::from_secs(secs as u64)))
+    }
+
+    /// Packs the time into a DOS date and time, or returns `None` if DOS
+    /// cannot represent it.
+    pub(crate) fn to_dos(&self) -> Option<(u16, u16)> {
+        let secs = self.0.as_secs();
+        let (year, month, day) = civil_from_days((secs / 86400) as i64);
+        if !(1980..=2107).contains(&year) {
+            return None;
+        }
+        let secs = secs % 86400;
+        let (hour, minute, second) = (secs / 3600, secs / 60 % 60, secs % 60);
+        let date = ((year - 1980) << 9) as u16 | (month << 5) as u16 | day as u16;
+        let time = (hour << 11) as u16 | (minute << 5) as u16 | (second / 2) as u16;
+        Some((date, time))
+    }
+}
+
+/// The BIOS timer tick count, incremented by IRQ 0 about 18.2 times a second.
+const BIOS_TICK_COUNT: u32 = 0x46c;
+/// The BIOS resets its tick count to 0 when it reaches this, at midnight.
+const TICKS_PER_DAY: u64 = 0x1800b0;
+/// The input frequency of the 8254 PIT.
+const PIT_FREQUENCY: u64 = 1_193_182;
+const PIT_CHANNEL_0: u16 = 0x40;
+const PIT_COMMAND: u16 = 0x43;
+/// Read-back command latching the status and count of channel 0.
+const PIT_READ_BACK_CHANNEL_0: u8 = 0xc2;
+/// Set in the read-back status if the channel's output is high.
+const PIT_STATUS_OUTPUT: u8 = 0x80;
+
+// Days passed since the first call to `monotonic_nanos`, found by noticing the
+// tick count going backwards. As long as an `Instant` is taken at least once
+// a day, no midnight is missed.
+static DAYS: AtomicU32 = AtomicU32::new(0);
+static LAST_TICKS: AtomicU32 = AtomicU32::new(0);
+// The PIT can wrap around a moment before IRQ 0 updates the tick count, so
+// readings are kept from ever going backwards.
+static LAST_NANOS: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn bios_ticks() -> u32 {
+    unsafe { dpmi::linear_to_ptr(BIOS_TICK_COUNT).cast::<u32>().read_volatile() }
+}
+
+/// How far channel 0 of the PIT is into the current tick, in PIT input
+/// clocks. The BIOS runs it in mode 3, where the counter goes down twice per
+/// tick in steps of 2, once with the output high and once with it low.
+pub(crate) fn pit_elapsed() -> u64 {
+    let (status, count) = unsafe {
+        port::outb(PIT_COMMAND, PIT_READ_BACK_CHANNEL_0);
+        let status = port::inb(PIT_CHANNEL_0);
+        let low = port::inb(PIT_CHANNEL_0);
+        let high = port::inb(PIT_CHANNEL_0);
+        (status, u16::from_le_bytes([low, high]))
+    };
+    // A count of 0 stands for 65536.
+    let count = if count == 0 { 0x10000 } else { u64::from(count) };
+    let half = (0x10000 - count) / 2;
+    if status & PIT_STATUS_OUTPUT != 0 { half } else { 0x8000 + half }
+}
+
+fn monotonic_nanos() -> u64 {
+    let (ticks, elapsed) = loop {
+        let ticks = bios_ticks();
+        let elapsed = pit_elapsed();
+        if bios_ticks() == ticks {
+            break (ticks, elapsed);
+        }
+    };
+
+    if ticks < LAST_TICKS.swap(ticks, Relaxed) {
+        DAYS.fetch_add(1, Relaxed);
+    }
+    let ticks = u64::from(DAYS.load(Relaxed)) * TICKS_PER_DAY + u64::from(ticks);
+    let clocks = u128::from(ticks * 0x10000 + elapsed.min(0xffff));
+    let nanos = (clocks * 1_000_000_000 / u128::from(PIT_FREQUENCY)) as u64;
+    LAST_NANOS.fetch_max(nanos, Relaxed).max(nanos)
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -5,6 +5,9 @@
     } else if #[cfg(all(target_vendor = "fortanix", target_env = "sgx"))] {
         mod sgx;
         pub use sgx::*;
+    } else if #[cfg(target_os = "msdos6")] {
+        mod msdos6;
+        pub use msdos6::*;
     } else if #[cfg(any(
         target_os = "uefi",
         target_os = "solid_asp3",
@@ -0,0 +1,71 @@
+use crate::ffi::OsStr;
+use crate::io;
+use crate::path::{Component, Path, PathBuf, Prefix};
+use crate::sys::os;
+
+#[cfg(test)]
+mod tests;
+
+pub const MAIN_SEP_STR: &str = "\\";
+pub const MAIN_SEP: char = '\\';
+
+/// DOS accepts `/` as well as `\`, even though its own tools print `\`.
+#[inline]
+pub fn is_sep_byte(b: u8) -> bool {
+    b == b'/' || b == b'\\'
+}
+
+#[inline]
+pub fn is_verbatim_sep(b: u8) -> bool {
+    b == b'\\'
+}
+
+/// Parses a drive prefix like `C:`, the only kind DOS has.
+///
+/// The drive letter is returned in uppercase, so that `c:` and `C:` compare
+/// equal as DOS treats them.
+pub fn parse_prefix(path: &OsStr) -> Option<Prefix<'_>> {
+    match path.as_encoded_bytes() {
+        [drive, b':', ..] if drive.is_ascii_alphabetic() => {
+            Some(Prefix::Disk(drive.to_ascii_uppercase()))
+        }
+        _ => None,
+    }
+}
+
+/// Makes `path` absolute the way DOS would resolve it.
+///
+/// A path without a drive refers to the current drive, and a path without a
+/// root, including a drive-relative one like `C:FOO`, to the current directory
+/// of its drive. DOS has no links, so `.` and `..` are resolved lexically.
+pub(crate) fn absolute(path: &Path) -> io::Result<PathBuf> {
+    let mut components = path.components().peekable();
+    let drive = match components.peek() {
+        Some(Component::Prefix(prefix)) => {
+            let Prefix::Disk(drive) = prefix.kind() else { unreachable!() };
+            components.next();
+            drive
+        }
+        _ => os::current_drive()?,
+    };
+    let mut normalized = if components.peek() == Some(&Component::RootDir) {
+        PathBuf::from(format!("{}:\\", char::from(drive)))
+    } else {
+        os::drive_current_dir(drive)?
+    };
+    for component in components {
+        match component {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Normal(name) => normalized.push(name),
+        }
+    }
+
+    // Keep a trailing separator, which says the path names a directory.
+    if path.as_os_str().as_encoded_bytes().last().is_some_and(|&b| is_sep_byte(b)) {
+        normalized.push("");
+    }
+    Ok(normalized)
+}
@@ -0,0 +1,65 @@
+use super::*;
+
+fn prefix(path: &str) -> Option<Prefix<'_>> {
+    parse_prefix(OsStr::new(path))
+}
+
+#[test]
+fn drive_prefix() {
+    assert_eq!(prefix("C:\\AUTOEXEC.BAT"), Some(Prefix::Disk(b'C')));
+    assert_eq!(prefix("C:/DOS"), Some(Prefix::Disk(b'C')));
+    assert_eq!(prefix("C:"), Some(Prefix::Disk(b'C')));
+    // Drive-relative, and in lowercase.
+    assert_eq!(prefix("c:foo"), Some(Prefix::Disk(b'C')));
+}
+
+#[test]
+fn no_prefix() {
+    for path in ["", "C", "\\DOS", "DOS\\X.EXE", "1:", ":", "::", "\u{e9}:"] {
+        assert_eq!(prefix(path), None, "{path:?}");
+    }
+    // DOS has no UNC paths, nor the verbatim ones of Windows.
+    assert_eq!(prefix("\\\\SERVER\\SHARE"), None);
+    assert_eq!(prefix("\\\\?\\C:\\"), None);
+}
+
+#[test]
+fn separators() {
+    assert!(is_sep_byte(b'\\'));
+    assert!(is_sep_byte(b'/'));
+    assert!(!is_sep_byte(b':'));
+    assert!(is_verbatim_sep(b'\\'));
+    assert!(!is_verbatim_sep(b'/'));
+}
+
+#[test]
+fn components() {
+    let path = Path::new("c:/DOS\\X.EXE");
+    let mut components = path.components();
+    let Some(Component::Prefix(drive)) = components.next() else { panic!("no prefix") };
+    assert_eq!(drive.kind(), Prefix::Disk(b'C'));
+    assert_eq!(drive.as_os_str(), "c:");
+    assert!(components.eq([
+        Component::RootDir,
+        Component::Normal("DOS".as_ref()),
+        Component::Normal("X.EXE".as_ref()),
+    ]));
+    assert_eq!(path, Path::new("C:\\DOS/X.EXE"));
+
+    assert_eq!(Path::new("C:FOO").components().nth(1), Some(Component::Normal("FOO".as_ref())));
+    assert_eq!(Path::new("C:\\DOS\\").file_name(), Some("DOS".as_ref()));
+    assert_eq!(Path::new("C:\\").parent(), None);
+}
+
+#[test]
+fn absolute_paths_need_a_drive_and_a_root() {
+    assert!(Path::new("C:\\AUTOEXEC.BAT").is_absolute());
+    assert!(Path::new("C:/").is_absolute());
+    // Relative to the current directory of drive C.
+    assert!(!Path::new("C:FOO").is_absolute());
+    assert!(!Path::new("C:").has_root());
+    // Relative to the current drive.
+    assert!(!Path::new("\\DOS").is_absolute());
+    assert!(Path::new("\\DOS").has_root());
+    assert!(!Path::new("\\\\SERVER\\SHARE").is_absolute());
+}
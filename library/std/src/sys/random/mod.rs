@@ -48,6 +48,9 @@
     ))] {
         mod unix_legacy;
         pub use unix_legacy::fill_bytes;
+    } else if #[cfg(target_os = "msdos6")] {
+        mod msdos6;
+        pub use msdos6::fill_bytes;
     } else if #[cfg(target_os = "redox")] {
         mod redox;
         pub use redox::fill_bytes;
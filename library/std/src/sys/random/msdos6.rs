@@ -0,0 +1,78 @@
+//! DOS has no entropy source, so this collects what unpredictability the
+//! machine has: how far the PIT and, where the CPU has one, the time stamp
+//! counter have moved on between samples, the BIOS tick count and where DOS
+//! loaded us. These are hashed with SipHash, together with a counter so that
+//! no two calls return the same bytes.
+//!
+//! The BIOS keyboard buffer is hashed in as well, but it only holds the last
+//! few keys typed, not when they were pressed, so it adds next to nothing.
+//!
+//! That is enough to keep `HashMap` keys from being guessed, but it is not
+//! cryptographically secure: on emulators and slow machines, the samples
+//! hold only a few bits of entropy, and someone with access to the machine
+//! can see most of them.
+
+use crate::arch::x86::{__cpuid, _rdtsc, has_cpuid};
+use crate::hash::{DefaultHasher, Hasher};
+use crate::sync::atomic::AtomicU64;
+use crate::sync::atomic::Ordering::Relaxed;
+use crate::sys::pal::{dpmi, psp, time};
+
+/// The BIOS keyboard shift flags, buffer head and tail, and the 16-key buffer
+/// itself, which still holds the last keys typed before we were started.
+const BIOS_KEYBOARD_STATE: u32 = 0x417;
+const BIOS_KEYBOARD_STATE_LEN: usize = 0x43e - 0x417;
+/// Timing samples taken per call.
+const SAMPLES: usize = 32;
+
+// Output of the previous call, fed into the next one.
+static STATE: AtomicU64 = AtomicU64::new(0);
+
+pub fn fill_bytes(bytes: &mut [u8]) {
+    let mut hasher = DefaultHasher::new();
+    hasher.write_u64(STATE.load(Relaxed));
+    collect(&mut hasher);
+
+    for (i, chunk) in bytes.chunks_mut(8).enumerate() {
+        let mut block = hasher.clone();
+        block.write_usize(i);
+        let value = block.finish().to_ne_bytes();
+        chunk.copy_from_slice(&value[..chunk.len()]);
+    }
+    hasher.write_u8(0xff);
+    STATE.store(hasher.finish(), Relaxed);
+}
+
+fn collect(hasher: &mut DefaultHasher) {
+    // Where we were loaded depends on the drivers and TSRs loaded before us.
+    let stack = 0u8;
+    hasher.write_u32(dpmi::ptr_to_linear(crate::ptr::null()));
+    hasher.write_u16(psp::segment());
+    hasher.write_usize(&stack as *const u8 as usize);
+    let keyboard = dpmi::linear_to_ptr(BIOS_KEYBOARD_STATE);
+    for i in 0..BIOS_KEYBOARD_STATE_LEN {
+        hasher.write_u8(unsafe { keyboard.add(i).read_volatile() });
+    }
+
+    let tsc = has_tsc();
+    for _ in 0..SAMPLES {
+        hasher.write_u32(time::bios_ticks());
+        let elapsed = time::pit_elapsed();
+        hasher.write_u64(elapsed);
+        if tsc {
+            hasher.write_u64(unsafe { _rdtsc() });
+        }
+        // Wait a varying while, so that interrupts, DMA and cache misses get
+        // a chance to shift the next sample.
+        for _ in 0..elapsed % 64 {
+            crate::hint::spin_loop();
+        }
+    }
+}
+
+/// Whether CPUID is there and reports a time stamp counter, which the 386
+/// and most 486s lack.
+fn has_tsc() -> bool {
+    const CPUID_EDX_TSC: u32 = 1 << 4;
+    has_cpuid() && unsafe { __cpuid(0).eax } >= 1 && unsafe { __cpuid(1).edx } & CPUID_EDX_TSC != 0
+}
@@ -8,6 +8,7 @@
         target_os = "dragonfly",
         all(target_family = "wasm", target_feature = "atomics"),
         target_os = "hermit",
+        target_os = "msdos6",
     ))] {
         mod futex;
         pub use futex::Mutex;
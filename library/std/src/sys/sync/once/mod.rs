@@ -18,6 +18,7 @@
         target_os = "dragonfly",
         target_os = "fuchsia",
         target_os = "hermit",
+        target_os = "msdos6",
     ))] {
         mod futex;
         pub use futex::{Once, OnceState};
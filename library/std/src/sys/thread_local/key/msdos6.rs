@@ -0,0 +1,98 @@
+//! Thread Local Storage for the green threads of `sys::pal::msdos6`.
+//!
+//! The scheduler keeps a table of values for every thread, which a key
+//! indexes. Keys are shared between all threads and never reused.
+//!
+//! As on Xous, destructors are kept in a list of our own, and `destroy_tls`
+//! runs them when a thread finishes. Unregistering a destructor is not
+//! supported, since keys are never deallocated anyway.
+
+use crate::mem::ManuallyDrop;
+use crate::ptr;
+use crate::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use crate::sync::atomic::{AtomicPtr, AtomicUsize};
+use crate::sys::thread::with_tls;
+
+pub type Key = usize;
+pub type Dtor = unsafe extern "C" fn(*mut u8);
+
+/// TLS keys start at `1`, as `LazyKey` takes `0` to mean no key.
+static NEXT_KEY: AtomicUsize = AtomicUsize::new(1);
+
+static DTORS: AtomicPtr<Node> = AtomicPtr::new(ptr::null_mut());
+
+#[inline]
+pub fn create(dtor: Option<Dtor>) -> Key {
+    let key = NEXT_KEY.fetch_add(1, Relaxed);
+    if let Some(f) = dtor {
+        unsafe { register_dtor(key, f) };
+    }
+    key
+}
+
+#[inline]
+pub unsafe fn set(key: Key, value: *mut u8) {
+    with_tls(|table| {
+        if table.len() <= key {
+            table.resize(key + 1, ptr::null_mut());
+        }
+        table[key] = value;
+    })
+}
+
+#[inline]
+pub unsafe fn get(key: Key) -> *mut u8 {
+    with_tls(|table| table.get(key).copied().unwrap_or(ptr::null_mut()))
+}
+
+#[inline]
+pub unsafe fn destroy(_key: Key) {
+    // Keys are leaked, like on Xous.
+}
+
+struct Node {
+    dtor: Dtor,
+    key: Key,
+    next: *mut Node,
+}
+
+unsafe fn register_dtor(key: Key, dtor: Dtor) {
+    let mut node = ManuallyDrop::new(Box::new(Node { key, dtor, next: ptr::null_mut() }));
+
+    let mut head = DTORS.load(Acquire);
+    loop {
+        node.next = head;
+        match DTORS.compare_exchange(head, &mut **node, Release, Acquire) {
+            Ok(_) => return, // nothing to drop, we successfully added the node to the list
+            Err(cur) => head = cur,
+        }
+    }
+}
+
+/// Runs the destructors of the running thread's values and frees its table.
+pub unsafe fn destroy_tls() {
+    // Like on Windows and Xous, go over the list a few times, as destructors
+    // may set values of their own.
+    for _ in 0..5 {
+        let mut any_run = false;
+        let mut cur = DTORS.load(Acquire);
+        while !cur.is_null() {
+            let ptr = unsafe { get((*cur).key) };
+
+            if !ptr.is_null() {
+                unsafe { set((*cur).key, ptr::null_mut()) };
+                unsafe { ((*cur).dtor)(ptr as *mut _) };
+                any_run = true;
+            }
+
+            unsafe { cur = (*cur).next };
+        }
+        if !any_run {
+            break;
+        }
+    }
+
+    crate::rt::thread_cleanup();
+
+    with_tls(|table| *table = Vec::new());
+}
@@ -28,7 +28,6 @@
         all(target_family = "wasm", not(target_feature = "atomics")),
         target_os = "uefi",
         target_os = "zkvm",
-        target_os = "msdos6",
     ))] {
         mod statik;
         pub use statik::{EagerStorage, LazyStorage, thread_local_inner};
@@ -109,6 +108,7 @@ pub(crate) fn enable() {
         } else if #[cfg(any(
             target_os = "hermit",
             target_os = "xous",
+            target_os = "msdos6",
         ))] {
             // `std` is the only runtime, so it just calls the destructor functions
             // itself when the time comes.
@@ -171,6 +171,13 @@ pub(crate) mod key {
             pub(crate) use xous::destroy_tls;
             pub(super) use xous::{Key, get, set};
             use xous::{create, destroy};
+        } else if #[cfg(target_os = "msdos6")] {
+            mod msdos6;
+            mod racy;
+            pub(super) use racy::LazyKey;
+            pub(crate) use msdos6::destroy_tls;
+            pub(super) use msdos6::{Key, get, set};
+            use msdos6::{create, destroy};
         }
     }
 }
@@ -770,16 +770,29 @@ fn make_run(run: RunConfig<'_>) {
 
     /// Builds and prepare startup objects like rsbegin.o and rsend.o
     ///
-    /// These are primarily used on Windows right now for linking executables/dlls.
+    /// These are primarily used on Windows right now for linking executables/dlls,
+    /// and as the C runtime startup objects of msdos6 executables.
     /// They don't require any library support as they're just plain old object
     /// files, so we just use the nightly snapshot compiler to always build them (as
     /// no other compilers are guaranteed to be available).
     fn run(self, builder: &Builder<'_>) -> Vec<(PathBuf, DependencyType)> {
         let for_compiler = self.compiler;
         let target = self.target;
-        if !target.is_windows_gnu() {
+        let files: &[&str] = if target.is_windows_gnu() {
+            &["rsbegin", "rsend"]
+        } else if target.contains("msdos6") {
+            &["crt0_msdos6", "crtend_msdos6"]
+        } else {
             return vec![];
-        }
+        };
+
+        // The snapshot compiler doesn't know about msdos6 yet (see `STAGE0_MISSING_TARGETS`),
+        // so hand it the target spec from the root of the source tree instead.
+        let target_arg = if target.contains("msdos6") && target.filepath().is_none() {
+            builder.src.join(format!("{}.json", target.triple)).into_os_string()
+        } else {
+            target.rustc_target_arg().into()
+        };
 
         let mut target_deps = vec![];
 
@@ -788,7 +801,7 @@ fn run(self, builder: &Builder<'_>) -> Vec<(PathBuf, DependencyType)> {
         let sysroot_dir = &builder.sysroot_target_libdir(for_compiler, target);
         t!(fs::create_dir_all(dst_dir));
 
-        for file in &["rsbegin", "rsend"] {
+        for file in files {
             let src_file = &src_dir.join(file.to_string() + ".rs");
             let dst_file = &dst_dir.join(file.to_string() + ".o");
             if !up_to_date(src_file, dst_file) {
@@ -799,7 +812,7 @@ fn run(self, builder: &Builder<'_>) -> Vec<(PathBuf, DependencyType)> {
                     cmd.arg("--cfg").arg("bootstrap");
                 }
                 cmd.arg("--target")
-                    .arg(target.rustc_target_arg())
+                    .arg(&target_arg)
                     .arg("--emit=obj")
                     .arg("-o")
                     .arg(dst_file)